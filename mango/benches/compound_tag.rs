@@ -0,0 +1,488 @@
+//! Loads NBT shaped like a large level.dat and a full chunk into `CompoundTag`, against the
+//! previous `Arc<DashMap>` compound layout pinned below as a baseline.
+//!
+//! The crate has no library target, so its modules are included by path and the benchmark runs
+//! the same reader the server uses. The baseline is the reader as it was before compounds became
+//! ordered, with only the map type differing.
+//!
+//! Run with `cargo bench -p mango --bench compound_tag`.
+
+// The included modules are already built and linted as part of the binary
+#![allow(unused, clippy::all)]
+
+#[path = "../src/bootstrap.rs"]
+mod bootstrap;
+#[path = "../src/codec/mod.rs"]
+mod codec;
+#[path = "../src/commands/mod.rs"]
+mod commands;
+#[path = "../src/core/mod.rs"]
+mod core;
+#[path = "../src/dedicated/mod.rs"]
+mod dedicated;
+#[path = "../src/detected_version.rs"]
+mod detected_version;
+#[path = "../src/file_util.rs"]
+mod file_util;
+#[path = "../src/minecraft_server.rs"]
+mod minecraft_server;
+#[path = "../src/nbt/mod.rs"]
+mod nbt;
+#[path = "../src/network/mod.rs"]
+mod network;
+#[path = "../src/packs/mod.rs"]
+mod packs;
+#[path = "../src/player_advancements.rs"]
+mod player_advancements;
+#[path = "../src/registry_layer.rs"]
+mod registry_layer;
+#[path = "../src/resources/mod.rs"]
+mod resources;
+#[path = "../src/shared_constants.rs"]
+mod shared_constants;
+#[path = "../src/sounds/mod.rs"]
+mod sounds;
+#[path = "../src/stats/mod.rs"]
+mod stats;
+#[path = "../src/util/mod.rs"]
+mod util;
+#[path = "../src/world/mod.rs"]
+mod world;
+#[path = "../src/world_loader.rs"]
+mod world_loader;
+
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::list_tag::ListTag;
+use crate::nbt::nbt_accounter::NbtAccounter;
+use crate::nbt::nbt_io;
+use crate::nbt::tag::Tag;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::future::Future;
+use std::task::{Context, Poll, Waker};
+
+/// Drives a future that never waits, such as reading from or writing to an in-memory buffer
+fn complete<T>(future: impl Future<Output = T>) -> T {
+    let mut future = std::pin::pin!(future);
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        Poll::Ready(res) => res,
+        Poll::Pending => panic!("In-memory NBT IO did not complete"),
+    }
+}
+
+/// The compound layout before it became ordered and copy-on-write, loaded the same way
+mod dashmap_baseline {
+    use crate::nbt::compound_tag::CompoundTag;
+    use crate::nbt::nbt_accounter::NbtAccounter;
+    use crate::nbt::DataInput;
+    use anyhow::{anyhow, Result};
+    use dashmap::DashMap;
+    use std::sync::Arc;
+    use tokio::io::AsyncReadExt;
+
+    #[derive(Clone)]
+    pub enum Tag {
+        EndTag,
+        ByteTag(u8),
+        ShortTag(i16),
+        IntTag(i32),
+        LongTag(i64),
+        FloatTag(f32),
+        DoubleTag(f64),
+        ByteArrayTag(Vec<u8>),
+        StringTag(String),
+        ListTag(Arc<Vec<Tag>>),
+        CompoundTag(Arc<DashMap<String, Tag>>),
+        IntArrayTag(Vec<i32>),
+        LongArrayTag(Vec<i64>),
+    }
+
+    pub async fn read(reader: &mut impl DataInput, nbt_accounter: &NbtAccounter) -> Result<Tag> {
+        let tag_type = reader.read_u8().await?;
+        CompoundTag::read_string(reader, nbt_accounter).await?;
+        load(tag_type, reader, nbt_accounter).await
+    }
+
+    async fn load(
+        tag_type: u8,
+        reader: &mut impl DataInput,
+        nbt_accounter: &NbtAccounter,
+    ) -> Result<Tag> {
+        match tag_type {
+            0 => {
+                nbt_accounter.account_bytes(8)?;
+                Ok(Tag::EndTag)
+            }
+            1 => {
+                nbt_accounter.account_bytes(9)?;
+                Ok(Tag::ByteTag(reader.read_u8().await?))
+            }
+            2 => {
+                nbt_accounter.account_bytes(10)?;
+                Ok(Tag::ShortTag(reader.read_i16().await?))
+            }
+            3 => {
+                nbt_accounter.account_bytes(12)?;
+                Ok(Tag::IntTag(reader.read_i32().await?))
+            }
+            4 => {
+                nbt_accounter.account_bytes(16)?;
+                Ok(Tag::LongTag(reader.read_i64().await?))
+            }
+            5 => {
+                nbt_accounter.account_bytes(12)?;
+                Ok(Tag::FloatTag(reader.read_f32().await?))
+            }
+            6 => {
+                nbt_accounter.account_bytes(16)?;
+                Ok(Tag::DoubleTag(reader.read_f64().await?))
+            }
+            7 => {
+                nbt_accounter.account_bytes(24)?;
+                let len = reader.read_i32().await?;
+                nbt_accounter.account_bytes(len as u64)?;
+                let mut buf = vec![0u8; len as usize];
+                reader.read_exact(&mut buf).await?;
+                Ok(Tag::ByteArrayTag(buf))
+            }
+            8 => {
+                nbt_accounter.account_bytes(36)?;
+                Ok(Tag::StringTag(
+                    CompoundTag::read_string(reader, nbt_accounter).await?,
+                ))
+            }
+            9 => {
+                nbt_accounter.push_depth()?;
+                nbt_accounter.account_bytes(37)?;
+                let element_type = reader.read_u8().await?;
+                let len = reader.read_i32().await?;
+                nbt_accounter.account_bytes(4 * len as u64)?;
+                let mut list = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    list.push(Box::pin(load(element_type, reader, nbt_accounter)).await?);
+                }
+                nbt_accounter.pop_depth()?;
+                Ok(Tag::ListTag(Arc::new(list)))
+            }
+            10 => {
+                nbt_accounter.push_depth()?;
+                nbt_accounter.account_bytes(48)?;
+                let map = DashMap::new();
+                loop {
+                    let tag_type = reader.read_u8().await?;
+                    if tag_type == 0 {
+                        break;
+                    }
+                    let name = CompoundTag::read_string(reader, nbt_accounter).await?;
+                    let tag = Box::pin(load(tag_type, reader, nbt_accounter)).await?;
+                    if map.insert(name, tag).is_none() {
+                        nbt_accounter.account_bytes(36)?;
+                    }
+                }
+                nbt_accounter.pop_depth()?;
+                Ok(Tag::CompoundTag(Arc::new(map)))
+            }
+            11 => {
+                nbt_accounter.account_bytes(24)?;
+                let len = reader.read_i32().await?;
+                nbt_accounter.account_bytes(4 * len as u64)?;
+                let mut buf = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    buf.push(reader.read_i32().await?);
+                }
+                Ok(Tag::IntArrayTag(buf))
+            }
+            12 => {
+                nbt_accounter.account_bytes(24)?;
+                let len = reader.read_i32().await?;
+                nbt_accounter.account_bytes(8 * len as u64)?;
+                let mut buf = Vec::with_capacity(len as usize);
+                for _ in 0..len {
+                    buf.push(reader.read_i64().await?);
+                }
+                Ok(Tag::LongArrayTag(buf))
+            }
+            _ => Err(anyhow!("Invalid tag id {}", tag_type)),
+        }
+    }
+}
+
+fn compound<K: Into<String>>(entries: impl IntoIterator<Item = (K, Tag)>) -> Tag {
+    Tag::CompoundTag(
+        entries
+            .into_iter()
+            .map(|(key, tag)| (key.into(), tag))
+            .collect(),
+    )
+}
+
+fn list(tags: impl IntoIterator<Item = Tag>) -> Tag {
+    Tag::ListTag(ListTag::new(tags.into_iter().collect()))
+}
+
+fn string(value: impl Into<String>) -> Tag {
+    Tag::StringTag(value.into())
+}
+
+fn item(slot: u8, id: &str) -> Tag {
+    compound([
+        ("Slot", Tag::ByteTag(slot)),
+        ("id", string(id)),
+        ("count", Tag::IntTag(64)),
+        (
+            "components",
+            compound([
+                ("minecraft:damage", Tag::IntTag(slot as i32)),
+                ("minecraft:repair_cost", Tag::IntTag(1)),
+            ]),
+        ),
+    ])
+}
+
+fn write(tag: Tag) -> Vec<u8> {
+    let Tag::CompoundTag(tag) = tag else {
+        unreachable!()
+    };
+    let mut out = Vec::new();
+    complete(nbt_io::write(&tag, &mut out)).unwrap();
+    out
+}
+
+/// A level.dat with a player carrying a full inventory, all game rules and a few hundred
+/// scheduled events, similar to a long running server
+fn level_dat() -> Vec<u8> {
+    let game_rules = (0..60).map(|i| (format!("rule{}", i), string("true")));
+    let dimension = |name: &str| {
+        compound([
+            ("type", string(format!("minecraft:{}", name))),
+            (
+                "generator",
+                compound([
+                    ("type", string("minecraft:noise")),
+                    ("settings", string(format!("minecraft:{}", name))),
+                    (
+                        "biome_source",
+                        compound([("type", string("minecraft:multi_noise"))]),
+                    ),
+                ]),
+            ),
+        ])
+    };
+    let scheduled = (0..300).map(|i| {
+        compound([
+            ("Name", string(format!("event{}", i))),
+            ("TriggerTime", Tag::LongTag(i * 20)),
+            (
+                "Callback",
+                compound([
+                    ("Type", string("minecraft:function")),
+                    ("Name", string(format!("pack:tick/{}", i))),
+                ]),
+            ),
+        ])
+    });
+    let data = compound([
+        ("DataVersion", Tag::IntTag(4189)),
+        ("LevelName", string("world")),
+        ("Time", Tag::LongTag(123456789)),
+        ("DayTime", Tag::LongTag(6000)),
+        ("SpawnX", Tag::IntTag(0)),
+        ("SpawnY", Tag::IntTag(64)),
+        ("SpawnZ", Tag::IntTag(0)),
+        ("SpawnAngle", Tag::FloatTag(0.0)),
+        ("BorderSize", Tag::DoubleTag(59999968.0)),
+        ("WanderingTraderId", Tag::IntArrayTag(vec![1, 2, 3, 4])),
+        ("GameRules", compound(game_rules)),
+        (
+            "WorldGenSettings",
+            compound([
+                ("seed", Tag::LongTag(42)),
+                ("generate_features", Tag::ByteTag(1)),
+                (
+                    "dimensions",
+                    compound([
+                        ("minecraft:overworld", dimension("overworld")),
+                        ("minecraft:the_nether", dimension("nether")),
+                        ("minecraft:the_end", dimension("end")),
+                    ]),
+                ),
+            ]),
+        ),
+        (
+            "Player",
+            compound([
+                (
+                    "Pos",
+                    list([
+                        Tag::DoubleTag(0.5),
+                        Tag::DoubleTag(64.0),
+                        Tag::DoubleTag(0.5),
+                    ]),
+                ),
+                (
+                    "Inventory",
+                    list((0..36).map(|i| item(i, "minecraft:stone"))),
+                ),
+                (
+                    "EnderItems",
+                    list((0..27).map(|i| item(i, "minecraft:dirt"))),
+                ),
+                (
+                    "recipeBook",
+                    compound([(
+                        "recipes",
+                        list((0..800).map(|i| string(format!("minecraft:recipe_{}", i)))),
+                    )]),
+                ),
+            ]),
+        ),
+        ("ScheduledEvents", list(scheduled)),
+        (
+            "Version",
+            compound([
+                ("Id", Tag::IntTag(4189)),
+                ("Name", string("1.21.4")),
+                ("Snapshot", Tag::ByteTag(0)),
+            ]),
+        ),
+    ]);
+    write(compound([("Data", data)]))
+}
+
+/// A fully generated chunk with all sections populated and a few dozen block entities
+fn chunk() -> Vec<u8> {
+    let section = |y: i8| {
+        compound([
+            ("Y", Tag::ByteTag(y as u8)),
+            (
+                "block_states",
+                compound([
+                    (
+                        "palette",
+                        list((0..16).map(|i| {
+                            compound([
+                                ("Name", string(format!("minecraft:block_{}", i))),
+                                ("Properties", compound([("axis", string("y"))])),
+                            ])
+                        })),
+                    ),
+                    ("data", Tag::LongArrayTag((0..256).collect())),
+                ]),
+            ),
+            (
+                "biomes",
+                compound([("palette", list([string("minecraft:plains")]))]),
+            ),
+            ("BlockLight", Tag::ByteArrayTag(vec![0; 2048])),
+            ("SkyLight", Tag::ByteArrayTag(vec![15; 2048])),
+        ])
+    };
+    let block_entity = |i: i32| {
+        compound([
+            ("id", string("minecraft:chest")),
+            ("x", Tag::IntTag(i % 16)),
+            ("y", Tag::IntTag(i)),
+            ("z", Tag::IntTag(i / 16)),
+            (
+                "Items",
+                list((0..27).map(|i| item(i, "minecraft:cobblestone"))),
+            ),
+        ])
+    };
+    write(compound([
+        ("DataVersion", Tag::IntTag(4189)),
+        ("xPos", Tag::IntTag(0)),
+        ("zPos", Tag::IntTag(0)),
+        ("yPos", Tag::IntTag(-4)),
+        ("Status", string("minecraft:full")),
+        ("LastUpdate", Tag::LongTag(1000)),
+        ("InhabitedTime", Tag::LongTag(1000)),
+        ("sections", list((-4..20).map(section))),
+        (
+            "Heightmaps",
+            compound([
+                ("MOTION_BLOCKING", Tag::LongArrayTag(vec![0; 37])),
+                ("WORLD_SURFACE", Tag::LongArrayTag(vec![0; 37])),
+            ]),
+        ),
+        ("block_entities", list((0..40).map(block_entity))),
+        (
+            "structures",
+            compound([
+                ("References", compound::<String>([])),
+                ("starts", compound::<String>([])),
+            ]),
+        ),
+    ]))
+}
+
+fn load(c: &mut Criterion) {
+    let mut group = c.benchmark_group("load");
+    for (fixture, data) in [("level.dat", level_dat()), ("chunk", chunk())] {
+        group.bench_with_input(
+            BenchmarkId::new("CompoundTag", fixture),
+            &data,
+            |b, data| {
+                b.iter(|| {
+                    black_box(nbt_io::read_sync(&data[..], NbtAccounter::unlimited_heap()).unwrap())
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("DashMap baseline", fixture),
+            &data,
+            |b, data| {
+                b.iter(|| {
+                    let accounter = NbtAccounter::unlimited_heap();
+                    let mut input = nbt::SyncInput(&data[..]);
+                    black_box(complete(dashmap_baseline::read(&mut input, &accounter)).unwrap())
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Changing one entry of a loaded tree, which the baseline could only do safely by copying the
+/// whole root map
+fn clone_and_modify(c: &mut Criterion) {
+    let mut group = c.benchmark_group("clone and modify");
+    for (fixture, data) in [("level.dat", level_dat()), ("chunk", chunk())] {
+        let tag = nbt_io::read_sync(&data[..], NbtAccounter::unlimited_heap()).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("CompoundTag", fixture),
+            &tag,
+            |b, tag: &CompoundTag| {
+                b.iter(|| {
+                    let mut copy = tag.clone();
+                    copy.put("DataVersion", Tag::IntTag(1));
+                    black_box(copy)
+                })
+            },
+        );
+
+        let accounter = NbtAccounter::unlimited_heap();
+        let mut input = nbt::SyncInput(&data[..]);
+        let baseline = complete(dashmap_baseline::read(&mut input, &accounter)).unwrap();
+        group.bench_with_input(
+            BenchmarkId::new("DashMap baseline", fixture),
+            &baseline,
+            |b, tag| {
+                b.iter(|| {
+                    let dashmap_baseline::Tag::CompoundTag(root) = tag else {
+                        unreachable!()
+                    };
+                    let copy = (**root).clone();
+                    copy.insert("DataVersion".to_string(), dashmap_baseline::Tag::IntTag(1));
+                    black_box(copy)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, load, clone_and_modify);
+criterion_main!(benches);
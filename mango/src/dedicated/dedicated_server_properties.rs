@@ -6,6 +6,7 @@ use std::fs::File;
 use std::io::Write;
 use std::io::{BufReader, BufWriter};
 use std::path::PathBuf;
+use std::time::Duration;
 use tracing::{error, warn};
 use typetag::serde;
 
@@ -26,6 +27,13 @@ pub struct DedicatedServerProperties {
     pub safe_mode: bool,
     #[serde(default = "default_function_permission_level")]
     pub function_permission_level: u8,
+    /// Compression of chunks written to region files: deflate, lz4 or none
+    #[serde(default = "default_region_file_compression")]
+    pub region_file_compression: String,
+    /// Seconds to wait for another instance to release the world's session lock, or 0 to fail
+    /// right away
+    #[serde(default)]
+    pub session_lock_timeout: u64,
     #[serde(flatten)]
     initial_data_pack_configuration: InitialDataPackConfig,
 }
@@ -64,6 +72,11 @@ impl DedicatedServerProperties {
     pub fn get_initial_data_pack_configuration(&self) -> DataPackConfig {
         DataPackConfig::from(&self.initial_data_pack_configuration)
     }
+
+    /// How long to wait for the world's session lock, if at all
+    pub fn get_session_lock_timeout(&self) -> Option<Duration> {
+        (self.session_lock_timeout > 0).then(|| Duration::from_secs(self.session_lock_timeout))
+    }
 }
 impl Default for DedicatedServerProperties {
     fn default() -> Self {
@@ -74,6 +87,8 @@ impl Default for DedicatedServerProperties {
             level_name: "world".to_string(),
             safe_mode: false,
             function_permission_level: 2,
+            region_file_compression: default_region_file_compression(),
+            session_lock_timeout: 0,
             initial_data_pack_configuration: InitialDataPackConfig::default(),
         }
     }
@@ -135,6 +150,10 @@ fn default_function_permission_level() -> u8 {
     2
 }
 
+fn default_region_file_compression() -> String {
+    "deflate".to_string()
+}
+
 fn default_initial_enabled_packs() -> String {
     DataPackConfig::default().enabled.join(",")
 }
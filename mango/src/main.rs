@@ -9,10 +9,12 @@ mod minecraft_server;
 mod nbt;
 mod network;
 mod packs;
+mod player_advancements;
 mod registry_layer;
 mod resources;
 mod shared_constants;
 mod sounds;
+mod stats;
 mod util;
 mod world;
 mod world_loader;
@@ -22,12 +24,15 @@ use crate::dedicated::dedicated_server_properties::DedicatedServerProperties;
 use crate::dedicated::dedicated_server_settings::DedicatedServerSettings;
 use crate::packs::repository::pack_repository::PackRepository;
 use crate::packs::repository::server_packs_source;
+use crate::util::worldupdate::world_upgrader::WorldUpgrader;
 use crate::world::flag::feature_flags;
 use crate::world::level::storage::level_storage_source::LevelStorageSource;
 use crate::world::level::storage::level_summary::LevelSummary;
+use crate::world::level::storage::region_file_version::RegionFileVersion;
 use crate::world::level::world_data_configuration::WorldDataConfiguration;
 use crate::world_loader::{InitConfig, PackConfig};
 use std::fs::OpenOptions;
+use tokio::task::LocalSet;
 use tracing::{error, info, warn, Level};
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::filter::LevelFilter;
@@ -68,16 +73,32 @@ async fn main() {
     // TODO: log file
     // TODO: profiling
 
+    let args: Vec<String> = std::env::args().collect();
+    let force_upgrade = args.iter().any(|e| e == "--forceUpgrade");
+    let erase_cache = args.iter().any(|e| e == "--eraseCache");
+
     bootstrap::bootstrap();
     // TODO: validate bootstrap including missing translations, commands, Attribute suppliers for entities
     // TODO: timer hack thread
     let properties = DedicatedServerSettings::new("server.properties");
     info!("Loaded server properties: {:#?}", properties);
     properties.force_save();
-    // TODO: RegionFileVersion, EULA, YggdrasilAuthenticationService
+    RegionFileVersion::configure(&properties.region_file_compression);
+    // TODO: EULA, YggdrasilAuthenticationService
     let level_storage_source = LevelStorageSource::create_default(properties.universe.clone());
-    let mut level_storage_access =
-        level_storage_source.validate_and_create_access(properties.level_name.clone());
+    let mut level_storage_access = match level_storage_source
+        .validate_and_create_access(
+            properties.level_name.clone(),
+            properties.get_session_lock_timeout(),
+        )
+        .await
+    {
+        Ok(level_storage_access) => level_storage_access,
+        Err(e) => {
+            error!("{}", e);
+            return;
+        }
+    };
 
     let mut level_data = None;
     if level_storage_access.has_world_data() {
@@ -93,7 +114,18 @@ async fn main() {
                     "Loaded level data: {}",
                     serde_json::to_string_pretty(&data_tag.value).unwrap()
                 );
-                let level_summary = level_storage_access.make_level_summary(&data_tag, false);
+                let level_summary = match level_storage_access.make_level_summary(&data_tag, false)
+                {
+                    Ok(level_summary) => level_summary,
+                    Err(e) => {
+                        error!(
+                            ?e,
+                            "Failed to read level summary from {:?}",
+                            level_storage_access.level_directory.data_file()
+                        );
+                        return;
+                    }
+                };
                 info!(
                     "Level summary: {}",
                     serde_json::to_string_pretty(&level_summary).unwrap()
@@ -118,12 +150,28 @@ async fn main() {
                             serde_json::to_string_pretty(&data_tag.value).unwrap()
                         );
                         let level_summary =
-                            level_storage_access.make_level_summary(&data_tag, false);
+                            match level_storage_access.make_level_summary(&data_tag, false) {
+                                Ok(level_summary) => level_summary,
+                                Err(e) => {
+                                    error!(
+                                        ?e,
+                                        "Failed to read level summary from {:?}",
+                                        level_storage_access.level_directory.old_data_file()
+                                    );
+                                    return;
+                                }
+                            };
                         info!(
                             "Fallback Level summary: {}",
                             serde_json::to_string_pretty(&level_summary).unwrap()
                         );
-                        level_storage_access.restore_level_data_from_old();
+                        if let Err(e) = level_storage_access.restore_level_data_from_old() {
+                            error!(
+                                ?e,
+                                "Failed to restore {:?}, it is still corrupted",
+                                level_storage_access.level_directory.data_file()
+                            );
+                        }
                         Some((data_tag, level_summary))
                     }
                     Err(e) => {
@@ -142,6 +190,21 @@ async fn main() {
             }
         };
 
+        // Back up before anything is written in this version's format, in case the upgrade breaks
+        // the world
+        if level_data.as_ref().is_some_and(|(_, level_summary)| {
+            force_upgrade
+                || level_summary.requires_upgrade()
+                || level_summary.is_downgrade()
+                || !level_summary.is_compatible()
+        }) {
+            info!("Backing up world before loading it in this version");
+            if let Err(e) = level_storage_access.make_world_backup().await {
+                error!(?e, "Failed to back up world, not loading it");
+                return;
+            }
+        }
+
         if level_data
             .as_ref()
             .is_some_and(|(_, level_summary)| level_summary.requires_manual_conversion)
@@ -161,6 +224,20 @@ async fn main() {
         }
     }
 
+    if force_upgrade {
+        info!("Forcing world upgrade!");
+        let world_upgrader = WorldUpgrader::new(
+            &mut level_storage_access,
+            level_data.as_ref().map(|(data_tag, _)| data_tag),
+            level_storage_source.get_data_fixer(),
+            erase_cache,
+        );
+        if let Err(e) = world_upgrader.work().await {
+            error!(?e, "Failed to upgrade world");
+            return;
+        }
+    }
+
     // TODO: handle safe mode command line arg
     if properties.safe_mode {
         warn!("Safe mode active, only vanilla datapack will be loaded");
@@ -174,17 +251,46 @@ async fn main() {
 
     let init_config = load_or_create_config(
         &properties,
-        level_data.map(|(_, level_summary)| level_summary),
+        level_data.as_ref().map(|(_, level_summary)| level_summary),
         properties.safe_mode,
         pack_repository,
     );
     //info!("Init Config: {:#?}", init_config);
-    world_loader::load(init_config).await;
+    // Like vanilla's runServer, the level is saved on the way out whether the server stopped,
+    // crashed or was interrupted. The loader isn't Send, so it runs as a local task, which also
+    // catches its panics.
+    let server = LocalSet::new();
+    let handle = server.spawn_local(world_loader::load(init_config));
+    tokio::select! {
+        res = server.run_until(handle) => {
+            if let Err(e) = res {
+                error!(?e, "Encountered an unexpected exception");
+            }
+        }
+        _ = tokio::signal::ctrl_c() => info!("Stopping server"),
+    }
+
+    // TODO: save on autosave and each dimension's saved data with DimensionDataStorage::save too,
+    //  once the server loop exists
+    if let Some((data_tag, level_summary)) = &level_data {
+        info!("Saving level data");
+        match data_tag.value.try_as_compound_tag_ref() {
+            Some(data) => {
+                if let Err(e) = level_storage_access
+                    .save_data_tag(data, &level_summary.settings, None)
+                    .await
+                {
+                    error!(?e, "Failed to save level data");
+                }
+            }
+            None => error!("Failed to save level data, it is not a compound"),
+        }
+    }
 }
 
 fn load_or_create_config(
     properties: &DedicatedServerProperties,
-    level_summary: Option<LevelSummary>,
+    level_summary: Option<&LevelSummary>,
     safe_mode: bool,
     pack_repo: PackRepository,
 ) -> InitConfig {
@@ -196,7 +302,7 @@ fn load_or_create_config(
                 feature_flags::FEATURE_FLAGS.default_flags.clone(),
             ),
         ),
-        Some(level_summary) => (false, level_summary.settings.data_configuration),
+        Some(level_summary) => (false, level_summary.settings.data_configuration.clone()),
     };
 
     let pack_config = PackConfig::new(pack_repo, world_data_config, safe_mode, init_mode);
@@ -1,29 +1,31 @@
 use crate::nbt::list_tag::ListTag;
 use crate::nbt::nbt_accounter::NbtAccounter;
+use crate::nbt::string_tag;
 use crate::nbt::tag::Tag;
 use crate::nbt::tag_type::TagType;
-use crate::nbt::DataInput;
+use crate::nbt::{DataInput, DataOutput};
 use anyhow::Result;
-use dashmap::DashMap;
+use indexmap::IndexMap;
 use serde::Serialize;
 use std::borrow::Borrow;
 use std::sync::Arc;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tracing::error;
 
-/// CompoundTag that is thread-safe and cloneable
+/// CompoundTag that keeps entries in insertion order so saving is deterministic. Clones are cheap
+/// and share entries until one of them is modified.
 #[derive(Default, Clone, Serialize)]
 pub struct CompoundTag {
-    tags: Arc<DashMap<String, Tag>>,
+    tags: Arc<IndexMap<String, Tag>>,
 }
 impl CompoundTag {
     pub async fn load_compound(
-        reader: &mut DataInput,
+        reader: &mut impl DataInput,
         nbt_accounter: impl Borrow<NbtAccounter>,
     ) -> Result<CompoundTag> {
         let accounter = nbt_accounter.borrow();
-        accounter.account_bytes(48);
-        let map = DashMap::new();
+        accounter.account_bytes(48)?;
+        let mut map = IndexMap::new();
 
         let mut tag_type = 1;
         while tag_type != 0 {
@@ -36,7 +38,7 @@ impl CompoundTag {
                 Self::read_named_tag_data(TagType::get_type(tag_type), &name, reader, accounter)
                     .await?;
             if map.insert(name, tag).is_none() {
-                accounter.account_bytes(36);
+                accounter.account_bytes(36)?;
             }
         }
 
@@ -47,21 +49,43 @@ impl CompoundTag {
 
     /// Reads a modified UTF-8 string based on
     pub async fn read_string(
-        reader: &mut DataInput,
+        reader: &mut impl DataInput,
         nbt_accounter: &NbtAccounter,
     ) -> Result<String> {
         let len = reader.read_u16().await?;
         let mut buf = vec![0u8; len as usize];
         reader.read_exact(&mut buf).await?;
         let res = cesu8::from_java_cesu8(&buf)?.to_string();
-        nbt_accounter.account_bytes(28 + 2 * res.len() as u64);
+        nbt_accounter.account_bytes(28 + 2 * res.len() as u64)?;
         Ok(res)
     }
 
+    pub async fn write(&self, output: &mut impl DataOutput) -> Result<()> {
+        for (name, tag) in self.tags.iter() {
+            Self::write_named_tag(name, tag, output).await?;
+        }
+        output.write_u8(0).await?;
+        Ok(())
+    }
+
+    /// Writes the type id, name and payload of a tag
+    pub async fn write_named_tag(
+        name: &str,
+        tag: &Tag,
+        output: &mut impl DataOutput,
+    ) -> Result<()> {
+        output.write_u8(tag.get_id()).await?;
+        if tag.get_id() != 0 {
+            string_tag::write_string(output, name).await?;
+            tag.write(output).await?;
+        }
+        Ok(())
+    }
+
     async fn read_named_tag_data(
         tag_type: TagType,
         name: &String,
-        reader: &mut DataInput,
+        reader: &mut impl DataInput,
         nbt_accounter: impl Borrow<NbtAccounter>,
     ) -> Result<Tag> {
         Box::pin(tag_type.load(reader, nbt_accounter))
@@ -75,9 +99,65 @@ impl CompoundTag {
             })
     }
 
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    pub fn get_all_keys(&self) -> Vec<String> {
+        self.tags.keys().cloned().collect()
+    }
+
+    pub fn get(&self, tag: impl AsRef<str>) -> Option<Tag> {
+        self.tags.get(tag.as_ref()).cloned()
+    }
+
+    /// Entries in insertion order
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Tag)> {
+        self.tags.iter()
+    }
+
+    /// Mutable access to a tag. Copies the underlying map first if it is shared with a clone.
+    pub fn get_mut(&mut self, tag: impl AsRef<str>) -> Option<&mut Tag> {
+        Arc::make_mut(&mut self.tags).get_mut(tag.as_ref())
+    }
+
+    /// Inserts a tag, returning the previous tag under the same name. Clones of this compound are
+    /// not affected.
+    pub fn put(&mut self, key: impl Into<String>, tag: Tag) -> Option<Tag> {
+        Arc::make_mut(&mut self.tags).insert(key.into(), tag)
+    }
+
+    pub fn remove(&mut self, key: impl AsRef<str>) -> Option<Tag> {
+        Arc::make_mut(&mut self.tags).shift_remove(key.as_ref())
+    }
+
+    pub fn contains_key(&self, key: impl AsRef<str>) -> bool {
+        self.tags.contains_key(key.as_ref())
+    }
+
+    /// Copies all entries of `other` into this compound like vanilla. Compounds present in both
+    /// are merged recursively, anything else is overwritten.
+    pub fn merge(&mut self, other: &CompoundTag) -> &mut Self {
+        for (key, tag) in other.iter() {
+            match (self.get_mut(key), tag) {
+                (Some(Tag::CompoundTag(existing)), Tag::CompoundTag(tag)) => {
+                    existing.merge(tag);
+                }
+                _ => {
+                    self.put(key.clone(), tag.clone());
+                }
+            }
+        }
+        self
+    }
+
     pub fn get_tag_type(&self, tag: impl AsRef<str>) -> TagType {
         match self.tags.get(tag.as_ref()) {
-            Some(tag) => TagType::from(tag.value()),
+            Some(tag) => TagType::from(tag),
             // vanilla defaults to end tag
             None => TagType::EndTag,
         }
@@ -90,7 +170,7 @@ impl CompoundTag {
 
     pub fn get_int_or_default(&self, tag: impl AsRef<str>, default: i32) -> i32 {
         if self.contains(&tag, TagType::Numeric) {
-            return self.tags.get(tag.as_ref()).unwrap().value().get_as_int();
+            return self.tags.get(tag.as_ref()).unwrap().get_as_int();
         }
         default
     }
@@ -101,7 +181,7 @@ impl CompoundTag {
 
     pub fn get_long_or_default(&self, tag: impl AsRef<str>, default: i64) -> i64 {
         if self.contains(&tag, TagType::Numeric) {
-            return self.tags.get(tag.as_ref()).unwrap().value().get_as_long();
+            return self.tags.get(tag.as_ref()).unwrap().get_as_long();
         }
         default
     }
@@ -112,7 +192,7 @@ impl CompoundTag {
 
     pub fn get_string_or_default(&self, tag: impl AsRef<str>, default: String) -> String {
         if self.contains(&tag, TagType::StringTag) {
-            return self.tags.get(tag.as_ref()).unwrap().value().get_as_string();
+            return self.tags.get(tag.as_ref()).unwrap().get_as_string();
         }
         default
     }
@@ -130,7 +210,7 @@ impl CompoundTag {
 
     pub fn get_bool_or_default(&self, tag: impl AsRef<str>, default: bool) -> bool {
         if self.contains(&tag, TagType::ByteTag) {
-            return self.tags.get(tag.as_ref()).unwrap().value().get_as_int() != 0;
+            return self.tags.get(tag.as_ref()).unwrap().get_as_int() != 0;
         }
         default
     }
@@ -145,7 +225,6 @@ impl CompoundTag {
                 .tags
                 .get(tag.as_ref())
                 .unwrap()
-                .value()
                 .try_as_list_tag_ref()
                 .unwrap()
                 .clone();
@@ -180,3 +259,16 @@ impl CompoundTag {
         )
     }
 }
+impl PartialEq for CompoundTag {
+    /// Entries are compared regardless of their order, like vanilla
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.tags, &other.tags) || self.tags == other.tags
+    }
+}
+impl FromIterator<(String, Tag)> for CompoundTag {
+    fn from_iter<T: IntoIterator<Item = (String, Tag)>>(iter: T) -> Self {
+        Self {
+            tags: Arc::new(iter.into_iter().collect()),
+        }
+    }
+}
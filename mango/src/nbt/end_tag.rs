@@ -2,5 +2,5 @@ use serde::Serialize;
 
 pub const INSTANCE: EndTag = EndTag {};
 
-#[derive(Serialize)]
+#[derive(Clone, PartialEq, Serialize)]
 pub struct EndTag {}
@@ -1,15 +1,15 @@
 use crate::nbt::nbt_accounter::NbtAccounter;
 use crate::nbt::tag::Tag;
 use crate::nbt::tag_type::TagType;
-use crate::nbt::DataInput;
-use anyhow::Result;
+use crate::nbt::{DataInput, DataOutput};
+use anyhow::{anyhow, Result};
 use serde::Serialize;
 use std::borrow::Borrow;
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
 use std::sync::Arc;
-use tokio::io::AsyncReadExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-#[derive(Clone, Default, Serialize)]
+#[derive(Clone, Default, PartialEq, Serialize)]
 pub struct ListTag {
     pub tags: Arc<Vec<Tag>>,
 }
@@ -19,6 +19,30 @@ impl ListTag {
             tags: Arc::new(tags),
         }
     }
+
+    /// Element type of the list. Empty lists are written with the end tag type like vanilla.
+    pub fn element_type(&self) -> u8 {
+        self.tags.first().map_or(0, Tag::get_id)
+    }
+
+    /// Writes the list, failing if its tags aren't all of the same type as NBT lists can only hold
+    /// one type
+    pub async fn write(&self, output: &mut impl DataOutput) -> Result<()> {
+        let element_type = self.element_type();
+        if let Some(tag) = self.tags.iter().find(|e| e.get_id() != element_type) {
+            return Err(anyhow!(
+                "Can't write ListTag of type {} holding a tag of type {}",
+                element_type,
+                tag.get_id()
+            ));
+        }
+        output.write_u8(element_type).await?;
+        output.write_i32(self.tags.len() as i32).await?;
+        for tag in self.tags.iter() {
+            tag.write(output).await?;
+        }
+        Ok(())
+    }
 }
 impl Deref for ListTag {
     type Target = Vec<Tag>;
@@ -27,22 +51,31 @@ impl Deref for ListTag {
         &self.tags
     }
 }
+/// Copies the underlying list first if it is shared with a clone
+impl DerefMut for ListTag {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        Arc::make_mut(&mut self.tags)
+    }
+}
 
 pub async fn load_list(
-    reader: &mut DataInput,
+    reader: &mut impl DataInput,
     nbt_accounter: impl Borrow<NbtAccounter>,
 ) -> Result<ListTag> {
     let nbt_accounter = nbt_accounter.borrow();
-    nbt_accounter.account_bytes(37);
+    nbt_accounter.account_bytes(37)?;
 
     let tag_type = reader.read_u8().await?;
     let len = reader.read_i32().await?;
 
     if tag_type == 0 && len > 0 {
-        panic!("Missing type on ListTag");
+        return Err(anyhow!("Missing type on ListTag"));
+    }
+    if len < 0 {
+        return Err(anyhow!("Negative length {} on ListTag", len));
     }
 
-    nbt_accounter.account_bytes(4 * len as u64);
+    nbt_accounter.account_bytes(4 * len as u64)?;
     let mut list = Vec::with_capacity(len as usize);
 
     for _ in 0..len {
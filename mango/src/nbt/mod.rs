@@ -1,6 +1,7 @@
-use async_compression::tokio::bufread::GzipDecoder;
-use tokio::fs::File;
-use tokio::io::BufReader;
+use std::io::Read;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
 
 pub mod compound_tag;
 mod end_tag;
@@ -8,10 +9,51 @@ pub mod list_tag;
 pub mod nbt_accounter;
 pub mod nbt_io;
 pub mod nbt_ops;
+pub mod nbt_path;
+pub mod nbt_serde;
 pub mod nbt_utils;
+pub mod snbt_printer;
+pub mod stream_tag_visitor;
 mod string_tag;
 pub mod tag;
+pub mod tag_parser;
 pub mod tag_type;
+pub mod visitors;
 
-/// Helper type for reading compressed NBT data
-type DataInput = GzipDecoder<BufReader<File>>;
+/// Any source NBT data can be read from, such as a decompressing file reader, a network buffer or
+/// an in-memory slice
+pub trait DataInput: AsyncRead + Unpin + Send {}
+impl<T: AsyncRead + Unpin + Send> DataInput for T {}
+
+/// Any sink NBT data can be written to, compressed or not
+pub trait DataOutput: AsyncWrite + Unpin + Send {}
+impl<T: AsyncWrite + Unpin + Send> DataOutput for T {}
+
+/// Reads past `len` bytes, as the underlying reader may not support seeking
+pub async fn skip_bytes(reader: &mut impl DataInput, len: u64) -> anyhow::Result<()> {
+    let skipped = tokio::io::copy(&mut reader.take(len), &mut tokio::io::sink()).await?;
+    if skipped != len {
+        return Err(anyhow::anyhow!(
+            "Unexpected end of input while skipping {} bytes",
+            len
+        ));
+    }
+    Ok(())
+}
+
+/// Adapts a blocking [Read] into a [DataInput] so NBT can be decoded from synchronous sources.
+///
+/// Reads complete immediately, so this must only wrap readers that are cheap to block on such as
+/// in-memory buffers or already opened files.
+pub struct SyncInput<R: Read>(pub R);
+impl<R: Read + Unpin> AsyncRead for SyncInput<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let read = self.get_mut().0.read(buf.initialize_unfilled())?;
+        buf.advance(read);
+        Poll::Ready(Ok(()))
+    }
+}
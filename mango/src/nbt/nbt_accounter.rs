@@ -1,5 +1,28 @@
 use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use thiserror::Error;
 
+/// Quota for NBT received over the network, matching vanilla's FriendlyByteBuf
+pub const DEFAULT_NBT_QUOTA: u64 = 2097152;
+/// Quota for reading level.dat, which can hold large player and world gen data
+pub const LEVEL_DATA_QUOTA: u64 = 104857600;
+pub const DEFAULT_MAX_DEPTH: u32 = 512;
+
+#[derive(Debug, Error)]
+pub enum NbtAccounterError {
+    #[error("Tried to read NBT tag that was too big; tried to allocate: {usage} + {requested} bytes where max allowed: {quota}")]
+    QuotaExceeded {
+        usage: u64,
+        requested: u64,
+        quota: u64,
+    },
+    #[error("Tried to read NBT tag with too high complexity, depth > {max_depth}")]
+    DepthExceeded { max_depth: u32 },
+    #[error("NBT-Accounter tried to pop stack-depth at top-level")]
+    PopAtTopLevel,
+}
+
+/// Tracks the approximate memory and nesting depth of NBT being read so malicious or corrupt input
+/// fails with an error instead of exhausting memory or the stack
 pub struct NbtAccounter {
     quota: u64,
     usage: AtomicU64,
@@ -7,7 +30,7 @@ pub struct NbtAccounter {
     depth: AtomicU32,
 }
 impl NbtAccounter {
-    fn new(quota: u64, max_depth: u32) -> Self {
+    pub fn new(quota: u64, max_depth: u32) -> Self {
         Self {
             quota,
             usage: AtomicU64::new(0),
@@ -18,30 +41,56 @@ impl NbtAccounter {
 
     /// Allocate an NbtAccounter on the heap
     pub fn create(quota: u64) -> Box<Self> {
-        Box::new(Self::new(quota, 512))
+        Box::new(Self::new(quota, DEFAULT_MAX_DEPTH))
+    }
+
+    /// Quota for untrusted input such as packets
+    pub fn default_quota() -> Box<Self> {
+        Self::create(DEFAULT_NBT_QUOTA)
+    }
+
+    pub fn level_data() -> Box<Self> {
+        Self::create(LEVEL_DATA_QUOTA)
+    }
+
+    /// No size limit, only the default depth limit. Only use this for trusted data.
+    pub fn unlimited_heap() -> Box<Self> {
+        Self::create(u64::MAX)
     }
 
-    pub fn account_bytes(&self, usage: u64) {
+    pub fn account_bytes(&self, usage: u64) -> Result<(), NbtAccounterError> {
         let prev = self.usage.fetch_add(usage, Ordering::Relaxed);
-        if prev + usage > self.quota {
-            panic!("Tried to read NBT tag that was too big; tried to allocate: {} + {} bytes where max allowed: {}", prev, usage, self.quota);
+        if prev.saturating_add(usage) > self.quota {
+            return Err(NbtAccounterError::QuotaExceeded {
+                usage: prev,
+                requested: usage,
+                quota: self.quota,
+            });
         }
+        Ok(())
     }
 
-    pub fn push_depth(&self) {
+    pub fn push_depth(&self) -> Result<(), NbtAccounterError> {
         let prev = self.depth.fetch_add(1, Ordering::Relaxed);
         if prev >= self.max_depth {
-            panic!(
-                "Tried to read NBT tag with too high complexity, depth > {}",
-                self.max_depth
-            );
+            self.depth.fetch_sub(1, Ordering::Relaxed);
+            return Err(NbtAccounterError::DepthExceeded {
+                max_depth: self.max_depth,
+            });
         }
+        Ok(())
     }
 
-    pub fn pop_depth(&self) {
-        let prev = self.depth.fetch_sub(1, Ordering::Relaxed);
-        if prev <= 0 {
-            panic!("NBT-Accounter tried to pop stack-depth at top-level");
-        }
+    pub fn pop_depth(&self) -> Result<(), NbtAccounterError> {
+        self.depth
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |depth| {
+                depth.checked_sub(1)
+            })
+            .map(|_| ())
+            .map_err(|_| NbtAccounterError::PopAtTopLevel)
+    }
+
+    pub fn get_usage(&self) -> u64 {
+        self.usage.load(Ordering::Relaxed)
     }
 }
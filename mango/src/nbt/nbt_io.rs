@@ -1,36 +1,121 @@
 use crate::nbt::compound_tag::CompoundTag;
 use crate::nbt::nbt_accounter::NbtAccounter;
+use crate::nbt::stream_tag_visitor::{StreamTagVisitor, ValueResult};
 use crate::nbt::tag::Tag;
 use crate::nbt::tag_type::TagType;
-use crate::nbt::{end_tag, string_tag, DataInput};
+use crate::nbt::SyncInput;
+use crate::nbt::{end_tag, string_tag, DataInput, DataOutput};
+use crate::util;
 use anyhow::{anyhow, Result};
-use async_compression::tokio::bufread::GzipDecoder;
+use async_compression::tokio::bufread::{GzipDecoder, ZlibDecoder};
+use async_compression::tokio::write::{GzipEncoder, ZlibEncoder};
 use std::borrow::Borrow;
-use std::path::PathBuf;
+use std::future::Future;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::task::{Context, Poll, Waker};
 use tokio::fs::File;
-use tokio::io::AsyncReadExt;
-use tokio::io::BufReader;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+use tokio::io::{BufReader, BufWriter};
 
+/// How a root tag is framed. Files prefix the root tag with a name, which vanilla always leaves
+/// empty. The protocol since 1.20.2 drops the name and allows any type of root tag.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NbtFormat {
+    File,
+    Network,
+}
+
+/// Reads a gzip compressed file, the format used by level.dat and player data
 pub async fn read_compressed(
     path: PathBuf,
     nbt_accounter: impl Borrow<NbtAccounter>,
 ) -> Result<CompoundTag> {
-    let reader = GzipDecoder::new(BufReader::new(File::open(&path).await?));
-    read(reader, nbt_accounter).await
+    read_compressed_from(BufReader::new(File::open(&path).await?), nbt_accounter).await
+}
+
+pub async fn read_compressed_from(
+    reader: impl AsyncBufRead + Unpin + Send,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> Result<CompoundTag> {
+    read(&mut GzipDecoder::new(reader), nbt_accounter).await
+}
+
+/// Reads a zlib compressed file, the default format of chunks in region files
+pub async fn read_zlib(
+    path: PathBuf,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> Result<CompoundTag> {
+    read_zlib_from(BufReader::new(File::open(&path).await?), nbt_accounter).await
+}
+
+pub async fn read_zlib_from(
+    reader: impl AsyncBufRead + Unpin + Send,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> Result<CompoundTag> {
+    read(&mut ZlibDecoder::new(reader), nbt_accounter).await
+}
+
+pub async fn read_uncompressed(
+    path: PathBuf,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> Result<CompoundTag> {
+    read(&mut BufReader::new(File::open(&path).await?), nbt_accounter).await
 }
 
-async fn read(
-    mut reader: DataInput,
+/// Reads a file that may be gzip compressed, zlib compressed or uncompressed
+pub async fn read_detect(
+    path: PathBuf,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> Result<CompoundTag> {
+    read_detect_from(BufReader::new(File::open(&path).await?), nbt_accounter).await
+}
+
+/// Detects the compression from the leading bytes without consuming them. Uncompressed NBT always
+/// starts with a tag id, which can never be confused with the gzip magic or a zlib header.
+pub async fn read_detect_from(
+    mut reader: impl AsyncBufRead + Unpin + Send,
     nbt_accounter: impl Borrow<NbtAccounter>,
 ) -> Result<CompoundTag> {
-    match read_unnamed_tag(&mut reader, nbt_accounter).await? {
+    let header = reader.fill_buf().await?;
+    match header.first() {
+        Some(0x1f) => read_compressed_from(reader, nbt_accounter).await,
+        Some(0x78) => read_zlib_from(reader, nbt_accounter).await,
+        _ => read(&mut reader, nbt_accounter).await,
+    }
+}
+
+/// Reads an uncompressed root compound tag. Decompression should be layered on the reader.
+pub async fn read(
+    reader: &mut impl DataInput,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> Result<CompoundTag> {
+    match read_unnamed_tag(reader, nbt_accounter).await? {
         Tag::CompoundTag(tag) => Ok(tag),
         _ => Err(anyhow!("Root tag must be a named compound tag")),
     }
 }
 
-async fn read_unnamed_tag(
-    reader: &mut DataInput,
+/// Reads an uncompressed root compound tag from a blocking reader or in-memory buffer
+pub fn read_sync(
+    reader: impl Read + Unpin + Send,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> Result<CompoundTag> {
+    let mut input = SyncInput(reader);
+    let mut future = std::pin::pin!(read(&mut input, nbt_accounter));
+    // SyncInput never returns pending, so the whole read completes within a single poll
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        Poll::Ready(res) => res,
+        Poll::Pending => Err(anyhow!("Synchronous NBT read did not complete")),
+    }
+}
+
+/// Reads a root tag in the file format, skipping its name
+pub async fn read_unnamed_tag(
+    reader: &mut impl DataInput,
     nbt_accounter: impl Borrow<NbtAccounter>,
 ) -> Result<Tag> {
     let tag_type = reader.read_u8().await?;
@@ -42,8 +127,83 @@ async fn read_unnamed_tag(
     }
 }
 
+/// Reads a root tag in the network format, which has no name and may be of any type
+pub async fn read_any_tag(
+    reader: &mut impl DataInput,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> Result<Tag> {
+    let tag_type = reader.read_u8().await?;
+    if tag_type == 0 {
+        Ok(Tag::EndTag(end_tag::INSTANCE))
+    } else {
+        read_tag_safe(reader, nbt_accounter, tag_type).await
+    }
+}
+
+pub async fn read_tag(
+    format: NbtFormat,
+    reader: &mut impl DataInput,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> Result<Tag> {
+    match format {
+        NbtFormat::File => read_unnamed_tag(reader, nbt_accounter).await,
+        NbtFormat::Network => read_any_tag(reader, nbt_accounter).await,
+    }
+}
+
+/// Reads an optional compound from a packet. An end tag stands in for a missing compound.
+pub async fn read_network_compound(
+    reader: &mut impl DataInput,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> Result<Option<CompoundTag>> {
+    match read_any_tag(reader, nbt_accounter).await? {
+        Tag::EndTag(_) => Ok(None),
+        Tag::CompoundTag(tag) => Ok(Some(tag)),
+        tag => Err(anyhow!("Not a compound tag: {}", tag)),
+    }
+}
+
+/// Streams a gzip compressed file through a visitor without building the tag tree
+pub async fn parse_compressed(
+    path: PathBuf,
+    visitor: &mut impl StreamTagVisitor,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> Result<()> {
+    let mut reader = GzipDecoder::new(BufReader::new(File::open(&path).await?));
+    parse(&mut reader, visitor, nbt_accounter).await
+}
+
+/// Streams an uncompressed root tag in the file format through a visitor. The visitor decides
+/// which parts get decoded; everything else is read past.
+pub async fn parse(
+    reader: &mut impl DataInput,
+    visitor: &mut impl StreamTagVisitor,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> Result<()> {
+    let nbt_accounter = nbt_accounter.borrow();
+    let tag_type = TagType::get_type(reader.read_u8().await?);
+    if tag_type == TagType::EndTag {
+        if visitor.visit_root_entry(tag_type) == ValueResult::Continue {
+            visitor.visit_end();
+        }
+        return Ok(());
+    }
+    match visitor.visit_root_entry(tag_type) {
+        ValueResult::Halt => {}
+        ValueResult::Break => {
+            string_tag::skip_string(reader).await?;
+            tag_type.skip(reader, nbt_accounter).await?;
+        }
+        ValueResult::Continue => {
+            string_tag::skip_string(reader).await?;
+            tag_type.parse(reader, visitor, nbt_accounter).await?;
+        }
+    }
+    Ok(())
+}
+
 async fn read_tag_safe(
-    reader: &mut DataInput,
+    reader: &mut impl DataInput,
     nbt_accounter: impl Borrow<NbtAccounter>,
     tag_type: u8,
 ) -> Result<Tag> {
@@ -52,3 +212,198 @@ async fn read_tag_safe(
         .load(reader, nbt_accounter)
         .await?)
 }
+
+/// Writes a gzip compressed root compound tag to a file, replacing any existing file
+pub async fn write_compressed(tag: &CompoundTag, path: PathBuf) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(&path).await?);
+    write_compressed_to(tag, &mut writer).await?;
+    writer.flush().await?;
+    writer.get_ref().sync_all().await?;
+    Ok(())
+}
+
+/// Writes a gzip compressed root compound tag to a temporary file, which then replaces `target`,
+/// keeping the previous file as `backup`. `target` is never partially written.
+pub async fn write_compressed_atomically(
+    tag: &CompoundTag,
+    target: &Path,
+    backup: &Path,
+) -> Result<()> {
+    let temp_file = util::temp_file_for(target);
+    let written = write_compressed(tag, temp_file.clone()).await;
+    util::replace_with_temp_file(target, &temp_file, Some(backup), written)
+}
+
+/// Writes a gzip compressed root compound tag, the format used by level.dat and player data
+pub async fn write_compressed_to(tag: &CompoundTag, output: &mut impl DataOutput) -> Result<()> {
+    let mut encoder = GzipEncoder::new(output);
+    write(tag, &mut encoder).await?;
+    encoder.shutdown().await?;
+    Ok(())
+}
+
+/// Writes a zlib compressed root compound tag, the default format of chunks in region files
+pub async fn write_zlib(tag: &CompoundTag, output: &mut impl DataOutput) -> Result<()> {
+    let mut encoder = ZlibEncoder::new(output);
+    write(tag, &mut encoder).await?;
+    encoder.shutdown().await?;
+    Ok(())
+}
+
+/// Writes an uncompressed root compound tag with an empty name
+pub async fn write(tag: &CompoundTag, output: &mut impl DataOutput) -> Result<()> {
+    write_unnamed_tag(&Tag::CompoundTag(tag.clone()), output).await
+}
+
+pub async fn write_unnamed_tag(tag: &Tag, output: &mut impl DataOutput) -> Result<()> {
+    write_named_tag("", tag, output).await
+}
+
+/// Writes a root tag in the network format, without a name
+pub async fn write_any_tag(tag: &Tag, output: &mut impl DataOutput) -> Result<()> {
+    output.write_u8(tag.get_id()).await?;
+    tag.write(output).await
+}
+
+pub async fn write_tag(format: NbtFormat, tag: &Tag, output: &mut impl DataOutput) -> Result<()> {
+    match format {
+        NbtFormat::File => write_unnamed_tag(tag, output).await,
+        NbtFormat::Network => write_any_tag(tag, output).await,
+    }
+}
+
+/// Writes an optional compound to a packet, using an end tag when missing
+pub async fn write_network_compound(
+    tag: Option<&CompoundTag>,
+    output: &mut impl DataOutput,
+) -> Result<()> {
+    match tag {
+        None => write_any_tag(&Tag::EndTag(end_tag::INSTANCE), output).await,
+        Some(tag) => write_any_tag(&Tag::CompoundTag(tag.clone()), output).await,
+    }
+}
+
+/// Writes a root tag with the given name. Vanilla always uses an empty name, but older tools
+/// and structure files may not.
+pub async fn write_named_tag(name: &str, tag: &Tag, output: &mut impl DataOutput) -> Result<()> {
+    CompoundTag::write_named_tag(name, tag, output).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A named tag, with its name already in modified UTF-8
+    fn named(id: u8, name: &[u8], payload: &[u8]) -> Vec<u8> {
+        [&[id], &(name.len() as u16).to_be_bytes()[..], name, payload].concat()
+    }
+
+    /// Every type of tag, encoded by hand the way vanilla writes it. Strings are modified UTF-8,
+    /// so NUL takes two bytes and supplementary characters are written as a surrogate pair.
+    /// Vanilla writes empty lists with the end tag as their type, so they read back the same way.
+    fn all_tags() -> Vec<u8> {
+        let entries = [
+            named(1, b"byte", &[0xff]),
+            named(2, b"short", &i16::MIN.to_be_bytes()),
+            named(3, b"int", &i32::MIN.to_be_bytes()),
+            named(4, b"long", &i64::MAX.to_be_bytes()),
+            named(5, b"float", &1.5f32.to_be_bytes()),
+            named(6, b"double", &(-2.25f64).to_be_bytes()),
+            named(7, b"byte_array", &[0, 0, 0, 3, 1, 2, 0xff]),
+            named(
+                8,
+                b"string",
+                &[
+                    0, 11, b'a', 0xc0, 0x80, 0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80, 0xc3, 0xa9,
+                ],
+            ),
+            named(8, &[0xed, 0xa0, 0xbd, 0xed, 0xb8, 0x80], &[0, 0]),
+            named(9, b"empty_list", &[0, 0, 0, 0, 0]),
+            named(
+                9,
+                b"nested_list",
+                &[9, 0, 0, 0, 2, 3, 0, 0, 0, 1, 0, 0, 0, 7, 0, 0, 0, 0, 0],
+            ),
+            named(
+                9,
+                b"compound_list",
+                &[
+                    [10, 0, 0, 0, 1].as_slice(),
+                    &named(8, b"k", &[0, 1, b'v']),
+                    &[0],
+                ]
+                .concat(),
+            ),
+            named(10, b"empty_compound", &[0]),
+            named(
+                11,
+                b"int_array",
+                &[0, 0, 0, 2, 0, 0, 0, 1, 0xff, 0xff, 0xff, 0xff],
+            ),
+            named(12, b"long_array", &[0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 9]),
+        ];
+        named(10, b"", &[entries.concat(), vec![0]].concat())
+    }
+
+    async fn write_raw(tag: &CompoundTag) -> Vec<u8> {
+        let mut out = Vec::new();
+        write(tag, &mut out).await.unwrap();
+        out
+    }
+
+    #[tokio::test]
+    async fn raw_round_trip_is_byte_for_byte() {
+        let bytes = all_tags();
+        let tag = read(&mut bytes.as_slice(), NbtAccounter::unlimited_heap())
+            .await
+            .unwrap();
+        assert_eq!(tag.len(), 15);
+        assert_eq!(tag.get_string("string"), "a\0\u{1f600}\u{e9}");
+        assert!(tag.contains_key("\u{1f600}"));
+        assert_eq!(tag.get_list("nested_list").len(), 2);
+        assert_eq!(write_raw(&tag).await, bytes);
+    }
+
+    #[tokio::test]
+    async fn compressed_round_trips_are_byte_for_byte() {
+        let bytes = all_tags();
+        let tag = read_sync(bytes.as_slice(), NbtAccounter::unlimited_heap()).unwrap();
+
+        let mut gzip = Vec::new();
+        write_compressed_to(&tag, &mut gzip).await.unwrap();
+        let mut zlib = Vec::new();
+        write_zlib(&tag, &mut zlib).await.unwrap();
+        assert_eq!(&gzip[..2], [0x1f, 0x8b]);
+        assert_eq!(zlib[0], 0x78);
+
+        let from_gzip = read_compressed_from(gzip.as_slice(), NbtAccounter::unlimited_heap())
+            .await
+            .unwrap();
+        let from_zlib = read_zlib_from(zlib.as_slice(), NbtAccounter::unlimited_heap())
+            .await
+            .unwrap();
+        assert_eq!(write_raw(&from_gzip).await, bytes);
+        assert_eq!(write_raw(&from_zlib).await, bytes);
+        for data in [gzip, zlib, bytes.clone()] {
+            let detected = read_detect_from(data.as_slice(), NbtAccounter::unlimited_heap())
+                .await
+                .unwrap();
+            assert_eq!(write_raw(&detected).await, bytes);
+        }
+    }
+
+    #[tokio::test]
+    async fn named_root_round_trips() {
+        let bytes = named(
+            10,
+            b"root",
+            &[named(3, b"int", &[0, 0, 0, 1]), vec![0]].concat(),
+        );
+        let tag = read_unnamed_tag(&mut bytes.as_slice(), NbtAccounter::unlimited_heap())
+            .await
+            .unwrap();
+        let mut out = Vec::new();
+        write_named_tag("root", &tag, &mut out).await.unwrap();
+        assert_eq!(out, bytes);
+    }
+}
@@ -1,7 +1,238 @@
-use crate::util::datafix::serialization::dynamic_ops::DynamicOps;
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::end_tag;
+use crate::nbt::list_tag::ListTag;
+use crate::nbt::tag::Tag;
+use crate::nbt::tag_type::TagType;
+use crate::util::datafix::serialization::dynamic_ops::{DynamicOps, Number, ValueKind};
+use anyhow::{anyhow, Result};
 use std::sync::{Arc, LazyLock};
 
-pub const INSTANCE: LazyLock<Arc<dyn DynamicOps>> = LazyLock::new(|| Arc::new(NbtOps {}));
+pub static INSTANCE: LazyLock<Arc<dyn DynamicOps<Tag>>> = LazyLock::new(|| Arc::new(NbtOps {}));
 
+/// Key of the compounds that wrap elements of mixed type lists, as NBT lists hold a single type
+const WRAPPER_MARKER: &str = "";
+
+/// [DynamicOps] over NBT, following vanilla's NbtOps. The end tag stands in for an empty value.
 pub struct NbtOps {}
-impl DynamicOps for NbtOps {}
+impl NbtOps {
+    fn is_wrapper(compound: &CompoundTag) -> bool {
+        compound.len() == 1 && compound.contains_key(WRAPPER_MARKER)
+    }
+
+    fn wrap_element(tag: Tag) -> Tag {
+        match tag {
+            Tag::CompoundTag(compound) if !Self::is_wrapper(&compound) => {
+                Tag::CompoundTag(compound)
+            }
+            tag => Tag::CompoundTag(CompoundTag::from_iter([(WRAPPER_MARKER.to_string(), tag)])),
+        }
+    }
+
+    fn try_unwrap(tag: Tag) -> Tag {
+        match tag {
+            Tag::CompoundTag(compound) if Self::is_wrapper(&compound) => {
+                compound.get(WRAPPER_MARKER).unwrap()
+            }
+            tag => tag,
+        }
+    }
+}
+impl DynamicOps<Tag> for NbtOps {
+    fn empty(&self) -> Tag {
+        Tag::EndTag(end_tag::INSTANCE)
+    }
+
+    fn kind(&self, input: &Tag) -> ValueKind {
+        match input {
+            Tag::EndTag(_) => ValueKind::Empty,
+            Tag::ByteTag(_)
+            | Tag::ShortTag(_)
+            | Tag::IntTag(_)
+            | Tag::LongTag(_)
+            | Tag::FloatTag(_)
+            | Tag::DoubleTag(_) => ValueKind::Number,
+            Tag::StringTag(_) => ValueKind::String,
+            Tag::ListTag(_) => ValueKind::List,
+            Tag::ByteArrayTag(_) => ValueKind::ByteList,
+            Tag::IntArrayTag(_) => ValueKind::IntList,
+            Tag::LongArrayTag(_) => ValueKind::LongList,
+            Tag::CompoundTag(_) => ValueKind::Map,
+        }
+    }
+
+    fn create_number(&self, value: Number) -> Tag {
+        match value {
+            Number::Byte(e) => Tag::ByteTag(e as u8),
+            Number::Short(e) => Tag::ShortTag(e),
+            Number::Int(e) => Tag::IntTag(e),
+            Number::Long(e) => Tag::LongTag(e),
+            Number::Float(e) => Tag::FloatTag(e),
+            Number::Double(e) => Tag::DoubleTag(e),
+        }
+    }
+
+    fn create_string(&self, value: &str) -> Tag {
+        Tag::StringTag(value.to_string())
+    }
+
+    /// Lists of only bytes, ints or longs become arrays like vanilla. Lists mixing types wrap
+    /// each element in a compound.
+    fn create_list(&self, values: Vec<Tag>) -> Tag {
+        let Some(first) = values.first() else {
+            return Tag::ListTag(ListTag::default());
+        };
+        let first_type = TagType::from(first);
+        if values.iter().any(|e| TagType::from(e) != first_type) {
+            return Tag::ListTag(ListTag::new(
+                values.into_iter().map(Self::wrap_element).collect(),
+            ));
+        }
+        match first_type {
+            TagType::ByteTag => {
+                self.create_byte_list(values.iter().map(|e| e.get_as_int() as i8).collect())
+            }
+            TagType::IntTag => self.create_int_list(values.iter().map(Tag::get_as_int).collect()),
+            TagType::LongTag => {
+                self.create_long_list(values.iter().map(Tag::get_as_long).collect())
+            }
+            TagType::CompoundTag => Tag::ListTag(ListTag::new(
+                values.into_iter().map(Self::wrap_element).collect(),
+            )),
+            _ => Tag::ListTag(ListTag::new(values)),
+        }
+    }
+
+    fn create_byte_list(&self, values: Vec<i8>) -> Tag {
+        Tag::ByteArrayTag(values.into_iter().map(|e| e as u8).collect())
+    }
+
+    fn create_int_list(&self, values: Vec<i32>) -> Tag {
+        Tag::IntArrayTag(values)
+    }
+
+    fn create_long_list(&self, values: Vec<i64>) -> Tag {
+        Tag::LongArrayTag(values)
+    }
+
+    /// Empty values are left out, as an end tag can't be stored in a compound
+    fn create_map(&self, entries: Vec<(String, Tag)>) -> Tag {
+        Tag::CompoundTag(
+            entries
+                .into_iter()
+                .filter(|(_, tag)| !matches!(tag, Tag::EndTag(_)))
+                .collect(),
+        )
+    }
+
+    fn get_number_value(&self, input: &Tag) -> Result<Number> {
+        match input {
+            Tag::ByteTag(e) => Ok(Number::Byte(*e as i8)),
+            Tag::ShortTag(e) => Ok(Number::Short(*e)),
+            Tag::IntTag(e) => Ok(Number::Int(*e)),
+            Tag::LongTag(e) => Ok(Number::Long(*e)),
+            Tag::FloatTag(e) => Ok(Number::Float(*e)),
+            Tag::DoubleTag(e) => Ok(Number::Double(*e)),
+            _ => Err(anyhow!("Not a number: {}", input)),
+        }
+    }
+
+    fn get_string_value(&self, input: &Tag) -> Result<String> {
+        match input {
+            Tag::StringTag(e) => Ok(e.clone()),
+            _ => Err(anyhow!("Not a string: {}", input)),
+        }
+    }
+
+    fn get_list(&self, input: &Tag) -> Result<Vec<Tag>> {
+        match input {
+            Tag::ListTag(list) => Ok(list.iter().cloned().map(Self::try_unwrap).collect()),
+            Tag::ByteArrayTag(array) => Ok(array.iter().map(|e| Tag::ByteTag(*e)).collect()),
+            Tag::IntArrayTag(array) => Ok(array.iter().map(|e| Tag::IntTag(*e)).collect()),
+            Tag::LongArrayTag(array) => Ok(array.iter().map(|e| Tag::LongTag(*e)).collect()),
+            _ => Err(anyhow!("Not a list: {}", input)),
+        }
+    }
+
+    fn get_byte_list(&self, input: &Tag) -> Result<Vec<i8>> {
+        match input {
+            Tag::ByteArrayTag(array) => Ok(array.iter().map(|e| *e as i8).collect()),
+            _ => self
+                .get_list(input)?
+                .iter()
+                .map(|e| self.get_number_value(e).map(|n| n.as_i8()))
+                .collect(),
+        }
+    }
+
+    fn get_int_list(&self, input: &Tag) -> Result<Vec<i32>> {
+        match input {
+            Tag::IntArrayTag(array) => Ok(array.clone()),
+            _ => self
+                .get_list(input)?
+                .iter()
+                .map(|e| self.get_number_value(e).map(|n| n.as_i32()))
+                .collect(),
+        }
+    }
+
+    fn get_long_list(&self, input: &Tag) -> Result<Vec<i64>> {
+        match input {
+            Tag::LongArrayTag(array) => Ok(array.clone()),
+            _ => self
+                .get_list(input)?
+                .iter()
+                .map(|e| self.get_number_value(e).map(|n| n.as_i64()))
+                .collect(),
+        }
+    }
+
+    fn get_map_entries(&self, input: &Tag) -> Result<Vec<(String, Tag)>> {
+        match input {
+            Tag::CompoundTag(compound) => Ok(compound
+                .iter()
+                .map(|(key, tag)| (key.clone(), tag.clone()))
+                .collect()),
+            _ => Err(anyhow!("Not a map: {}", input)),
+        }
+    }
+
+    fn get(&self, input: &Tag, key: &str) -> Result<Tag> {
+        match input {
+            Tag::CompoundTag(compound) => compound
+                .get(key)
+                .ok_or_else(|| anyhow!("No key {} in map", key)),
+            _ => Err(anyhow!("Not a map: {}", input)),
+        }
+    }
+
+    fn merge_to_list(&self, list: Tag, value: Tag) -> Result<Tag> {
+        let mut values = match list {
+            Tag::EndTag(_) => Vec::new(),
+            list => self.get_list(&list)?,
+        };
+        values.push(value);
+        Ok(self.create_list(values))
+    }
+
+    fn merge_to_map(&self, map: Tag, key: &str, value: Tag) -> Result<Tag> {
+        let mut compound = match map {
+            Tag::EndTag(_) => CompoundTag::default(),
+            Tag::CompoundTag(compound) => compound,
+            map => return Err(anyhow!("Not a map: {}", map)),
+        };
+        if !matches!(value, Tag::EndTag(_)) {
+            compound.put(key, value);
+        }
+        Ok(Tag::CompoundTag(compound))
+    }
+
+    fn remove(&self, input: Tag, key: &str) -> Tag {
+        match input {
+            Tag::CompoundTag(mut compound) => {
+                compound.remove(key);
+                Tag::CompoundTag(compound)
+            }
+            input => input,
+        }
+    }
+}
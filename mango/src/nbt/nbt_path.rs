@@ -0,0 +1,509 @@
+//! NBT paths such as `Inventory[0].components{"minecraft:custom_name":"x"}`, following vanilla's
+//! NbtPathArgument. Paths select tags by walking compounds by key and lists by index, with SNBT
+//! patterns to filter compounds and list elements.
+
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::list_tag::ListTag;
+use crate::nbt::nbt_utils;
+use crate::nbt::tag::Tag;
+use crate::nbt::tag_parser::{is_quoted_string_start, TagParser};
+use crate::nbt::tag_type::TagType;
+use anyhow::{anyhow, Result};
+use std::fmt::{Display, Formatter};
+
+/// Whether a character may appear in an unquoted key of a path
+pub fn is_allowed_in_unquoted_name(c: char) -> bool {
+    !matches!(c, ' ' | '"' | '\'' | '[' | ']' | '.' | '{' | '}')
+}
+
+pub enum NbtPathNode {
+    /// `name`
+    CompoundChild(String),
+    /// `[index]`, counting from the end if negative
+    IndexedElement(i32),
+    /// `[]`
+    AllElements,
+    /// `[{pattern}]`
+    MatchElement(CompoundTag),
+    /// `{pattern}`, only at the start of a path
+    MatchRootObject(CompoundTag),
+    /// `name{pattern}`
+    MatchObject(String, CompoundTag),
+}
+impl NbtPathNode {
+    fn read(parser: &mut TagParser, root: bool) -> Result<Self> {
+        match parser.peek() {
+            Some('{') => {
+                if !root {
+                    return Err(parser.error("Invalid NBT path element"));
+                }
+                Ok(Self::MatchRootObject(parser.read_struct()?))
+            }
+            Some('[') => {
+                parser.skip();
+                match parser.peek() {
+                    Some('{') => {
+                        let pattern = parser.read_struct()?;
+                        parser.expect(']')?;
+                        Ok(Self::MatchElement(pattern))
+                    }
+                    Some(']') => {
+                        parser.skip();
+                        Ok(Self::AllElements)
+                    }
+                    _ => {
+                        let start = parser.cursor;
+                        let index = parser.read_while(|c| c.is_ascii_digit() || c == '-');
+                        let Ok(index) = index.parse() else {
+                            parser.cursor = start;
+                            return Err(parser.error("Expected integer"));
+                        };
+                        parser.expect(']')?;
+                        Ok(Self::IndexedElement(index))
+                    }
+                }
+            }
+            Some(c) if is_quoted_string_start(c) => {
+                let name = parser.read_quoted_string()?;
+                Self::read_object_node(parser, name)
+            }
+            _ => {
+                let name = parser.read_while(is_allowed_in_unquoted_name).to_string();
+                Self::read_object_node(parser, name)
+            }
+        }
+    }
+
+    fn read_object_node(parser: &mut TagParser, name: String) -> Result<Self> {
+        if name.is_empty() {
+            return Err(parser.error("Invalid NBT path element"));
+        }
+        if parser.peek() == Some('{') {
+            Ok(Self::MatchObject(name, parser.read_struct()?))
+        } else {
+            Ok(Self::CompoundChild(name))
+        }
+    }
+
+    /// Empty tag to create when a missing parent of this node is needed
+    fn create_preferred_parent_tag(&self) -> Tag {
+        match self {
+            Self::CompoundChild(_) | Self::MatchRootObject(_) | Self::MatchObject(..) => {
+                Tag::CompoundTag(CompoundTag::default())
+            }
+            Self::IndexedElement(_) | Self::AllElements | Self::MatchElement(_) => {
+                Tag::ListTag(ListTag::default())
+            }
+        }
+    }
+
+    fn get_tag(&self, tag: &Tag, output: &mut Vec<Tag>) {
+        match (self, tag) {
+            (Self::CompoundChild(name), Tag::CompoundTag(compound)) => {
+                output.extend(compound.get(name));
+            }
+            (Self::IndexedElement(index), _) => {
+                if let Some(i) = collection_len(tag).and_then(|len| resolve_index(len, *index)) {
+                    output.extend(collection_get(tag, i));
+                }
+            }
+            (Self::AllElements, _) => {
+                for i in 0..collection_len(tag).unwrap_or(0) {
+                    output.extend(collection_get(tag, i));
+                }
+            }
+            (Self::MatchElement(pattern), Tag::ListTag(list)) => {
+                output.extend(list.iter().filter(|e| matches(pattern, e)).cloned());
+            }
+            (Self::MatchRootObject(pattern), Tag::CompoundTag(_)) if matches(pattern, tag) => {
+                output.push(tag.clone());
+            }
+            (Self::MatchObject(name, pattern), Tag::CompoundTag(compound)) => {
+                output.extend(compound.get(name).filter(|child| matches(pattern, child)));
+            }
+            _ => {}
+        }
+    }
+
+    /// Calls `f` on every tag this node selects, returning the sum of the results. If `create` is
+    /// given, missing children are created so the path can be set.
+    fn for_each_child_mut(
+        &self,
+        tag: &mut Tag,
+        create: Option<&dyn Fn() -> Tag>,
+        f: &mut dyn FnMut(&mut Tag) -> usize,
+    ) -> usize {
+        match (self, tag) {
+            (Self::CompoundChild(name), Tag::CompoundTag(compound)) => {
+                if !compound.contains_key(name) {
+                    let Some(create) = create else {
+                        return 0;
+                    };
+                    compound.put(name.clone(), create());
+                }
+                compound.get_mut(name).map_or(0, f)
+            }
+            // Array elements are never containers, so only lists can be walked through
+            (Self::IndexedElement(index), Tag::ListTag(list)) => {
+                resolve_index(list.len(), *index).map_or(0, |i| f(&mut list[i]))
+            }
+            (Self::AllElements, Tag::ListTag(list)) => {
+                if list.is_empty() {
+                    if let Some(create) = create {
+                        list.push(create());
+                    }
+                }
+                list.iter_mut().map(f).sum()
+            }
+            (Self::MatchElement(pattern), Tag::ListTag(list)) => {
+                let mut res = 0;
+                let mut found = false;
+                for e in list.iter_mut() {
+                    if matches(pattern, e) {
+                        found = true;
+                        res += f(e);
+                    }
+                }
+                let child = Tag::CompoundTag(pattern.clone());
+                if !found && create.is_some() && list_accepts(list, &child) {
+                    list.push(child);
+                    res += f(list.last_mut().unwrap());
+                }
+                res
+            }
+            (Self::MatchRootObject(pattern), tag @ Tag::CompoundTag(_))
+                if matches(pattern, tag) =>
+            {
+                f(tag)
+            }
+            (Self::MatchObject(name, pattern), Tag::CompoundTag(compound)) => {
+                if !compound.contains_key(name) {
+                    if create.is_none() {
+                        return 0;
+                    }
+                    compound.put(name.clone(), Tag::CompoundTag(pattern.clone()));
+                }
+                match compound.get_mut(name) {
+                    Some(child) if matches(pattern, child) => f(child),
+                    _ => 0,
+                }
+            }
+            _ => 0,
+        }
+    }
+
+    /// Sets the tags this node selects in `tag`, returning how many changed
+    fn set_tag(&self, tag: &mut Tag, value: &Tag) -> usize {
+        match (self, tag) {
+            (Self::CompoundChild(name), Tag::CompoundTag(compound)) => {
+                let prev = compound.put(name.clone(), value.clone());
+                (prev.as_ref() != Some(value)) as usize
+            }
+            (Self::IndexedElement(index), tag) => {
+                let Some(i) = collection_len(tag).and_then(|len| resolve_index(len, *index)) else {
+                    return 0;
+                };
+                (collection_get(tag, i).as_ref() != Some(value) && collection_set(tag, i, value))
+                    as usize
+            }
+            (Self::AllElements, tag) => {
+                let Some(len) = collection_len(tag) else {
+                    return 0;
+                };
+                if len == 0 {
+                    return collection_add(tag, 0, value) as usize;
+                }
+                let changed = (0..len)
+                    .filter(|i| collection_get(tag, *i).as_ref() != Some(value))
+                    .count();
+                if changed == 0 {
+                    return 0;
+                }
+                collection_clear(tag);
+                if !collection_add(tag, 0, value) {
+                    return 0;
+                }
+                for i in 1..len {
+                    collection_add(tag, i, value);
+                }
+                changed
+            }
+            (Self::MatchElement(pattern), Tag::ListTag(list)) => {
+                if list.is_empty() {
+                    list.push(value.clone());
+                    return 1;
+                }
+                let accepts = list_accepts(list, value);
+                let mut changed = 0;
+                for e in list.iter_mut() {
+                    if matches(pattern, e) && e != value && accepts {
+                        *e = value.clone();
+                        changed += 1;
+                    }
+                }
+                changed
+            }
+            (Self::MatchObject(name, pattern), Tag::CompoundTag(compound)) => {
+                match compound.get(name) {
+                    Some(child) if matches(pattern, &child) && child != *value => {
+                        compound.put(name.clone(), value.clone());
+                        1
+                    }
+                    _ => 0,
+                }
+            }
+            _ => 0,
+        }
+    }
+
+    /// Removes the tags this node selects from `tag`, returning how many were removed
+    fn remove_tag(&self, tag: &mut Tag) -> usize {
+        match (self, tag) {
+            (Self::CompoundChild(name), Tag::CompoundTag(compound)) => {
+                compound.remove(name).is_some() as usize
+            }
+            (Self::IndexedElement(index), tag) => {
+                match collection_len(tag).and_then(|len| resolve_index(len, *index)) {
+                    Some(i) => {
+                        collection_remove(tag, i);
+                        1
+                    }
+                    None => 0,
+                }
+            }
+            (Self::AllElements, tag) => {
+                let len = collection_len(tag).unwrap_or(0);
+                collection_clear(tag);
+                len
+            }
+            (Self::MatchElement(pattern), Tag::ListTag(list)) => {
+                let len = list.len();
+                list.retain(|e| !matches(pattern, e));
+                len - list.len()
+            }
+            (Self::MatchObject(name, pattern), Tag::CompoundTag(compound)) => {
+                match compound.get(name) {
+                    Some(child) if matches(pattern, &child) => {
+                        compound.remove(name);
+                        1
+                    }
+                    _ => 0,
+                }
+            }
+            _ => 0,
+        }
+    }
+}
+
+pub struct NbtPath {
+    original: String,
+    nodes: Vec<NbtPathNode>,
+    /// Where each node ends in the original path, to point errors at the node that matched nothing
+    node_ends: Vec<usize>,
+}
+impl NbtPath {
+    /// Parses a whole path, erroring on any trailing data
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut parser = TagParser::new(input);
+        let path = Self::read(&mut parser)?;
+        if parser.can_read() {
+            return Err(parser.error("Unexpected trailing data"));
+        }
+        Ok(path)
+    }
+
+    /// Reads a path up to the next space, so it can be read as part of a command
+    pub fn read(parser: &mut TagParser) -> Result<Self> {
+        let start = parser.cursor;
+        let mut nodes = Vec::new();
+        let mut node_ends = Vec::new();
+        while parser.peek().is_some_and(|c| c != ' ') {
+            nodes.push(NbtPathNode::read(parser, nodes.is_empty())?);
+            node_ends.push(parser.cursor - start);
+            if parser.peek().is_some_and(|c| !matches!(c, ' ' | '[' | '{')) {
+                parser.expect('.')?;
+            }
+        }
+        if nodes.is_empty() {
+            return Err(parser.error("Invalid NBT path element"));
+        }
+        Ok(Self {
+            original: parser.read_since(start).to_string(),
+            nodes,
+            node_ends,
+        })
+    }
+
+    fn not_found(&self, node: usize) -> anyhow::Error {
+        anyhow!(
+            "Found no elements matching {}",
+            &self.original[..self.node_ends[node]]
+        )
+    }
+
+    /// All tags the path selects, erroring at the first node that selects nothing
+    pub fn get(&self, tag: &Tag) -> Result<Vec<Tag>> {
+        let mut tags = vec![tag.clone()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            let mut children = Vec::new();
+            for tag in &tags {
+                node.get_tag(tag, &mut children);
+            }
+            if children.is_empty() {
+                return Err(self.not_found(i));
+            }
+            tags = children;
+        }
+        Ok(tags)
+    }
+
+    pub fn count_matching(&self, tag: &Tag) -> usize {
+        self.get(tag).map_or(0, |tags| tags.len())
+    }
+
+    /// Sets every tag the path selects, creating missing parents along the way. Returns how many
+    /// tags changed.
+    pub fn set(&self, tag: &mut Tag, value: &Tag) -> Result<usize> {
+        let mut parents_found = vec![0; self.nodes.len() - 1];
+        let changed = self.set_from(0, tag, value, &mut parents_found);
+        match parents_found.iter().position(|found| *found == 0) {
+            Some(node) => Err(self.not_found(node)),
+            None => Ok(changed),
+        }
+    }
+
+    fn set_from(
+        &self,
+        depth: usize,
+        tag: &mut Tag,
+        value: &Tag,
+        parents_found: &mut [usize],
+    ) -> usize {
+        let node = &self.nodes[depth];
+        let Some(next) = self.nodes.get(depth + 1) else {
+            return node.set_tag(tag, value);
+        };
+        node.for_each_child_mut(
+            tag,
+            Some(&|| next.create_preferred_parent_tag()),
+            &mut |child| {
+                parents_found[depth] += 1;
+                self.set_from(depth + 1, child, value, parents_found)
+            },
+        )
+    }
+
+    /// Removes every tag the path selects, returning how many were removed
+    pub fn remove(&self, tag: &mut Tag) -> usize {
+        self.remove_from(0, tag)
+    }
+
+    fn remove_from(&self, depth: usize, tag: &mut Tag) -> usize {
+        let node = &self.nodes[depth];
+        if depth + 1 == self.nodes.len() {
+            return node.remove_tag(tag);
+        }
+        node.for_each_child_mut(tag, None, &mut |child| self.remove_from(depth + 1, child))
+    }
+}
+impl Display for NbtPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.original)
+    }
+}
+
+fn matches(pattern: &CompoundTag, tag: &Tag) -> bool {
+    nbt_utils::compare_nbt(Some(&Tag::CompoundTag(pattern.clone())), Some(tag), true)
+}
+
+/// Lists only hold one type of tag, which is fixed by their first element
+fn list_accepts(list: &ListTag, value: &Tag) -> bool {
+    value.get_id() != 0 && (list.is_empty() || list.element_type() == value.get_id())
+}
+
+fn resolve_index(len: usize, index: i32) -> Option<usize> {
+    let index = if index < 0 {
+        len as i64 + index as i64
+    } else {
+        index as i64
+    };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+fn collection_len(tag: &Tag) -> Option<usize> {
+    match tag {
+        Tag::ListTag(list) => Some(list.len()),
+        Tag::ByteArrayTag(array) => Some(array.len()),
+        Tag::IntArrayTag(array) => Some(array.len()),
+        Tag::LongArrayTag(array) => Some(array.len()),
+        _ => None,
+    }
+}
+
+fn collection_get(tag: &Tag, i: usize) -> Option<Tag> {
+    match tag {
+        Tag::ListTag(list) => list.get(i).cloned(),
+        Tag::ByteArrayTag(array) => array.get(i).map(|e| Tag::ByteTag(*e)),
+        Tag::IntArrayTag(array) => array.get(i).map(|e| Tag::IntTag(*e)),
+        Tag::LongArrayTag(array) => array.get(i).map(|e| Tag::LongTag(*e)),
+        _ => None,
+    }
+}
+
+/// Replaces an element, failing if the value can't be stored in the collection. Arrays accept any
+/// numeric tag, converting it like vanilla.
+fn collection_set(tag: &mut Tag, i: usize, value: &Tag) -> bool {
+    collection_insert(tag, i, value, true)
+}
+
+fn collection_add(tag: &mut Tag, i: usize, value: &Tag) -> bool {
+    collection_insert(tag, i, value, false)
+}
+
+fn collection_insert(tag: &mut Tag, i: usize, value: &Tag, replace: bool) -> bool {
+    fn insert<T>(vec: &mut Vec<T>, i: usize, value: T, replace: bool) {
+        if replace {
+            vec[i] = value;
+        } else {
+            vec.insert(i, value);
+        }
+    }
+
+    let numeric = TagType::from(value).is_numeric();
+    match tag {
+        Tag::ListTag(list) if list_accepts(list, value) => insert(list, i, value.clone(), replace),
+        Tag::ByteArrayTag(array) if numeric => insert(array, i, value.get_as_int() as u8, replace),
+        Tag::IntArrayTag(array) if numeric => insert(array, i, value.get_as_int(), replace),
+        Tag::LongArrayTag(array) if numeric => insert(array, i, value.get_as_long(), replace),
+        _ => return false,
+    }
+    true
+}
+
+fn collection_remove(tag: &mut Tag, i: usize) {
+    match tag {
+        Tag::ListTag(list) => {
+            list.remove(i);
+        }
+        Tag::ByteArrayTag(array) => {
+            array.remove(i);
+        }
+        Tag::IntArrayTag(array) => {
+            array.remove(i);
+        }
+        Tag::LongArrayTag(array) => {
+            array.remove(i);
+        }
+        _ => {}
+    }
+}
+
+fn collection_clear(tag: &mut Tag) {
+    match tag {
+        Tag::ListTag(list) => list.clear(),
+        Tag::ByteArrayTag(array) => array.clear(),
+        Tag::IntArrayTag(array) => array.clear(),
+        Tag::LongArrayTag(array) => array.clear(),
+        _ => {}
+    }
+}
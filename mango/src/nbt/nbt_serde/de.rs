@@ -0,0 +1,391 @@
+use crate::nbt::nbt_serde::Error;
+use crate::nbt::tag::Tag;
+use crate::nbt::tag_type::TagType;
+use serde::de::value::StringDeserializer;
+use serde::de::{
+    DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, Unexpected,
+    VariantAccess, Visitor,
+};
+use serde::{de, forward_to_deserialize_any};
+use std::vec::IntoIter;
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn error(msg: impl std::fmt::Display) -> Error {
+    <Error as de::Error>::custom(msg)
+}
+
+/// Deserializes values out of a tag. Numbers are converted between widths like vanilla's numeric
+/// getters, so a field read as `i32` accepts any numeric tag that fits.
+pub struct TagDeserializer {
+    tag: Tag,
+}
+impl TagDeserializer {
+    pub fn new(tag: Tag) -> Self {
+        Self { tag }
+    }
+
+    fn unexpected(&self) -> Unexpected<'_> {
+        match &self.tag {
+            Tag::ByteTag(e) => Unexpected::Signed(*e as i8 as i64),
+            Tag::ShortTag(e) => Unexpected::Signed(*e as i64),
+            Tag::IntTag(e) => Unexpected::Signed(*e as i64),
+            Tag::LongTag(e) => Unexpected::Signed(*e),
+            Tag::FloatTag(e) => Unexpected::Float(*e as f64),
+            Tag::DoubleTag(e) => Unexpected::Float(*e),
+            Tag::StringTag(e) => Unexpected::Str(e),
+            Tag::CompoundTag(_) => Unexpected::Map,
+            Tag::EndTag(_) => Unexpected::Unit,
+            Tag::ListTag(_) | Tag::ByteArrayTag(_) | Tag::IntArrayTag(_) | Tag::LongArrayTag(_) => {
+                Unexpected::Seq
+            }
+        }
+    }
+}
+impl<'de> Deserializer<'de> for TagDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.tag {
+            Tag::EndTag(_) => visitor.visit_unit(),
+            Tag::ByteTag(e) => visitor.visit_i8(e as i8),
+            Tag::ShortTag(e) => visitor.visit_i16(e),
+            Tag::IntTag(e) => visitor.visit_i32(e),
+            Tag::LongTag(e) => visitor.visit_i64(e),
+            Tag::FloatTag(e) => visitor.visit_f32(e),
+            Tag::DoubleTag(e) => visitor.visit_f64(e),
+            Tag::StringTag(e) => visitor.visit_string(e),
+            Tag::ByteArrayTag(e) => {
+                visitor.visit_seq(ListAccess::new(e.into_iter().map(Tag::ByteTag).collect()))
+            }
+            Tag::IntArrayTag(e) => {
+                visitor.visit_seq(ListAccess::new(e.into_iter().map(Tag::IntTag).collect()))
+            }
+            Tag::LongArrayTag(e) => {
+                visitor.visit_seq(ListAccess::new(e.into_iter().map(Tag::LongTag).collect()))
+            }
+            Tag::ListTag(e) => visitor.visit_seq(ListAccess::new(e.to_vec())),
+            Tag::CompoundTag(e) => visitor.visit_map(CompoundAccess::new(
+                e.iter()
+                    .map(|(key, tag)| (key.clone(), tag.clone()))
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Booleans are bytes in NBT, but any non-zero number counts as true like vanilla
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match &self.tag {
+            tag if TagType::from(tag).is_numeric() => visitor.visit_bool(tag.get_as_long() != 0),
+            _ => Err(de::Error::invalid_type(self.unexpected(), &visitor)),
+        }
+    }
+
+    /// Bytes are signed in NBT, but a `u8` field should see the raw byte instead of failing on
+    /// negative values
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.tag {
+            Tag::ByteTag(e) => visitor.visit_u8(e),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.tag {
+            Tag::ByteArrayTag(e) => visitor.visit_byte_buf(e),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        match self.tag {
+            Tag::StringTag(variant) => visitor.visit_enum(VariantNameAccess { variant }),
+            Tag::CompoundTag(compound) if compound.len() == 1 => {
+                let (variant, value) = compound.iter().next().unwrap();
+                visitor.visit_enum(VariantTagAccess {
+                    variant: variant.clone(),
+                    value: value.clone(),
+                })
+            }
+            _ => Err(error(
+                "Expected a string or a compound with a single entry for an enum",
+            )),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u16 u32 u64 u128 f32 f64 char str string
+        seq tuple tuple_struct map struct identifier
+    }
+}
+impl IntoDeserializer<'_, Error> for TagDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+struct ListAccess {
+    tags: IntoIter<Tag>,
+}
+impl ListAccess {
+    fn new(tags: Vec<Tag>) -> Self {
+        Self {
+            tags: tags.into_iter(),
+        }
+    }
+}
+impl<'de> SeqAccess<'de> for ListAccess {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        self.tags
+            .next()
+            .map(|tag| seed.deserialize(TagDeserializer::new(tag)))
+            .transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.tags.len())
+    }
+}
+
+struct CompoundAccess {
+    entries: IntoIter<(String, Tag)>,
+    value: Option<Tag>,
+}
+impl CompoundAccess {
+    fn new(entries: Vec<(String, Tag)>) -> Self {
+        Self {
+            entries: entries.into_iter(),
+            value: None,
+        }
+    }
+}
+impl<'de> MapAccess<'de> for CompoundAccess {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.entries.next() {
+            None => Ok(None),
+            Some((key, value)) => {
+                self.value = Some(value);
+                seed.deserialize(KeyDeserializer { key }).map(Some)
+            }
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| error("Compound value read before its key"))?;
+        seed.deserialize(TagDeserializer::new(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.entries.len())
+    }
+}
+
+/// Deserializes a compound key, parsing it back into the number, bool or char it was serialized
+/// from when one is asked for
+struct KeyDeserializer {
+    key: String,
+}
+impl KeyDeserializer {
+    fn parse<T: std::str::FromStr>(&self) -> Result<T> {
+        self.key
+            .parse()
+            .map_err(|_| de::Error::invalid_value(Unexpected::Str(&self.key), &"a parsable key"))
+    }
+}
+macro_rules! deserialize_parsed_key {
+    ($($deserialize:ident => $visit:ident,)*) => {
+        $(
+            fn $deserialize<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+                visitor.$visit(self.parse()?)
+            }
+        )*
+    };
+}
+impl<'de> Deserializer<'de> for KeyDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.key)
+    }
+
+    deserialize_parsed_key! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_i128 => visit_i128,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_u128 => visit_u128,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(self.key.into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        f32 f64 str string bytes byte_buf unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+/// A variant stored as just its name: a unit variant, or a newtype variant holding `None`
+struct VariantNameAccess {
+    variant: String,
+}
+impl<'de> EnumAccess<'de> for VariantNameAccess {
+    type Error = Error;
+    type Variant = MissingValue;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let variant = seed.deserialize(StringDeserializer::<Error>::new(self.variant))?;
+        Ok((variant, MissingValue))
+    }
+}
+
+/// The value of a variant stored as just its name, which can only be read as `None`
+struct MissingValue;
+impl<'de> VariantAccess<'de> for MissingValue {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        Err(de::Error::invalid_type(Unexpected::UnitVariant, &visitor))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        Err(de::Error::invalid_type(Unexpected::UnitVariant, &visitor))
+    }
+}
+impl<'de> Deserializer<'de> for MissingValue {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        Err(de::Error::invalid_type(Unexpected::UnitVariant, &visitor))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_none()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+struct VariantTagAccess {
+    variant: String,
+    value: Tag,
+}
+impl<'de> EnumAccess<'de> for VariantTagAccess {
+    type Error = Error;
+    type Variant = TagDeserializer;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self::Variant)> {
+        let variant = seed.deserialize(StringDeserializer::<Error>::new(self.variant))?;
+        Ok((variant, TagDeserializer::new(self.value)))
+    }
+}
+impl<'de> VariantAccess<'de> for TagDeserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.deserialize_map(visitor)
+    }
+}
@@ -0,0 +1,253 @@
+//! Serde data format for NBT so `#[derive(Serialize, Deserialize)]` structs can be converted to and
+//! from [CompoundTag] and binary NBT without walking the tags by hand.
+//!
+//! Type mapping follows vanilla's codecs:
+//! - `bool` is stored as a byte tag, and any numeric tag is read back as `true` when non-zero
+//! - structs and maps are compound tags, sequences are list tags and `Option::None` fields are
+//!   omitted from the compound entirely
+//! - `Vec<i32>`/`Vec<i64>` are list tags unless annotated with [int_array] or [long_array], and
+//!   bytes serialized via `serialize_bytes` (or [byte_array]) are byte array tags
+//! - map keys are strings, with numbers, bools and chars parsed back from them when read
+//! - unit enum variants and newtype variants holding `None` are strings, other variants are
+//!   compounds keyed by the variant name
+//!
+//! Deserializing accepts both list tags and typed arrays wherever a sequence is expected.
+
+mod de;
+mod ser;
+
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::nbt_accounter::NbtAccounter;
+use crate::nbt::tag::Tag;
+use crate::nbt::{nbt_io, DataInput, DataOutput};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::borrow::Borrow;
+use std::fmt::{Display, Formatter};
+
+pub use de::TagDeserializer;
+pub use ser::TagSerializer;
+
+const BYTE_ARRAY_NAME: &str = "__nbt_byte_array";
+const INT_ARRAY_NAME: &str = "__nbt_int_array";
+const LONG_ARRAY_NAME: &str = "__nbt_long_array";
+
+#[derive(Debug)]
+pub struct Error(String);
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+impl std::error::Error for Error {}
+impl serde::ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+impl serde::de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+pub fn to_tag<T: Serialize + ?Sized>(value: &T) -> anyhow::Result<Tag> {
+    value
+        .serialize(TagSerializer)?
+        .ok_or_else(|| anyhow::anyhow!("Value serialized to nothing"))
+}
+
+pub fn to_compound<T: Serialize + ?Sized>(value: &T) -> anyhow::Result<CompoundTag> {
+    match to_tag(value)? {
+        Tag::CompoundTag(compound) => Ok(compound),
+        tag => Err(anyhow::anyhow!(
+            "Expected value to serialize to a compound tag, got {}",
+            tag
+        )),
+    }
+}
+
+pub fn from_tag<T: DeserializeOwned>(tag: Tag) -> anyhow::Result<T> {
+    Ok(T::deserialize(TagDeserializer::new(tag))?)
+}
+
+pub fn from_compound<T: DeserializeOwned>(compound: CompoundTag) -> anyhow::Result<T> {
+    from_tag(Tag::CompoundTag(compound))
+}
+
+/// Writes a value as an uncompressed root compound. Compression can be layered on the output.
+pub async fn to_writer<T: Serialize + ?Sized>(
+    value: &T,
+    output: &mut impl DataOutput,
+) -> anyhow::Result<()> {
+    nbt_io::write(&to_compound(value)?, output).await
+}
+
+/// Reads a value from an uncompressed root compound. Decompression can be layered on the reader.
+pub async fn from_reader<T: DeserializeOwned>(
+    reader: &mut impl DataInput,
+    nbt_accounter: impl Borrow<NbtAccounter>,
+) -> anyhow::Result<T> {
+    from_compound(nbt_io::read(reader, nbt_accounter).await?)
+}
+
+/// `#[serde(with = "byte_array")]` to store bytes as a byte array tag
+pub mod byte_array {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(super::BYTE_ARRAY_NAME, value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        Vec::<u8>::deserialize(deserializer)
+    }
+}
+
+/// `#[serde(with = "int_array")]` to store integers as an int array tag instead of a list tag
+pub mod int_array {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &[i32], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(super::INT_ARRAY_NAME, value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<i32>, D::Error> {
+        Vec::<i32>::deserialize(deserializer)
+    }
+}
+
+/// `#[serde(with = "long_array")]` to store longs as a long array tag instead of a list tag
+pub mod long_array {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &[i64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(super::LONG_ARRAY_NAME, value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<i64>, D::Error> {
+        Vec::<i64>::deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::list_tag::ListTag;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Section {
+        y: i8,
+        #[serde(with = "long_array")]
+        states: Vec<i64>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Target {
+        Spawn,
+        Player(Option<String>),
+        Position { x: i32, z: i32 },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Chunk {
+        generated: bool,
+        #[serde(with = "int_array")]
+        heights: Vec<i32>,
+        sections: Vec<Section>,
+        counts: BTreeMap<i32, i16>,
+        spawn: Target,
+        owner: Target,
+        last_owner: Target,
+        center: Target,
+    }
+
+    fn chunk() -> Chunk {
+        Chunk {
+            generated: true,
+            heights: vec![64, -3, 320],
+            sections: vec![
+                Section {
+                    y: -4,
+                    states: vec![1, i64::MIN],
+                },
+                Section {
+                    y: 0,
+                    states: vec![],
+                },
+            ],
+            counts: BTreeMap::from([(-1, 2), (7, 3)]),
+            spawn: Target::Spawn,
+            owner: Target::Player(Some("Steve".to_string())),
+            last_owner: Target::Player(None),
+            center: Target::Position { x: 1, z: -1 },
+        }
+    }
+
+    #[test]
+    fn values_round_trip() {
+        let compound = to_compound(&chunk()).unwrap();
+        assert_eq!(from_compound::<Chunk>(compound).unwrap(), chunk());
+    }
+
+    #[test]
+    fn values_are_stored_as_vanilla_tags() {
+        let compound = to_compound(&chunk()).unwrap();
+        assert!(compound.get("generated") == Some(Tag::ByteTag(1)));
+        assert!(compound.get("heights") == Some(Tag::IntArrayTag(vec![64, -3, 320])));
+
+        let sections = compound.get_list("sections");
+        assert_eq!(sections.len(), 2);
+        let Tag::CompoundTag(section) = &sections[0] else {
+            panic!("Section is not a compound: {}", sections[0]);
+        };
+        assert!(section.get("y") == Some(Tag::ByteTag(-4i8 as u8)));
+        assert!(section.get("states") == Some(Tag::LongArrayTag(vec![1, i64::MIN])));
+
+        let counts = compound.get_compound("counts");
+        assert_eq!(counts.get_all_keys(), ["-1", "7"]);
+        assert!(counts.get("7") == Some(Tag::ShortTag(3)));
+
+        assert_eq!(compound.get_string("spawn"), "Spawn");
+        assert_eq!(compound.get_compound("owner").get_string("Player"), "Steve");
+        assert_eq!(compound.get_string("last_owner"), "Player");
+        let center = compound.get_compound("center").get_compound("Position");
+        assert_eq!(center.get_int("x"), 1);
+    }
+
+    #[test]
+    fn bools_are_read_from_any_numeric_tag() {
+        #[derive(Deserialize)]
+        struct Flags {
+            byte: bool,
+            int: bool,
+            zero: bool,
+        }
+        let mut compound = CompoundTag::default();
+        compound.put("byte", Tag::ByteTag(2));
+        compound.put("int", Tag::IntTag(-1));
+        compound.put("zero", Tag::ShortTag(0));
+        let flags: Flags = from_compound(compound).unwrap();
+        assert!(flags.byte && flags.int && !flags.zero);
+    }
+
+    #[test]
+    fn arrays_are_read_from_list_tags() {
+        let mut compound = to_compound(&chunk()).unwrap();
+        compound.put(
+            "heights",
+            Tag::ListTag(ListTag::new(vec![Tag::IntTag(64), Tag::IntTag(-3)])),
+        );
+        let chunk: Chunk = from_compound(compound).unwrap();
+        assert_eq!(chunk.heights, [64, -3]);
+    }
+
+    #[test]
+    fn unparsable_map_keys_are_rejected() {
+        let mut counts = CompoundTag::default();
+        counts.put("seven", Tag::ShortTag(3));
+        assert!(from_tag::<BTreeMap<i32, i16>>(Tag::CompoundTag(counts)).is_err());
+    }
+}
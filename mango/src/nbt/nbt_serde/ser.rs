@@ -0,0 +1,531 @@
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::list_tag::ListTag;
+use crate::nbt::nbt_serde::{Error, BYTE_ARRAY_NAME, INT_ARRAY_NAME, LONG_ARRAY_NAME};
+use crate::nbt::tag::Tag;
+use crate::nbt::tag_type::TagType;
+use serde::ser::{
+    Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+};
+use serde::{ser, Serialize, Serializer};
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn error(msg: impl std::fmt::Display) -> Error {
+    <Error as ser::Error>::custom(msg)
+}
+
+/// Serializes values into tags. `None` serializes to nothing so optional struct fields can be
+/// left out of the compound.
+pub struct TagSerializer;
+impl Serializer for TagSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = VariantSerializer<ListSerializer>;
+    type SerializeMap = CompoundSerializer;
+    type SerializeStruct = CompoundSerializer;
+    type SerializeStructVariant = VariantSerializer<CompoundSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
+        Ok(Some(Tag::ByteTag(v as u8)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok> {
+        Ok(Some(Tag::ByteTag(v as u8)))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok> {
+        Ok(Some(Tag::ShortTag(v)))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok> {
+        Ok(Some(Tag::IntTag(v)))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
+        Ok(Some(Tag::LongTag(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok> {
+        Ok(Some(Tag::ByteTag(v)))
+    }
+
+    /// NBT has no unsigned types, so unsigned values widen to the next signed type
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok> {
+        Ok(Some(Tag::IntTag(v as i32)))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok> {
+        Ok(Some(Tag::LongTag(v as i64)))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok> {
+        i64::try_from(v)
+            .map(|v| Some(Tag::LongTag(v)))
+            .map_err(|_| error(format!("{} does not fit in a long tag", v)))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok> {
+        Ok(Some(Tag::FloatTag(v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok> {
+        Ok(Some(Tag::DoubleTag(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok> {
+        Ok(Some(Tag::StringTag(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok> {
+        Ok(Some(Tag::StringTag(v.to_string())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
+        Ok(Some(Tag::ByteArrayTag(v.to_vec())))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok> {
+        Ok(Some(Tag::CompoundTag(CompoundTag::default())))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        let tag = value.serialize(self)?;
+        match name {
+            BYTE_ARRAY_NAME => to_array(tag, name, Tag::try_as_byte_tag).map(Tag::ByteArrayTag),
+            INT_ARRAY_NAME => to_array(tag, name, Tag::try_as_int_tag).map(Tag::IntArrayTag),
+            LONG_ARRAY_NAME => to_array(tag, name, Tag::try_as_long_tag).map(Tag::LongArrayTag),
+            _ => return Ok(tag),
+        }
+        .map(Some)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok> {
+        // A variant holding `None` is stored as just its name, like a unit variant
+        Ok(Some(match value.serialize(self)? {
+            Some(tag) => Tag::CompoundTag(CompoundTag::from_iter([(variant.to_string(), tag)])),
+            None => Tag::StringTag(variant.to_string()),
+        }))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(ListSerializer {
+            tags: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Ok(VariantSerializer {
+            variant,
+            inner: self.serialize_seq(Some(len))?,
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Ok(CompoundSerializer::default())
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(CompoundSerializer::default())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Ok(VariantSerializer {
+            variant,
+            inner: CompoundSerializer::default(),
+        })
+    }
+}
+
+/// Converts a list of numeric tags into the elements of a typed array
+fn to_array<T>(tag: Option<Tag>, name: &str, extract: impl Fn(Tag) -> Option<T>) -> Result<Vec<T>> {
+    let Some(Tag::ListTag(list)) = tag else {
+        return Err(error(format!("Expected a sequence for {}", name)));
+    };
+    list.iter()
+        .map(|tag| {
+            extract(tag.clone()).ok_or_else(|| {
+                error(format!(
+                    "Can't insert {:?} into {}",
+                    TagType::from(tag),
+                    name
+                ))
+            })
+        })
+        .collect()
+}
+
+pub struct ListSerializer {
+    tags: Vec<Tag>,
+}
+impl ListSerializer {
+    fn push(&mut self, tag: Option<Tag>) -> Result<()> {
+        let tag = tag.ok_or_else(|| error("List elements can't be empty"))?;
+        if let Some(first) = self.tags.first() {
+            if first.get_id() != tag.get_id() {
+                return Err(error(format!(
+                    "Can't insert {:?} into list of {:?}",
+                    TagType::from(&tag),
+                    TagType::from(first)
+                )));
+            }
+        }
+        self.tags.push(tag);
+        Ok(())
+    }
+
+    fn finish(self) -> Tag {
+        Tag::ListTag(ListTag::new(self.tags))
+    }
+}
+impl SerializeSeq for ListSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        self.push(value.serialize(TagSerializer)?)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(Some(self.finish()))
+    }
+}
+impl SerializeTuple for ListSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        SerializeSeq::end(self)
+    }
+}
+impl SerializeTupleStruct for ListSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        SerializeSeq::end(self)
+    }
+}
+
+#[derive(Default)]
+pub struct CompoundSerializer {
+    entries: Vec<(String, Tag)>,
+    next_key: Option<String>,
+}
+impl CompoundSerializer {
+    fn put(&mut self, key: String, tag: Option<Tag>) {
+        if let Some(tag) = tag {
+            self.entries.push((key, tag));
+        }
+    }
+
+    fn finish(self) -> Tag {
+        Tag::CompoundTag(CompoundTag::from_iter(self.entries))
+    }
+}
+impl SerializeMap for CompoundSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        self.next_key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let key = self
+            .next_key
+            .take()
+            .ok_or_else(|| error("Map value serialized before its key"))?;
+        let tag = value.serialize(TagSerializer)?;
+        self.put(key, tag);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(Some(self.finish()))
+    }
+}
+impl SerializeStruct for CompoundSerializer {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        let tag = value.serialize(TagSerializer)?;
+        self.put(key.to_string(), tag);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(Some(self.finish()))
+    }
+}
+
+/// Wraps the serialized variant content in a compound keyed by the variant name
+pub struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+impl<S> VariantSerializer<S> {
+    fn wrap(variant: &'static str, tag: Tag) -> Option<Tag> {
+        Some(Tag::CompoundTag(CompoundTag::from_iter([(
+            variant.to_string(),
+            tag,
+        )])))
+    }
+}
+impl SerializeTupleVariant for VariantSerializer<ListSerializer> {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(Self::wrap(self.variant, self.inner.finish()))
+    }
+}
+impl SerializeStructVariant for VariantSerializer<CompoundSerializer> {
+    type Ok = Option<Tag>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok> {
+        Ok(Self::wrap(self.variant, self.inner.finish()))
+    }
+}
+
+/// Compound keys must be strings. Numbers and chars are accepted and stringified like JSON.
+struct KeySerializer;
+impl Serializer for KeySerializer {
+    type Ok = String;
+    type Error = Error;
+    type SerializeSeq = Impossible<String, Error>;
+    type SerializeTuple = Impossible<String, Error>;
+    type SerializeTupleStruct = Impossible<String, Error>;
+    type SerializeTupleVariant = Impossible<String, Error>;
+    type SerializeMap = Impossible<String, Error>;
+    type SerializeStruct = Impossible<String, Error>;
+    type SerializeStructVariant = Impossible<String, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<String> {
+        Err(error("Compound keys can't be floats"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<String> {
+        Err(error("Compound keys can't be doubles"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<String> {
+        Err(error("Compound keys can't be bytes"))
+    }
+
+    fn serialize_none(self) -> Result<String> {
+        Err(error("Compound keys can't be empty"))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<String> {
+        Err(error("Compound keys can't be empty"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<String> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String> {
+        Ok(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String> {
+        Err(error("Compound keys must be strings"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(error("Compound keys must be strings"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(error("Compound keys must be strings"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(error("Compound keys must be strings"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(error("Compound keys must be strings"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(error("Compound keys must be strings"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(error("Compound keys must be strings"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(error("Compound keys must be strings"))
+    }
+}
@@ -1,4 +1,10 @@
 use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::tag::Tag;
+use crate::shared_constants;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+
+/// Data version assumed for data saved before versions were recorded, which is that of 1.12.2
+pub const NO_DATA_VERSION: i32 = 1343;
 
 pub fn get_data_version(compound_tag: &CompoundTag, default: i32) -> i32 {
     let res = compound_tag.get_int("DataVersion");
@@ -8,3 +14,50 @@ pub fn get_data_version(compound_tag: &CompoundTag, default: i32) -> i32 {
         res
     }
 }
+
+/// Marks data as being at the current version, for when it is saved
+pub fn add_current_data_version(compound_tag: &mut CompoundTag) {
+    compound_tag.put(
+        "DataVersion",
+        Tag::IntTag(shared_constants::get_current_data_version()),
+    );
+}
+
+/// Data version of data in any format, such as stats and advancements JSON
+pub fn get_dynamic_data_version<T: Clone>(dynamic: &Dynamic<T>, default: i32) -> i32 {
+    dynamic.get("DataVersion").as_int(default)
+}
+
+/// Checks whether `actual` contains everything in `expected`. Compounds only need the expected
+/// keys, and with `partial_lists` every expected list element only needs to match some element of
+/// the actual list.
+pub fn compare_nbt(expected: Option<&Tag>, actual: Option<&Tag>, partial_lists: bool) -> bool {
+    let Some(expected) = expected else {
+        return true;
+    };
+    let Some(actual) = actual else {
+        return false;
+    };
+    match (expected, actual) {
+        (Tag::CompoundTag(expected), Tag::CompoundTag(actual)) => {
+            expected.get_all_keys().iter().all(|key| {
+                compare_nbt(
+                    expected.get(key).as_ref(),
+                    actual.get(key).as_ref(),
+                    partial_lists,
+                )
+            })
+        }
+        (Tag::ListTag(expected), Tag::ListTag(actual)) if partial_lists => {
+            if expected.is_empty() {
+                return actual.is_empty();
+            }
+            expected.iter().all(|expected| {
+                actual
+                    .iter()
+                    .any(|actual| compare_nbt(Some(expected), Some(actual), partial_lists))
+            })
+        }
+        _ => expected == actual,
+    }
+}
@@ -0,0 +1,160 @@
+//! Stringified NBT (SNBT) output. [Display] gives the compact form vanilla uses in commands and
+//! [pretty_print] gives the indented form used for debugging and test fixtures. Both can be read
+//! back by [crate::nbt::tag_parser].
+
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::list_tag::ListTag;
+use crate::nbt::string_tag;
+use crate::nbt::tag::Tag;
+use crate::nbt::tag_parser;
+use std::fmt::{Display, Formatter, Result, Write};
+
+const INDENTATION: &str = "    ";
+
+/// Formats a tag over multiple lines with keys sorted, like vanilla's SnbtPrinterTagVisitor
+pub fn pretty_print(tag: &Tag) -> String {
+    let mut res = String::new();
+    SnbtPrinter::pretty(&mut res)
+        .write_tag(tag)
+        .expect("Writing to a String should not fail");
+    res
+}
+
+struct SnbtPrinter<'a, W: Write> {
+    output: &'a mut W,
+    indentation: &'static str,
+    depth: usize,
+}
+impl<'a, W: Write> SnbtPrinter<'a, W> {
+    fn compact(output: &'a mut W) -> Self {
+        Self {
+            output,
+            indentation: "",
+            depth: 0,
+        }
+    }
+
+    fn pretty(output: &'a mut W) -> Self {
+        Self {
+            output,
+            indentation: INDENTATION,
+            depth: 0,
+        }
+    }
+
+    fn is_pretty(&self) -> bool {
+        !self.indentation.is_empty()
+    }
+
+    fn new_line(&mut self) -> Result {
+        if self.is_pretty() {
+            self.output.write_char('\n')?;
+            for _ in 0..self.depth {
+                self.output.write_str(self.indentation)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_tag(&mut self, tag: &Tag) -> Result {
+        match tag {
+            Tag::EndTag(_) => self.output.write_str("END"),
+            Tag::ByteTag(e) => write!(self.output, "{}b", *e as i8),
+            Tag::ShortTag(e) => write!(self.output, "{}s", e),
+            Tag::IntTag(e) => write!(self.output, "{}", e),
+            Tag::LongTag(e) => write!(self.output, "{}L", e),
+            // Debug keeps the decimal point on whole numbers, e.g. 1.0 instead of 1
+            Tag::FloatTag(e) => write!(self.output, "{:?}f", e),
+            Tag::DoubleTag(e) => write!(self.output, "{:?}d", e),
+            Tag::ByteArrayTag(e) => {
+                self.write_array('B', e.iter().map(|b| format!("{}B", *b as i8)))
+            }
+            Tag::StringTag(e) => self.output.write_str(&string_tag::quote_and_escape(e)),
+            Tag::ListTag(e) => self.write_list(e),
+            Tag::CompoundTag(e) => self.write_compound(e),
+            Tag::IntArrayTag(e) => self.write_array('I', e.iter().map(i32::to_string)),
+            Tag::LongArrayTag(e) => self.write_array('L', e.iter().map(|l| format!("{}L", l))),
+        }
+    }
+
+    fn write_array(&mut self, array_type: char, elements: impl Iterator<Item = String>) -> Result {
+        let separator = if self.is_pretty() { ", " } else { "," };
+        write!(self.output, "[{};", array_type)?;
+        for (i, element) in elements.enumerate() {
+            if i == 0 && self.is_pretty() {
+                self.output.write_char(' ')?;
+            } else if i != 0 {
+                self.output.write_str(separator)?;
+            }
+            self.output.write_str(&element)?;
+        }
+        self.output.write_char(']')
+    }
+
+    fn write_list(&mut self, list: &ListTag) -> Result {
+        if list.is_empty() {
+            return self.output.write_str("[]");
+        }
+        self.output.write_char('[')?;
+        self.depth += 1;
+        for (i, tag) in list.iter().enumerate() {
+            if i != 0 {
+                self.output.write_char(',')?;
+            }
+            self.new_line()?;
+            self.write_tag(tag)?;
+        }
+        self.depth -= 1;
+        self.new_line()?;
+        self.output.write_char(']')
+    }
+
+    fn write_compound(&mut self, compound: &CompoundTag) -> Result {
+        if compound.is_empty() {
+            return self.output.write_str("{}");
+        }
+        let mut entries: Vec<_> = compound.iter().collect();
+        entries.sort_by_key(|(key, _)| *key);
+
+        self.output.write_char('{')?;
+        self.depth += 1;
+        for (i, (key, tag)) in entries.into_iter().enumerate() {
+            if i != 0 {
+                self.output.write_char(',')?;
+            }
+            self.new_line()?;
+            self.output.write_str(&handle_escape(key))?;
+            self.output
+                .write_str(if self.is_pretty() { ": " } else { ":" })?;
+            self.write_tag(tag)?;
+        }
+        self.depth -= 1;
+        self.new_line()?;
+        self.output.write_char('}')
+    }
+}
+
+/// Keys are only quoted when they contain characters that can't be read back unquoted
+fn handle_escape(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(tag_parser::is_allowed_in_unquoted_string) {
+        key.to_string()
+    } else {
+        string_tag::quote_and_escape(key)
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        SnbtPrinter::compact(f).write_tag(self)
+    }
+}
+impl Display for CompoundTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        SnbtPrinter::compact(f).write_compound(self)
+    }
+}
+impl Display for ListTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        SnbtPrinter::compact(f).write_list(self)
+    }
+}
@@ -0,0 +1,65 @@
+use crate::nbt::tag_type::TagType;
+
+/// What to do after visiting a value
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ValueResult {
+    Continue,
+    /// Skip the rest of the enclosing container
+    Break,
+    /// Stop parsing entirely
+    Halt,
+}
+
+/// What to do with an entry of a compound or element of a list before it is read
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EntryResult {
+    /// Parse the entry, visiting its value
+    Enter,
+    /// Skip over the entry without visiting it
+    Skip,
+    /// Skip the entry and the rest of the enclosing container
+    Break,
+    /// Stop parsing entirely
+    Halt,
+}
+
+/// Visitor driven directly by the NBT reader via [TagType::parse], so callers can pick out the
+/// fields they need and skip everything else without building a [crate::nbt::tag::Tag] tree.
+///
+/// Compound entries are visited in two steps: first by type only, then by type and name. Skipping
+/// at the first step avoids even reading the name.
+pub trait StreamTagVisitor {
+    fn visit_end(&mut self) -> ValueResult;
+
+    fn visit_string(&mut self, value: String) -> ValueResult;
+
+    fn visit_byte(&mut self, value: u8) -> ValueResult;
+
+    fn visit_short(&mut self, value: i16) -> ValueResult;
+
+    fn visit_int(&mut self, value: i32) -> ValueResult;
+
+    fn visit_long(&mut self, value: i64) -> ValueResult;
+
+    fn visit_float(&mut self, value: f32) -> ValueResult;
+
+    fn visit_double(&mut self, value: f64) -> ValueResult;
+
+    fn visit_byte_array(&mut self, value: Vec<u8>) -> ValueResult;
+
+    fn visit_int_array(&mut self, value: Vec<i32>) -> ValueResult;
+
+    fn visit_long_array(&mut self, value: Vec<i64>) -> ValueResult;
+
+    fn visit_list(&mut self, element_type: TagType, length: i32) -> ValueResult;
+
+    fn visit_element(&mut self, element_type: TagType, index: i32) -> EntryResult;
+
+    fn visit_entry(&mut self, tag_type: TagType) -> EntryResult;
+
+    fn visit_entry_named(&mut self, tag_type: TagType, name: &str) -> EntryResult;
+
+    fn visit_container_end(&mut self) -> ValueResult;
+
+    fn visit_root_entry(&mut self, tag_type: TagType) -> ValueResult;
+}
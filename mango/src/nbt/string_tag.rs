@@ -1,16 +1,46 @@
-use crate::nbt::DataInput;
-use anyhow::Result;
-use std::io::SeekFrom;
-use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use crate::nbt::{skip_bytes, DataInput, DataOutput};
+use anyhow::{anyhow, Result};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-/// Skip some bytes
-///
-/// https://stackoverflow.com/questions/59826242/how-to-skip-n-bytes-with-read-without-allocation
-pub async fn skip_string(reader: &mut DataInput) -> Result<()> {
-    let skip_bytes = reader.read_u16().await?;
-    reader
-        .get_mut()
-        .seek(SeekFrom::Current(skip_bytes as i64))
-        .await?;
+/// Skip a string by reading past it, as the underlying reader may not support seeking
+pub async fn skip_string(reader: &mut impl DataInput) -> Result<()> {
+    let len = reader.read_u16().await?;
+    skip_bytes(reader, len as u64).await
+}
+
+/// Writes a modified UTF-8 string, prefixed by its length in bytes
+pub async fn write_string(output: &mut impl DataOutput, value: &str) -> Result<()> {
+    let buf = cesu8::to_java_cesu8(value);
+    if buf.len() > u16::MAX as usize {
+        return Err(anyhow!(
+            "String is too long to write as NBT: {} bytes",
+            buf.len()
+        ));
+    }
+    output.write_u16(buf.len() as u16).await?;
+    output.write_all(&buf).await?;
     Ok(())
 }
+
+/// Quotes a string for SNBT, preferring double quotes unless the string contains them
+pub fn quote_and_escape(value: &str) -> String {
+    let mut res = String::with_capacity(value.len() + 2);
+    let mut quote = None;
+    for c in value.chars() {
+        if c == '\\' {
+            res.push('\\');
+        } else if c == '"' || c == '\'' {
+            if quote.is_none() {
+                quote = Some(if c == '"' { '\'' } else { '"' });
+            }
+            if quote == Some(c) {
+                res.push('\\');
+            }
+        }
+        res.push(c);
+    }
+    let quote = quote.unwrap_or('"');
+    res.insert(0, quote);
+    res.push(quote);
+    res
+}
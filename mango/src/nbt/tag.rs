@@ -1,13 +1,17 @@
 use crate::nbt::compound_tag::CompoundTag;
 use crate::nbt::end_tag::EndTag;
 use crate::nbt::list_tag::ListTag;
+use crate::nbt::string_tag;
+use crate::nbt::DataOutput;
+use anyhow::Result;
 use serde::Serialize;
 use strum::EnumTryAs;
+use tokio::io::AsyncWriteExt;
 
 /// We model Tags as an enum as it is unlikely we would need to customize this
 ///
 /// Simple tags are represented as their primitive types, while complex tags have their own structs
-#[derive(EnumTryAs, Serialize)]
+#[derive(Clone, PartialEq, EnumTryAs, Serialize)]
 pub enum Tag {
     EndTag(EndTag),
     ByteTag(u8),
@@ -24,6 +28,58 @@ pub enum Tag {
     LongArrayTag(Vec<i64>),
 }
 impl Tag {
+    /// Id of the tag type as written in binary NBT
+    pub fn get_id(&self) -> u8 {
+        match self {
+            Tag::EndTag(_) => 0,
+            Tag::ByteTag(_) => 1,
+            Tag::ShortTag(_) => 2,
+            Tag::IntTag(_) => 3,
+            Tag::LongTag(_) => 4,
+            Tag::FloatTag(_) => 5,
+            Tag::DoubleTag(_) => 6,
+            Tag::ByteArrayTag(_) => 7,
+            Tag::StringTag(_) => 8,
+            Tag::ListTag(_) => 9,
+            Tag::CompoundTag(_) => 10,
+            Tag::IntArrayTag(_) => 11,
+            Tag::LongArrayTag(_) => 12,
+        }
+    }
+
+    /// Writes the payload of this tag. The type id and name are written by the enclosing tag.
+    pub async fn write(&self, output: &mut impl DataOutput) -> Result<()> {
+        match self {
+            Tag::EndTag(_) => {}
+            Tag::ByteTag(e) => output.write_u8(*e).await?,
+            Tag::ShortTag(e) => output.write_i16(*e).await?,
+            Tag::IntTag(e) => output.write_i32(*e).await?,
+            Tag::LongTag(e) => output.write_i64(*e).await?,
+            Tag::FloatTag(e) => output.write_f32(*e).await?,
+            Tag::DoubleTag(e) => output.write_f64(*e).await?,
+            Tag::ByteArrayTag(e) => {
+                output.write_i32(e.len() as i32).await?;
+                output.write_all(e).await?;
+            }
+            Tag::StringTag(e) => string_tag::write_string(output, e).await?,
+            Tag::ListTag(e) => Box::pin(e.write(output)).await?,
+            Tag::CompoundTag(e) => Box::pin(e.write(output)).await?,
+            Tag::IntArrayTag(e) => {
+                output.write_i32(e.len() as i32).await?;
+                for i in e {
+                    output.write_i32(*i).await?;
+                }
+            }
+            Tag::LongArrayTag(e) => {
+                output.write_i32(e.len() as i32).await?;
+                for i in e {
+                    output.write_i64(*i).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_as_int(&self) -> i32 {
         match self {
             Tag::ByteTag(e) => *e as i32,
@@ -0,0 +1,395 @@
+//! Parser for stringified NBT (SNBT), e.g. `{foo:1b,bar:[I;1,2,3]}`, following vanilla's TagParser
+
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::list_tag::ListTag;
+use crate::nbt::tag::Tag;
+use crate::nbt::tag_type::TagType;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::sync::LazyLock;
+
+const ELEMENT_SEPARATOR: char = ',';
+const NAME_VALUE_SEPARATOR: char = ':';
+const LIST_OPEN: char = '[';
+const LIST_CLOSE: char = ']';
+const STRUCT_OPEN: char = '{';
+const STRUCT_CLOSE: char = '}';
+
+static DOUBLE_PATTERN_NOSUFFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^[-+]?(?:[0-9]+[.]|[0-9]*[.][0-9]+)(?:e[-+]?[0-9]+)?$").unwrap()
+});
+static DOUBLE_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^([-+]?(?:[0-9]+[.]?|[0-9]*[.][0-9]+)(?:e[-+]?[0-9]+)?)d$").unwrap()
+});
+static FLOAT_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^([-+]?(?:[0-9]+[.]?|[0-9]*[.][0-9]+)(?:e[-+]?[0-9]+)?)f$").unwrap()
+});
+static BYTE_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^([-+]?(?:0|[1-9][0-9]*))b$").unwrap());
+static LONG_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^([-+]?(?:0|[1-9][0-9]*))l$").unwrap());
+static SHORT_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^([-+]?(?:0|[1-9][0-9]*))s$").unwrap());
+static INT_PATTERN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[-+]?(?:0|[1-9][0-9]*)$").unwrap());
+
+/// Parses a compound tag, erroring on any trailing data
+pub fn parse_tag(input: &str) -> Result<CompoundTag> {
+    let mut parser = TagParser::new(input);
+    let tag = parser.read_struct()?;
+    parser.skip_whitespace();
+    if parser.can_read() {
+        return Err(parser.error("Unexpected trailing data"));
+    }
+    Ok(tag)
+}
+
+/// Parses any tag, erroring on any trailing data
+pub fn parse_as_tag(input: &str) -> Result<Tag> {
+    let mut parser = TagParser::new(input);
+    let tag = parser.read_value()?;
+    parser.skip_whitespace();
+    if parser.can_read() {
+        return Err(parser.error("Unexpected trailing data"));
+    }
+    Ok(tag)
+}
+
+/// Whether a character may appear in an unquoted string or key
+pub fn is_allowed_in_unquoted_string(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.' || c == '+'
+}
+
+/// Cursor over an SNBT string. Reads may leave the cursor anywhere on failure, so callers that
+/// want to continue past a value (e.g. command argument parsing) should read [Self::cursor] first.
+pub struct TagParser<'a> {
+    input: &'a str,
+    pub cursor: usize,
+}
+impl<'a> TagParser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self { input, cursor: 0 }
+    }
+
+    pub fn can_read(&self) -> bool {
+        self.cursor < self.input.len()
+    }
+
+    pub fn peek(&self) -> Option<char> {
+        self.input[self.cursor..].chars().next()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.input[self.cursor..].chars().nth(offset)
+    }
+
+    pub fn skip(&mut self) {
+        if let Some(c) = self.peek() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    pub fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.skip();
+        }
+    }
+
+    /// Error annotated with the position and the text leading up to it, like brigadier
+    pub fn error(&self, message: impl AsRef<str>) -> anyhow::Error {
+        let context_start = self.input[..self.cursor]
+            .char_indices()
+            .rev()
+            .nth(9)
+            .map_or(0, |(i, _)| i);
+        anyhow!(
+            "{} at position {}: {}{}<--[HERE]",
+            message.as_ref(),
+            self.cursor,
+            if context_start > 0 { "..." } else { "" },
+            &self.input[context_start..self.cursor]
+        )
+    }
+
+    pub fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.skip();
+            Ok(())
+        } else {
+            Err(self.error(format!("Expected '{}'", expected)))
+        }
+    }
+
+    fn has_element_separator(&mut self) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(ELEMENT_SEPARATOR) {
+            self.skip();
+            self.skip_whitespace();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn read_value(&mut self) -> Result<Tag> {
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(self.error("Expected value")),
+            Some(STRUCT_OPEN) => self.read_struct().map(Tag::CompoundTag),
+            Some(LIST_OPEN) => self.read_list(),
+            Some(_) => self.read_typed_value(),
+        }
+    }
+
+    pub fn read_struct(&mut self) -> Result<CompoundTag> {
+        self.expect(STRUCT_OPEN)?;
+        let mut entries = Vec::new();
+        self.skip_whitespace();
+        while self.peek().is_some_and(|c| c != STRUCT_CLOSE) {
+            let start = self.cursor;
+            let key = self.read_key()?;
+            if key.is_empty() {
+                self.cursor = start;
+                return Err(self.error("Expected non-empty key"));
+            }
+            self.expect(NAME_VALUE_SEPARATOR)?;
+            entries.push((key, self.read_value()?));
+            if !self.has_element_separator() {
+                break;
+            }
+            if !self.can_read() {
+                return Err(self.error("Expected key"));
+            }
+        }
+        self.expect(STRUCT_CLOSE)?;
+        Ok(CompoundTag::from_iter(entries))
+    }
+
+    fn read_key(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        if !self.can_read() {
+            return Err(self.error("Expected key"));
+        }
+        self.read_string()
+    }
+
+    fn read_typed_value(&mut self) -> Result<Tag> {
+        self.skip_whitespace();
+        let start = self.cursor;
+        if self.peek().is_some_and(is_quoted_string_start) {
+            return self.read_quoted_string().map(Tag::StringTag);
+        }
+        let value = self.read_unquoted_string();
+        if value.is_empty() {
+            self.cursor = start;
+            return Err(self.error("Expected value"));
+        }
+        Ok(type_value(value))
+    }
+
+    fn read_list(&mut self) -> Result<Tag> {
+        let is_array = self.peek_at(1).is_some_and(|c| !is_quoted_string_start(c))
+            && self.peek_at(2) == Some(';');
+        if is_array {
+            self.read_array()
+        } else {
+            self.read_list_tag()
+        }
+    }
+
+    fn read_list_tag(&mut self) -> Result<Tag> {
+        self.expect(LIST_OPEN)?;
+        self.skip_whitespace();
+        if !self.can_read() {
+            return Err(self.error("Expected value"));
+        }
+
+        let mut list: Vec<Tag> = Vec::new();
+        let mut list_type = None;
+        while self.peek().is_some_and(|c| c != LIST_CLOSE) {
+            let start = self.cursor;
+            let tag = self.read_value()?;
+            let tag_type = TagType::from(&tag);
+            match &list_type {
+                None => list_type = Some(tag_type),
+                Some(list_type) if *list_type != tag_type => {
+                    self.cursor = start;
+                    return Err(self.error(format!(
+                        "Can't insert {:?} into list of {:?}",
+                        tag_type, list_type
+                    )));
+                }
+                Some(_) => {}
+            }
+            list.push(tag);
+            if !self.has_element_separator() {
+                break;
+            }
+            if !self.can_read() {
+                return Err(self.error("Expected value"));
+            }
+        }
+        self.expect(LIST_CLOSE)?;
+        Ok(Tag::ListTag(ListTag::new(list)))
+    }
+
+    fn read_array(&mut self) -> Result<Tag> {
+        self.expect(LIST_OPEN)?;
+        let start = self.cursor;
+        let array_type = self.peek();
+        self.skip();
+        self.skip();
+        self.skip_whitespace();
+        if !self.can_read() {
+            return Err(self.error("Expected value"));
+        }
+        match array_type {
+            Some('B') => self
+                .read_array_elements(TagType::ByteTag, |tag| tag.try_as_byte_tag())
+                .map(Tag::ByteArrayTag),
+            Some('I') => self
+                .read_array_elements(TagType::IntTag, |tag| tag.try_as_int_tag())
+                .map(Tag::IntArrayTag),
+            Some('L') => self
+                .read_array_elements(TagType::LongTag, |tag| tag.try_as_long_tag())
+                .map(Tag::LongArrayTag),
+            Some(c) => {
+                self.cursor = start;
+                Err(self.error(format!("Invalid array type '{}'", c)))
+            }
+            None => Err(self.error("Expected value")),
+        }
+    }
+
+    fn read_array_elements<T>(
+        &mut self,
+        element_type: TagType,
+        extract: impl Fn(Tag) -> Option<T>,
+    ) -> Result<Vec<T>> {
+        let mut res = Vec::new();
+        while self.peek().is_some_and(|c| c != LIST_CLOSE) {
+            let start = self.cursor;
+            let tag = self.read_value()?;
+            let tag_type = TagType::from(&tag);
+            match extract(tag) {
+                Some(value) => res.push(value),
+                None => {
+                    self.cursor = start;
+                    return Err(self.error(format!(
+                        "Can't insert {:?} into array of {:?}",
+                        tag_type, element_type
+                    )));
+                }
+            }
+            if !self.has_element_separator() {
+                break;
+            }
+            if !self.can_read() {
+                return Err(self.error("Expected value"));
+            }
+        }
+        self.expect(LIST_CLOSE)?;
+        Ok(res)
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        match self.peek() {
+            Some(c) if is_quoted_string_start(c) => self.read_quoted_string(),
+            _ => Ok(self.read_unquoted_string().to_string()),
+        }
+    }
+
+    fn read_unquoted_string(&mut self) -> &'a str {
+        self.read_while(is_allowed_in_unquoted_string)
+    }
+
+    /// Everything read since `start`
+    pub fn read_since(&self, start: usize) -> &'a str {
+        &self.input[start..self.cursor]
+    }
+
+    /// Reads characters for as long as they match
+    pub fn read_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let start = self.cursor;
+        while self.peek().is_some_and(&predicate) {
+            self.skip();
+        }
+        &self.input[start..self.cursor]
+    }
+
+    pub fn read_quoted_string(&mut self) -> Result<String> {
+        let Some(terminator) = self.peek() else {
+            return Err(self.error("Expected quote to start a string"));
+        };
+        self.skip();
+
+        let mut res = String::new();
+        let mut escaped = false;
+        while let Some(c) = self.peek() {
+            self.skip();
+            if escaped {
+                if c == terminator || c == '\\' {
+                    res.push(c);
+                    escaped = false;
+                } else {
+                    return Err(self.error(format!("Invalid escape sequence '\\{}'", c)));
+                }
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == terminator {
+                return Ok(res);
+            } else {
+                res.push(c);
+            }
+        }
+        Err(self.error("Unclosed quoted string"))
+    }
+}
+
+pub fn is_quoted_string_start(c: char) -> bool {
+    c == '"' || c == '\''
+}
+
+/// Infers the type of an unquoted value from its number suffix, falling back to a string like
+/// vanilla if the number does not fit
+fn type_value(value: &str) -> Tag {
+    fn number(pattern: &Regex, value: &str) -> Option<String> {
+        pattern.captures(value).map(|c| c[1].to_string())
+    }
+
+    if let Some(n) = number(&FLOAT_PATTERN, value) {
+        if let Ok(f) = n.parse::<f32>() {
+            return Tag::FloatTag(f);
+        }
+    } else if let Some(n) = number(&BYTE_PATTERN, value) {
+        if let Ok(b) = n.parse::<i8>() {
+            return Tag::ByteTag(b as u8);
+        }
+    } else if let Some(n) = number(&LONG_PATTERN, value) {
+        if let Ok(l) = n.parse::<i64>() {
+            return Tag::LongTag(l);
+        }
+    } else if let Some(n) = number(&SHORT_PATTERN, value) {
+        if let Ok(s) = n.parse::<i16>() {
+            return Tag::ShortTag(s);
+        }
+    } else if INT_PATTERN.is_match(value) {
+        if let Ok(i) = value.parse::<i32>() {
+            return Tag::IntTag(i);
+        }
+    } else if let Some(n) = number(&DOUBLE_PATTERN, value) {
+        if let Ok(d) = n.parse::<f64>() {
+            return Tag::DoubleTag(d);
+        }
+    } else if DOUBLE_PATTERN_NOSUFFIX.is_match(value) {
+        if let Ok(d) = value.parse::<f64>() {
+            return Tag::DoubleTag(d);
+        }
+    } else if value.eq_ignore_ascii_case("true") {
+        return Tag::ByteTag(1);
+    } else if value.eq_ignore_ascii_case("false") {
+        return Tag::ByteTag(0);
+    }
+    Tag::StringTag(value.to_string())
+}
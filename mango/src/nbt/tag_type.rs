@@ -1,14 +1,15 @@
 use crate::nbt::compound_tag::CompoundTag;
 use crate::nbt::nbt_accounter::NbtAccounter;
+use crate::nbt::stream_tag_visitor::{EntryResult, StreamTagVisitor, ValueResult};
 use crate::nbt::tag::Tag;
-use crate::nbt::{end_tag, list_tag, DataInput};
+use crate::nbt::{end_tag, list_tag, skip_bytes, string_tag, DataInput};
 use anyhow::anyhow;
 use std::borrow::Borrow;
 use strum::{EnumCount, FromRepr};
 use tokio::io::AsyncReadExt;
 
 /// Tag types. Only used internally here for split processing for [crate::nbt::tag::Tag]
-#[derive(EnumCount, FromRepr, PartialEq, Eq, Debug)]
+#[derive(EnumCount, FromRepr, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 #[repr(u8)]
 pub enum TagType {
     EndTag,
@@ -53,79 +54,82 @@ impl TagType {
 
     pub async fn load(
         &self,
-        reader: &mut DataInput,
+        reader: &mut impl DataInput,
         nbt_accounter: impl Borrow<NbtAccounter>,
     ) -> anyhow::Result<Tag> {
         match self {
             TagType::EndTag => {
-                nbt_accounter.borrow().account_bytes(8);
+                nbt_accounter.borrow().account_bytes(8)?;
                 Ok(Tag::EndTag(end_tag::INSTANCE))
             }
             TagType::ByteTag => {
-                nbt_accounter.borrow().account_bytes(9);
+                nbt_accounter.borrow().account_bytes(9)?;
                 Ok(Tag::ByteTag(reader.read_u8().await?))
             }
             TagType::ShortTag => {
-                nbt_accounter.borrow().account_bytes(10);
+                nbt_accounter.borrow().account_bytes(10)?;
                 Ok(Tag::ShortTag(reader.read_i16().await?))
             }
             TagType::IntTag => {
-                nbt_accounter.borrow().account_bytes(12);
+                nbt_accounter.borrow().account_bytes(12)?;
                 Ok(Tag::IntTag(reader.read_i32().await?))
             }
             TagType::LongTag => {
-                nbt_accounter.borrow().account_bytes(16);
+                nbt_accounter.borrow().account_bytes(16)?;
                 Ok(Tag::LongTag(reader.read_i64().await?))
             }
             TagType::FloatTag => {
-                nbt_accounter.borrow().account_bytes(12);
+                nbt_accounter.borrow().account_bytes(12)?;
                 Ok(Tag::FloatTag(reader.read_f32().await?))
             }
             TagType::DoubleTag => {
-                nbt_accounter.borrow().account_bytes(16);
+                nbt_accounter.borrow().account_bytes(16)?;
                 Ok(Tag::DoubleTag(reader.read_f64().await?))
             }
             TagType::ByteArrayTag => {
                 let nbt_accounter = nbt_accounter.borrow();
-                nbt_accounter.account_bytes(24);
+                nbt_accounter.account_bytes(24)?;
                 let len = reader.read_i32().await?;
-                nbt_accounter.account_bytes(len as u64);
+                if len < 0 {
+                    return Err(anyhow!("Negative length {} on ByteArrayTag", len));
+                }
+                nbt_accounter.account_bytes(len as u64)?;
                 let mut buf = vec![0u8; len as usize];
                 reader.read_exact(&mut buf).await?;
                 Ok(Tag::ByteArrayTag(buf))
             }
             TagType::StringTag => {
                 let nbt_accounter = nbt_accounter.borrow();
-                nbt_accounter.account_bytes(36);
+                nbt_accounter.account_bytes(36)?;
                 let len = reader.read_u16().await?;
                 let mut buf = vec![0u8; len as usize];
                 reader.read_exact(&mut buf).await?;
                 let res = cesu8::from_java_cesu8(&buf)?.to_string();
-                nbt_accounter.account_bytes(28 + 2 * res.len() as u64);
+                nbt_accounter.account_bytes(28 + 2 * res.len() as u64)?;
                 Ok(Tag::StringTag(res))
             }
-            TagType::ListTag => list_tag::load_list(reader, nbt_accounter)
-                .await
-                .map(Tag::ListTag),
+            TagType::ListTag => {
+                let nbt_accounter = nbt_accounter.borrow();
+                nbt_accounter.push_depth()?;
+                let res = list_tag::load_list(reader, nbt_accounter).await;
+                nbt_accounter.pop_depth()?;
+                res.map(Tag::ListTag)
+            }
             TagType::CompoundTag => {
                 let nbt_accounter = nbt_accounter.borrow();
-                nbt_accounter.push_depth();
-                match CompoundTag::load_compound(reader, nbt_accounter).await {
-                    Ok(res) => {
-                        nbt_accounter.pop_depth();
-                        Ok(Tag::CompoundTag(res))
-                    }
-                    Err(e) => {
-                        nbt_accounter.pop_depth();
-                        Err(e)
-                    }
-                }
+                nbt_accounter.push_depth()?;
+                let res = CompoundTag::load_compound(reader, nbt_accounter).await;
+                nbt_accounter.pop_depth()?;
+                res.map(Tag::CompoundTag)
             }
             TagType::IntArrayTag => {
                 let nbt_accounter = nbt_accounter.borrow();
-                nbt_accounter.account_bytes(24);
+                nbt_accounter.account_bytes(24)?;
                 let len = reader.read_i32().await?;
-                nbt_accounter.account_bytes(4 * len as u64);
+                if len < 0 {
+                    return Err(anyhow!("Negative length {} on IntArrayTag", len));
+                }
+                nbt_accounter.account_bytes(4 * len as u64)?;
                 let mut buf = vec![0i32; len as usize];
                 for i in 0..len {
                     buf[i as usize] = reader.read_i32().await?;
@@ -134,9 +138,12 @@ impl TagType {
             }
             TagType::LongArrayTag => {
                 let nbt_accounter = nbt_accounter.borrow();
-                nbt_accounter.account_bytes(24);
+                nbt_accounter.account_bytes(24)?;
                 let len = reader.read_i32().await?;
-                nbt_accounter.account_bytes(8 * len as u64);
+                if len < 0 {
+                    return Err(anyhow!("Negative length {} on LongArrayTag", len));
+                }
+                nbt_accounter.account_bytes(8 * len as u64)?;
                 let mut buf = vec![0i64; len as usize];
                 for i in 0..len {
                     buf[i as usize] = reader.read_i64().await?;
@@ -144,10 +151,272 @@ impl TagType {
                 Ok(Tag::LongArrayTag(buf))
             }
             TagType::Invalid(tag_id) => Err(anyhow!("Invalid tag id: {}", tag_id)),
-            TagType::Numeric => {
-                todo!();
+            TagType::Numeric => Err(anyhow!("Numeric is not a concrete tag type to load")),
+        }
+    }
+
+    /// Size in bytes of the payload if it is the same for every tag of this type
+    fn fixed_size(&self) -> Option<u64> {
+        match self {
+            TagType::EndTag => Some(0),
+            TagType::ByteTag => Some(1),
+            TagType::ShortTag => Some(2),
+            TagType::IntTag | TagType::FloatTag => Some(4),
+            TagType::LongTag | TagType::DoubleTag => Some(8),
+            _ => None,
+        }
+    }
+
+    /// Reads the payload of a tag of this type, feeding it to the visitor instead of building a
+    /// [Tag]. Anything the visitor skips is read past without being decoded.
+    pub async fn parse(
+        &self,
+        reader: &mut impl DataInput,
+        visitor: &mut impl StreamTagVisitor,
+        nbt_accounter: &NbtAccounter,
+    ) -> anyhow::Result<ValueResult> {
+        match self {
+            TagType::EndTag => Ok(visitor.visit_end()),
+            TagType::ByteTag => {
+                nbt_accounter.account_bytes(9)?;
+                Ok(visitor.visit_byte(reader.read_u8().await?))
+            }
+            TagType::ShortTag => {
+                nbt_accounter.account_bytes(10)?;
+                Ok(visitor.visit_short(reader.read_i16().await?))
+            }
+            TagType::IntTag => {
+                nbt_accounter.account_bytes(12)?;
+                Ok(visitor.visit_int(reader.read_i32().await?))
+            }
+            TagType::LongTag => {
+                nbt_accounter.account_bytes(16)?;
+                Ok(visitor.visit_long(reader.read_i64().await?))
+            }
+            TagType::FloatTag => {
+                nbt_accounter.account_bytes(12)?;
+                Ok(visitor.visit_float(reader.read_f32().await?))
+            }
+            TagType::DoubleTag => {
+                nbt_accounter.account_bytes(16)?;
+                Ok(visitor.visit_double(reader.read_f64().await?))
+            }
+            TagType::StringTag => {
+                nbt_accounter.account_bytes(36)?;
+                let value = CompoundTag::read_string(reader, nbt_accounter).await?;
+                Ok(visitor.visit_string(value))
+            }
+            TagType::ListTag => {
+                nbt_accounter.push_depth()?;
+                let res = parse_list(reader, visitor, nbt_accounter).await;
+                nbt_accounter.pop_depth()?;
+                res
+            }
+            TagType::CompoundTag => {
+                nbt_accounter.push_depth()?;
+                let res = parse_compound(reader, visitor, nbt_accounter).await;
+                nbt_accounter.pop_depth()?;
+                res
+            }
+            TagType::ByteArrayTag | TagType::IntArrayTag | TagType::LongArrayTag => {
+                Ok(match self.load(reader, nbt_accounter).await? {
+                    Tag::ByteArrayTag(value) => visitor.visit_byte_array(value),
+                    Tag::IntArrayTag(value) => visitor.visit_int_array(value),
+                    Tag::LongArrayTag(value) => visitor.visit_long_array(value),
+                    _ => unreachable!("Array tag types always load as arrays"),
+                })
+            }
+            TagType::Invalid(tag_id) => Err(anyhow!("Invalid tag id: {}", tag_id)),
+            TagType::Numeric => Err(anyhow!("Numeric is not a concrete tag type to parse")),
+        }
+    }
+
+    /// Reads past the payload of a tag of this type without decoding it
+    pub async fn skip(
+        &self,
+        reader: &mut impl DataInput,
+        nbt_accounter: &NbtAccounter,
+    ) -> anyhow::Result<()> {
+        if let Some(size) = self.fixed_size() {
+            return skip_bytes(reader, size).await;
+        }
+        match self {
+            TagType::ByteArrayTag | TagType::IntArrayTag | TagType::LongArrayTag => {
+                let len = reader.read_i32().await?;
+                if len < 0 {
+                    return Err(anyhow!("Negative length {} on {:?}", len, self));
+                }
+                let element_size = match self {
+                    TagType::ByteArrayTag => 1,
+                    TagType::IntArrayTag => 4,
+                    _ => 8,
+                };
+                skip_bytes(reader, element_size * len as u64).await
+            }
+            TagType::StringTag => string_tag::skip_string(reader).await,
+            TagType::ListTag => {
+                nbt_accounter.push_depth()?;
+                let res = async {
+                    let element_type = TagType::get_type(reader.read_u8().await?);
+                    let len = reader.read_i32().await?;
+                    if len < 0 {
+                        return Err(anyhow!("Negative length {} on ListTag", len));
+                    }
+                    Box::pin(element_type.skip_count(reader, len as u64, nbt_accounter)).await
+                }
+                .await;
+                nbt_accounter.pop_depth()?;
+                res
+            }
+            TagType::CompoundTag => {
+                nbt_accounter.push_depth()?;
+                let res = skip_remaining_entries(reader, nbt_accounter).await;
+                nbt_accounter.pop_depth()?;
+                res
+            }
+            TagType::Invalid(tag_id) => Err(anyhow!("Invalid tag id: {}", tag_id)),
+            _ => Err(anyhow!("{:?} is not a concrete tag type to skip", self)),
+        }
+    }
+
+    /// Reads past `count` consecutive payloads of this type, such as the rest of a list
+    pub async fn skip_count(
+        &self,
+        reader: &mut impl DataInput,
+        count: u64,
+        nbt_accounter: &NbtAccounter,
+    ) -> anyhow::Result<()> {
+        if let Some(size) = self.fixed_size() {
+            return skip_bytes(reader, size * count).await;
+        }
+        for _ in 0..count {
+            Box::pin(self.skip(reader, nbt_accounter)).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Based on vanilla's ListTag parse, which lets the visitor decide per element whether to enter it
+async fn parse_list(
+    reader: &mut impl DataInput,
+    visitor: &mut impl StreamTagVisitor,
+    nbt_accounter: &NbtAccounter,
+) -> anyhow::Result<ValueResult> {
+    nbt_accounter.account_bytes(37)?;
+    let element_type = TagType::get_type(reader.read_u8().await?);
+    let len = reader.read_i32().await?;
+    if element_type == TagType::EndTag && len > 0 {
+        return Err(anyhow!("Missing type on ListTag"));
+    }
+    if len < 0 {
+        return Err(anyhow!("Negative length {} on ListTag", len));
+    }
+
+    match visitor.visit_list(element_type, len) {
+        ValueResult::Halt => return Ok(ValueResult::Halt),
+        ValueResult::Break => {
+            element_type
+                .skip_count(reader, len as u64, nbt_accounter)
+                .await?;
+            return Ok(visitor.visit_container_end());
+        }
+        ValueResult::Continue => {}
+    }
+
+    nbt_accounter.account_bytes(4 * len as u64)?;
+    let mut index = 0;
+    while index < len {
+        match visitor.visit_element(element_type, index) {
+            EntryResult::Halt => return Ok(ValueResult::Halt),
+            EntryResult::Break => {
+                element_type.skip(reader, nbt_accounter).await?;
+                break;
             }
+            EntryResult::Skip => element_type.skip(reader, nbt_accounter).await?,
+            EntryResult::Enter => {
+                match Box::pin(element_type.parse(reader, visitor, nbt_accounter)).await? {
+                    ValueResult::Halt => return Ok(ValueResult::Halt),
+                    ValueResult::Break => break,
+                    ValueResult::Continue => {}
+                }
+            }
+        }
+        index += 1;
+    }
+
+    let remaining = len - index - 1;
+    if remaining > 0 {
+        element_type
+            .skip_count(reader, remaining as u64, nbt_accounter)
+            .await?;
+    }
+    Ok(visitor.visit_container_end())
+}
+
+/// Based on vanilla's CompoundTag parse. Entries are offered to the visitor by type first so
+/// unwanted entries can be skipped without decoding their names.
+async fn parse_compound(
+    reader: &mut impl DataInput,
+    visitor: &mut impl StreamTagVisitor,
+    nbt_accounter: &NbtAccounter,
+) -> anyhow::Result<ValueResult> {
+    nbt_accounter.account_bytes(48)?;
+    loop {
+        let tag_type = TagType::get_type(reader.read_u8().await?);
+        if tag_type == TagType::EndTag {
+            return Ok(visitor.visit_container_end());
+        }
+
+        match visitor.visit_entry(tag_type) {
+            EntryResult::Halt => return Ok(ValueResult::Halt),
+            EntryResult::Break => {
+                string_tag::skip_string(reader).await?;
+                tag_type.skip(reader, nbt_accounter).await?;
+                break;
+            }
+            EntryResult::Skip => {
+                string_tag::skip_string(reader).await?;
+                tag_type.skip(reader, nbt_accounter).await?;
+                continue;
+            }
+            EntryResult::Enter => {}
+        }
+
+        let name = CompoundTag::read_string(reader, nbt_accounter).await?;
+        match visitor.visit_entry_named(tag_type, &name) {
+            EntryResult::Halt => return Ok(ValueResult::Halt),
+            EntryResult::Break => {
+                tag_type.skip(reader, nbt_accounter).await?;
+                break;
+            }
+            EntryResult::Skip => tag_type.skip(reader, nbt_accounter).await?,
+            EntryResult::Enter => {
+                nbt_accounter.account_bytes(36)?;
+                match Box::pin(tag_type.parse(reader, visitor, nbt_accounter)).await? {
+                    ValueResult::Halt => return Ok(ValueResult::Halt),
+                    ValueResult::Break => break,
+                    ValueResult::Continue => {}
+                }
+            }
+        }
+    }
+
+    skip_remaining_entries(reader, nbt_accounter).await?;
+    Ok(visitor.visit_container_end())
+}
+
+/// Reads past the rest of a compound's entries, including its end tag
+async fn skip_remaining_entries(
+    reader: &mut impl DataInput,
+    nbt_accounter: &NbtAccounter,
+) -> anyhow::Result<()> {
+    loop {
+        let tag_type = TagType::get_type(reader.read_u8().await?);
+        if tag_type == TagType::EndTag {
+            return Ok(());
         }
+        string_tag::skip_string(reader).await?;
+        Box::pin(tag_type.skip(reader, nbt_accounter)).await?;
     }
 }
 impl From<&Tag> for TagType {
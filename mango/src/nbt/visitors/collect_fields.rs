@@ -0,0 +1,150 @@
+use crate::nbt::stream_tag_visitor::{EntryResult, StreamTagVisitor, ValueResult};
+use crate::nbt::tag::Tag;
+use crate::nbt::tag_type::TagType;
+use crate::nbt::visitors::collect_to_tag::CollectToTag;
+use crate::nbt::visitors::field_selector::FieldSelector;
+use crate::nbt::visitors::field_tree::{FieldTree, FieldTreeNodeId};
+use std::collections::HashSet;
+
+/// Collects only the selected fields into a compound, skipping everything else. Reading halts as
+/// soon as every field has been found, so the rest of the input is never read.
+pub struct CollectFields {
+    inner: CollectToTag,
+    fields_to_get_count: usize,
+    wanted_types: HashSet<TagType>,
+    tree: FieldTree,
+    stack: Vec<FieldTreeNodeId>,
+}
+impl CollectFields {
+    pub fn new(selectors: &[FieldSelector]) -> Self {
+        let mut wanted_types: HashSet<TagType> = selectors.iter().map(|s| s.tag_type).collect();
+        wanted_types.insert(TagType::CompoundTag);
+        Self {
+            inner: CollectToTag::new(),
+            fields_to_get_count: selectors.len(),
+            wanted_types,
+            tree: FieldTree::new(selectors),
+            stack: vec![FieldTree::ROOT],
+        }
+    }
+
+    /// Number of selected fields that were not found in the input
+    pub fn get_missing_field_count(&self) -> usize {
+        self.fields_to_get_count
+    }
+
+    pub fn get_result(&self) -> Option<&Tag> {
+        self.inner.get_result()
+    }
+
+    pub fn into_result(self) -> Option<Tag> {
+        self.inner.into_result()
+    }
+
+    fn current_depth(&self) -> usize {
+        self.stack
+            .last()
+            .map_or(0, |node| self.tree.node(*node).depth)
+    }
+}
+impl StreamTagVisitor for CollectFields {
+    fn visit_end(&mut self) -> ValueResult {
+        self.inner.visit_end()
+    }
+
+    fn visit_string(&mut self, value: String) -> ValueResult {
+        self.inner.visit_string(value)
+    }
+
+    fn visit_byte(&mut self, value: u8) -> ValueResult {
+        self.inner.visit_byte(value)
+    }
+
+    fn visit_short(&mut self, value: i16) -> ValueResult {
+        self.inner.visit_short(value)
+    }
+
+    fn visit_int(&mut self, value: i32) -> ValueResult {
+        self.inner.visit_int(value)
+    }
+
+    fn visit_long(&mut self, value: i64) -> ValueResult {
+        self.inner.visit_long(value)
+    }
+
+    fn visit_float(&mut self, value: f32) -> ValueResult {
+        self.inner.visit_float(value)
+    }
+
+    fn visit_double(&mut self, value: f64) -> ValueResult {
+        self.inner.visit_double(value)
+    }
+
+    fn visit_byte_array(&mut self, value: Vec<u8>) -> ValueResult {
+        self.inner.visit_byte_array(value)
+    }
+
+    fn visit_int_array(&mut self, value: Vec<i32>) -> ValueResult {
+        self.inner.visit_int_array(value)
+    }
+
+    fn visit_long_array(&mut self, value: Vec<i64>) -> ValueResult {
+        self.inner.visit_long_array(value)
+    }
+
+    fn visit_list(&mut self, element_type: TagType, length: i32) -> ValueResult {
+        self.inner.visit_list(element_type, length)
+    }
+
+    fn visit_element(&mut self, element_type: TagType, index: i32) -> EntryResult {
+        self.inner.visit_element(element_type, index)
+    }
+
+    fn visit_entry(&mut self, tag_type: TagType) -> EntryResult {
+        // Inside a selected container, which is collected whole
+        if self.inner.depth() > self.current_depth() {
+            return self.inner.visit_entry(tag_type);
+        }
+        if self.fields_to_get_count == 0 {
+            return EntryResult::Halt;
+        }
+        if !self.wanted_types.contains(&tag_type) {
+            return EntryResult::Skip;
+        }
+        self.inner.visit_entry(tag_type)
+    }
+
+    fn visit_entry_named(&mut self, tag_type: TagType, name: &str) -> EntryResult {
+        if self.inner.depth() > self.current_depth() {
+            return self.inner.visit_entry_named(tag_type, name);
+        }
+        let node = *self.stack.last().unwrap();
+        let selected = &mut self.tree.node_mut(node).selected_fields;
+        if selected.get(name) == Some(&tag_type) {
+            selected.remove(name);
+            self.fields_to_get_count -= 1;
+            return self.inner.visit_entry_named(tag_type, name);
+        }
+        if tag_type == TagType::CompoundTag {
+            if let Some(&child) = self.tree.node(node).fields_to_recurse.get(name) {
+                self.stack.push(child);
+                return self.inner.visit_entry_named(tag_type, name);
+            }
+        }
+        EntryResult::Skip
+    }
+
+    fn visit_container_end(&mut self) -> ValueResult {
+        if self.inner.depth() == self.current_depth() {
+            self.stack.pop();
+        }
+        self.inner.visit_container_end()
+    }
+
+    fn visit_root_entry(&mut self, tag_type: TagType) -> ValueResult {
+        if tag_type != TagType::CompoundTag {
+            return ValueResult::Halt;
+        }
+        self.inner.visit_root_entry(tag_type)
+    }
+}
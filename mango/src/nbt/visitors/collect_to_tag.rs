@@ -0,0 +1,161 @@
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::end_tag;
+use crate::nbt::list_tag::ListTag;
+use crate::nbt::stream_tag_visitor::{EntryResult, StreamTagVisitor, ValueResult};
+use crate::nbt::tag::Tag;
+use crate::nbt::tag_type::TagType;
+use std::mem;
+
+enum Container {
+    List(Vec<Tag>),
+    Compound(Vec<(String, Tag)>),
+}
+
+/// A container being read, along with the name it will be put under in its parent
+struct Frame {
+    name: String,
+    container: Container,
+}
+
+/// Builds a [Tag] out of everything it visits. Other visitors wrap this to only collect part of
+/// the input.
+#[derive(Default)]
+pub struct CollectToTag {
+    last_id: String,
+    stack: Vec<Frame>,
+    result: Option<Tag>,
+}
+impl CollectToTag {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_result(&self) -> Option<&Tag> {
+        self.result.as_ref()
+    }
+
+    pub fn into_result(self) -> Option<Tag> {
+        self.result
+    }
+
+    /// Number of containers currently being read, with the root compound at depth 1
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn append_entry(&mut self, name: String, tag: Tag) {
+        match self.stack.last_mut() {
+            Some(Frame {
+                container: Container::List(list),
+                ..
+            }) => list.push(tag),
+            Some(Frame {
+                container: Container::Compound(entries),
+                ..
+            }) => entries.push((name, tag)),
+            None => self.result = Some(tag),
+        }
+    }
+
+    fn append_value(&mut self, tag: Tag) -> ValueResult {
+        let name = mem::take(&mut self.last_id);
+        self.append_entry(name, tag);
+        ValueResult::Continue
+    }
+
+    fn enter_container_if_needed(&mut self, tag_type: TagType) {
+        let container = match tag_type {
+            TagType::ListTag => Container::List(Vec::new()),
+            TagType::CompoundTag => Container::Compound(Vec::new()),
+            _ => return,
+        };
+        self.stack.push(Frame {
+            name: mem::take(&mut self.last_id),
+            container,
+        });
+    }
+}
+impl StreamTagVisitor for CollectToTag {
+    fn visit_end(&mut self) -> ValueResult {
+        self.append_value(Tag::EndTag(end_tag::INSTANCE))
+    }
+
+    fn visit_string(&mut self, value: String) -> ValueResult {
+        self.append_value(Tag::StringTag(value))
+    }
+
+    fn visit_byte(&mut self, value: u8) -> ValueResult {
+        self.append_value(Tag::ByteTag(value))
+    }
+
+    fn visit_short(&mut self, value: i16) -> ValueResult {
+        self.append_value(Tag::ShortTag(value))
+    }
+
+    fn visit_int(&mut self, value: i32) -> ValueResult {
+        self.append_value(Tag::IntTag(value))
+    }
+
+    fn visit_long(&mut self, value: i64) -> ValueResult {
+        self.append_value(Tag::LongTag(value))
+    }
+
+    fn visit_float(&mut self, value: f32) -> ValueResult {
+        self.append_value(Tag::FloatTag(value))
+    }
+
+    fn visit_double(&mut self, value: f64) -> ValueResult {
+        self.append_value(Tag::DoubleTag(value))
+    }
+
+    fn visit_byte_array(&mut self, value: Vec<u8>) -> ValueResult {
+        self.append_value(Tag::ByteArrayTag(value))
+    }
+
+    fn visit_int_array(&mut self, value: Vec<i32>) -> ValueResult {
+        self.append_value(Tag::IntArrayTag(value))
+    }
+
+    fn visit_long_array(&mut self, value: Vec<i64>) -> ValueResult {
+        self.append_value(Tag::LongArrayTag(value))
+    }
+
+    fn visit_list(&mut self, _element_type: TagType, _length: i32) -> ValueResult {
+        ValueResult::Continue
+    }
+
+    fn visit_element(&mut self, element_type: TagType, _index: i32) -> EntryResult {
+        self.enter_container_if_needed(element_type);
+        EntryResult::Enter
+    }
+
+    fn visit_entry(&mut self, _tag_type: TagType) -> EntryResult {
+        EntryResult::Enter
+    }
+
+    fn visit_entry_named(&mut self, tag_type: TagType, name: &str) -> EntryResult {
+        self.last_id = name.to_string();
+        self.enter_container_if_needed(tag_type);
+        EntryResult::Enter
+    }
+
+    fn visit_container_end(&mut self) -> ValueResult {
+        if let Some(Frame { name, container }) = self.stack.pop() {
+            let tag = match container {
+                Container::List(list) => Tag::ListTag(ListTag::new(list)),
+                Container::Compound(entries) => {
+                    Tag::CompoundTag(entries.into_iter().collect::<CompoundTag>())
+                }
+            };
+            self.append_entry(name, tag);
+        }
+        ValueResult::Continue
+    }
+
+    fn visit_root_entry(&mut self, tag_type: TagType) -> ValueResult {
+        self.stack.clear();
+        self.result = None;
+        self.enter_container_if_needed(tag_type);
+        ValueResult::Continue
+    }
+}
@@ -0,0 +1,26 @@
+use crate::nbt::tag_type::TagType;
+
+/// A field of a given type to select, nested under the compounds named by `path`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldSelector {
+    pub path: Vec<String>,
+    pub tag_type: TagType,
+    pub name: String,
+}
+impl FieldSelector {
+    pub fn new(tag_type: TagType, name: impl Into<String>) -> Self {
+        Self::nested(Vec::<String>::new(), tag_type, name)
+    }
+
+    pub fn nested(
+        path: impl IntoIterator<Item = impl Into<String>>,
+        tag_type: TagType,
+        name: impl Into<String>,
+    ) -> Self {
+        Self {
+            path: path.into_iter().map(Into::into).collect(),
+            tag_type,
+            name: name.into(),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use crate::nbt::tag_type::TagType;
+use crate::nbt::visitors::field_selector::FieldSelector;
+use std::collections::HashMap;
+
+/// Index of a node in a [FieldTree]
+pub type FieldTreeNodeId = usize;
+
+pub struct FieldTreeNode {
+    /// Depth of the compound this node matches, with the root compound at depth 1
+    pub depth: usize,
+    pub selected_fields: HashMap<String, TagType>,
+    pub fields_to_recurse: HashMap<String, FieldTreeNodeId>,
+}
+impl FieldTreeNode {
+    fn new(depth: usize) -> Self {
+        Self {
+            depth,
+            selected_fields: HashMap::new(),
+            fields_to_recurse: HashMap::new(),
+        }
+    }
+
+    pub fn is_selected(&self, tag_type: TagType, name: &str) -> bool {
+        self.selected_fields.get(name) == Some(&tag_type)
+    }
+}
+
+/// [FieldSelector]s merged into a tree of compounds, so visitors can track where they are while
+/// walking the input. Nodes live in an arena so visitors can keep a stack of node ids.
+pub struct FieldTree {
+    nodes: Vec<FieldTreeNode>,
+}
+impl FieldTree {
+    pub const ROOT: FieldTreeNodeId = 0;
+
+    pub fn new(selectors: &[FieldSelector]) -> Self {
+        let mut tree = Self {
+            nodes: vec![FieldTreeNode::new(1)],
+        };
+        for selector in selectors {
+            tree.add_entry(selector);
+        }
+        tree
+    }
+
+    pub fn add_entry(&mut self, selector: &FieldSelector) {
+        let mut node = Self::ROOT;
+        for name in &selector.path {
+            node = match self.nodes[node].fields_to_recurse.get(name) {
+                Some(&child) => child,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes
+                        .push(FieldTreeNode::new(self.nodes[node].depth + 1));
+                    self.nodes[node]
+                        .fields_to_recurse
+                        .insert(name.clone(), child);
+                    child
+                }
+            };
+        }
+        self.nodes[node]
+            .selected_fields
+            .insert(selector.name.clone(), selector.tag_type);
+    }
+
+    pub fn node(&self, id: FieldTreeNodeId) -> &FieldTreeNode {
+        &self.nodes[id]
+    }
+
+    pub fn node_mut(&mut self, id: FieldTreeNodeId) -> &mut FieldTreeNode {
+        &mut self.nodes[id]
+    }
+}
@@ -0,0 +1,8 @@
+//! [crate::nbt::stream_tag_visitor::StreamTagVisitor] implementations, based on vanilla's
+//! `net.minecraft.nbt.visitors`
+
+pub mod collect_fields;
+pub mod collect_to_tag;
+pub mod field_selector;
+pub mod field_tree;
+pub mod skip_fields;
@@ -0,0 +1,122 @@
+use crate::nbt::stream_tag_visitor::{EntryResult, StreamTagVisitor, ValueResult};
+use crate::nbt::tag::Tag;
+use crate::nbt::tag_type::TagType;
+use crate::nbt::visitors::collect_to_tag::CollectToTag;
+use crate::nbt::visitors::field_selector::FieldSelector;
+use crate::nbt::visitors::field_tree::{FieldTree, FieldTreeNodeId};
+
+/// Collects the whole input except for the selected fields, which are read past without being
+/// decoded
+pub struct SkipFields {
+    inner: CollectToTag,
+    tree: FieldTree,
+    stack: Vec<FieldTreeNodeId>,
+}
+impl SkipFields {
+    pub fn new(selectors: &[FieldSelector]) -> Self {
+        Self {
+            inner: CollectToTag::new(),
+            tree: FieldTree::new(selectors),
+            stack: vec![FieldTree::ROOT],
+        }
+    }
+
+    pub fn get_result(&self) -> Option<&Tag> {
+        self.inner.get_result()
+    }
+
+    pub fn into_result(self) -> Option<Tag> {
+        self.inner.into_result()
+    }
+
+    fn current_depth(&self) -> usize {
+        self.stack
+            .last()
+            .map_or(0, |node| self.tree.node(*node).depth)
+    }
+}
+impl StreamTagVisitor for SkipFields {
+    fn visit_end(&mut self) -> ValueResult {
+        self.inner.visit_end()
+    }
+
+    fn visit_string(&mut self, value: String) -> ValueResult {
+        self.inner.visit_string(value)
+    }
+
+    fn visit_byte(&mut self, value: u8) -> ValueResult {
+        self.inner.visit_byte(value)
+    }
+
+    fn visit_short(&mut self, value: i16) -> ValueResult {
+        self.inner.visit_short(value)
+    }
+
+    fn visit_int(&mut self, value: i32) -> ValueResult {
+        self.inner.visit_int(value)
+    }
+
+    fn visit_long(&mut self, value: i64) -> ValueResult {
+        self.inner.visit_long(value)
+    }
+
+    fn visit_float(&mut self, value: f32) -> ValueResult {
+        self.inner.visit_float(value)
+    }
+
+    fn visit_double(&mut self, value: f64) -> ValueResult {
+        self.inner.visit_double(value)
+    }
+
+    fn visit_byte_array(&mut self, value: Vec<u8>) -> ValueResult {
+        self.inner.visit_byte_array(value)
+    }
+
+    fn visit_int_array(&mut self, value: Vec<i32>) -> ValueResult {
+        self.inner.visit_int_array(value)
+    }
+
+    fn visit_long_array(&mut self, value: Vec<i64>) -> ValueResult {
+        self.inner.visit_long_array(value)
+    }
+
+    fn visit_list(&mut self, element_type: TagType, length: i32) -> ValueResult {
+        self.inner.visit_list(element_type, length)
+    }
+
+    fn visit_element(&mut self, element_type: TagType, index: i32) -> EntryResult {
+        self.inner.visit_element(element_type, index)
+    }
+
+    fn visit_entry(&mut self, tag_type: TagType) -> EntryResult {
+        self.inner.visit_entry(tag_type)
+    }
+
+    fn visit_entry_named(&mut self, tag_type: TagType, name: &str) -> EntryResult {
+        // Selectors only apply to the compounds along their path, not to same named fields nested
+        // somewhere else
+        if self.inner.depth() == self.current_depth() {
+            let node = self.tree.node(*self.stack.last().unwrap());
+            if node.is_selected(tag_type, name) {
+                return EntryResult::Skip;
+            }
+            if tag_type == TagType::CompoundTag {
+                if let Some(&child) = node.fields_to_recurse.get(name) {
+                    self.stack.push(child);
+                }
+            }
+        }
+        self.inner.visit_entry_named(tag_type, name)
+    }
+
+    fn visit_container_end(&mut self) -> ValueResult {
+        if self.inner.depth() == self.current_depth() {
+            self.stack.pop();
+        }
+        self.inner.visit_container_end()
+    }
+
+    fn visit_root_entry(&mut self, tag_type: TagType) -> ValueResult {
+        self.inner.visit_root_entry(tag_type)
+    }
+}
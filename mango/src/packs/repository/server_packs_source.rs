@@ -100,7 +100,7 @@ impl ServerPacksSource {
                 Some(dir) => {
                     folder_repository_source::discover_packs(
                         dir,
-                        self.validator,
+                        self.validator.clone(),
                         |path, supplier| {
                             consumer(
                                 Self::path_to_id(path.path()),
@@ -237,14 +237,14 @@ pub fn create_pack_repository(
     PackRepository::new(
         [
             // Built in packs
-            Box::new(ServerPacksSource::new(source.world_dir_validator))
+            Box::new(ServerPacksSource::new(source.world_dir_validator.clone()))
                 as Box<dyn RepositorySource>,
             // External datapacks
             Box::new(FolderRepositorySource::new(
                 path,
                 PackType::ServerData,
                 PackSource::World,
-                source.world_dir_validator,
+                source.world_dir_validator.clone(),
             )),
         ]
         .into_iter(),
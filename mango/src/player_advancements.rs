@@ -0,0 +1,66 @@
+use crate::nbt::nbt_utils;
+use crate::nbt::nbt_utils::NO_DATA_VERSION;
+use crate::shared_constants;
+use crate::util;
+use crate::util::datafix::data_fix_types::DataFixTypes;
+use crate::util::datafix::data_fixers::DataFixer;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+use crate::util::datafix::serialization::json_ops;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Progress of a single advancement, with the time each criterion was completed
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AdvancementProgress {
+    #[serde(default)]
+    pub criteria: BTreeMap<String, String>,
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// A player's advancement progress, stored in `advancements/<uuid>.json`. Advancements and
+/// criteria are kept sorted, so saving the same progress always writes the same file.
+pub struct PlayerAdvancements {
+    player_save_path: PathBuf,
+    progress: BTreeMap<String, AdvancementProgress>,
+}
+impl PlayerAdvancements {
+    pub async fn load(
+        player_save_path: PathBuf,
+        data_fixer: impl AsRef<DataFixer>,
+    ) -> Result<Self> {
+        let mut progress = BTreeMap::new();
+        if player_save_path.is_file() {
+            let value: Value =
+                serde_json::from_str(&tokio::fs::read_to_string(&player_save_path).await?)?;
+            let dynamic = Dynamic::new(json_ops::INSTANCE.clone(), value);
+            let data_version = nbt_utils::get_dynamic_data_version(&dynamic, NO_DATA_VERSION);
+            let dynamic = DataFixTypes::Advancements
+                .update_to_current_version(data_fixer.as_ref(), dynamic, data_version)
+                .remove("DataVersion");
+            progress = serde_json::from_value(dynamic.into_value())?;
+        }
+        Ok(Self {
+            player_save_path,
+            progress,
+        })
+    }
+
+    pub fn get_progress(&self, advancement: &str) -> Option<&AdvancementProgress> {
+        self.progress.get(advancement)
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        let mut value = serde_json::to_value(&self.progress)?;
+        value["DataVersion"] = shared_constants::get_current_data_version().into();
+        util::write_file_atomically(
+            &self.player_save_path,
+            serde_json::to_string_pretty(&value)?,
+            None,
+        )
+        .await
+    }
+}
@@ -0,0 +1,115 @@
+use crate::nbt::nbt_utils;
+use crate::nbt::nbt_utils::NO_DATA_VERSION;
+use crate::shared_constants;
+use crate::util;
+use crate::util::datafix::data_fix_types::DataFixTypes;
+use crate::util::datafix::data_fixers::DataFixer;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+use crate::util::datafix::serialization::json_ops;
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// A player's stats, stored in `stats/<uuid>.json` as values by stat ID under each stat type. Both
+/// are kept sorted, so saving the same stats always writes the same file.
+pub struct ServerStatsCounter {
+    file: PathBuf,
+    stats: BTreeMap<String, BTreeMap<String, i32>>,
+}
+impl ServerStatsCounter {
+    pub async fn load(file: PathBuf, data_fixer: impl AsRef<DataFixer>) -> Result<Self> {
+        let mut counter = Self {
+            file,
+            stats: BTreeMap::new(),
+        };
+        if counter.file.is_file() {
+            let json = tokio::fs::read_to_string(&counter.file).await?;
+            counter.parse_local(data_fixer.as_ref(), &json)?;
+        }
+        Ok(counter)
+    }
+
+    pub fn parse_local(&mut self, data_fixer: &DataFixer, json: &str) -> Result<()> {
+        let value: Value = serde_json::from_str(json)?;
+        if !value.is_object() {
+            return Err(anyhow!(
+                "Unable to parse stat data from {}",
+                self.file.display()
+            ));
+        }
+        let dynamic = Dynamic::new(json_ops::INSTANCE.clone(), value);
+        let data_version = nbt_utils::get_dynamic_data_version(&dynamic, NO_DATA_VERSION);
+        let dynamic =
+            DataFixTypes::Stats.update_to_current_version(data_fixer, dynamic, data_version);
+
+        for (stat_type, stats) in dynamic.get("stats").as_map().unwrap_or_default() {
+            let values = self.stats.entry(stat_type).or_default();
+            for (stat, value) in stats.as_map().unwrap_or_default() {
+                values.insert(stat, value.as_int(0));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_value(&self, stat_type: &str, stat: &str) -> i32 {
+        self.stats
+            .get(stat_type)
+            .and_then(|e| e.get(stat))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn set_value(&mut self, stat_type: &str, stat: &str, value: i32) {
+        self.stats
+            .entry(stat_type.to_string())
+            .or_default()
+            .insert(stat.to_string(), value);
+    }
+
+    pub fn to_json(&self) -> String {
+        json!({
+            "stats": self.stats,
+            "DataVersion": shared_constants::get_current_data_version(),
+        })
+        .to_string()
+    }
+
+    pub async fn save(&self) -> Result<()> {
+        util::write_file_atomically(&self.file, self.to_json(), None).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::datafix::data_fixers;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn stats_are_saved_sorted_and_read_back() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("00000000-0000-0000-0000-000000000000.json");
+        let mut counter = ServerStatsCounter::load(file.clone(), data_fixers::get_data_fixer())
+            .await
+            .unwrap();
+        counter.set_value("minecraft:mined", "minecraft:stone", 2);
+        counter.set_value("minecraft:custom", "minecraft:jump", 3);
+        counter.set_value("minecraft:custom", "minecraft:deaths", 1);
+        counter.save().await.unwrap();
+
+        let json = std::fs::read_to_string(&file).unwrap();
+        assert_eq!(
+            json,
+            format!(
+                r#"{{"DataVersion":{},"stats":{{"minecraft:custom":{{"minecraft:deaths":1,"minecraft:jump":3}},"minecraft:mined":{{"minecraft:stone":2}}}}}}"#,
+                shared_constants::get_current_data_version()
+            )
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+        let counter = ServerStatsCounter::load(file, data_fixers::get_data_fixer())
+            .await
+            .unwrap();
+        assert_eq!(counter.get_value("minecraft:custom", "minecraft:jump"), 3);
+    }
+}
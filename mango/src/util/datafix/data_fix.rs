@@ -1,2 +1,91 @@
-#[derive(Debug)]
-pub struct DataFix {}
+use crate::nbt::tag::Tag;
+use crate::util::datafix::data_fix_types::DataFixTypes;
+use crate::util::datafix::schema::Schema;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+use serde_json::Value;
+use std::fmt::{Debug, Formatter};
+
+/// Conversion applied by a [DataFix]. Rules go through [Dynamic] so the same rule fixes both NBT
+/// and JSON data.
+pub trait FixRule: Send + Sync {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T>;
+}
+
+/// [FixRule] instantiated for every format in [FixFormat], so rules can be stored as trait objects
+pub trait ErasedFixRule: Send + Sync {
+    fn fix_nbt(&self, input: Dynamic<Tag>) -> Dynamic<Tag>;
+
+    fn fix_json(&self, input: Dynamic<Value>) -> Dynamic<Value>;
+}
+impl<R: FixRule> ErasedFixRule for R {
+    fn fix_nbt(&self, input: Dynamic<Tag>) -> Dynamic<Tag> {
+        self.fix(input)
+    }
+
+    fn fix_json(&self, input: Dynamic<Value>) -> Dynamic<Value> {
+        self.fix(input)
+    }
+}
+
+/// A serialized format that data fixes can run on
+pub trait FixFormat: Clone {
+    fn apply(rule: &dyn ErasedFixRule, input: Dynamic<Self>) -> Dynamic<Self>;
+}
+impl FixFormat for Tag {
+    fn apply(rule: &dyn ErasedFixRule, input: Dynamic<Self>) -> Dynamic<Self> {
+        rule.fix_nbt(input)
+    }
+}
+impl FixFormat for Value {
+    fn apply(rule: &dyn ErasedFixRule, input: Dynamic<Self>) -> Dynamic<Self> {
+        rule.fix_json(input)
+    }
+}
+
+/// A named fix that brings one type of data up to the version of its output schema
+pub struct DataFix {
+    name: String,
+    version_key: u32,
+    fix_type: DataFixTypes,
+    rule: Box<dyn ErasedFixRule>,
+}
+impl DataFix {
+    pub fn new(
+        output_schema: &Schema,
+        name: impl Into<String>,
+        fix_type: DataFixTypes,
+        rule: impl FixRule + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            version_key: output_schema.version_key(),
+            fix_type,
+            rule: Box::new(rule),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn version_key(&self) -> u32 {
+        self.version_key
+    }
+
+    pub fn fix_type(&self) -> DataFixTypes {
+        self.fix_type
+    }
+
+    pub fn fix<T: FixFormat>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        T::apply(self.rule.as_ref(), input)
+    }
+}
+impl Debug for DataFix {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DataFix")
+            .field("name", &self.name)
+            .field("version_key", &self.version_key)
+            .field("fix_type", &self.fix_type)
+            .finish()
+    }
+}
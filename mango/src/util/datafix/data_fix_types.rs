@@ -1,7 +1,9 @@
 use crate::shared_constants;
+use crate::util::datafix::data_fix::FixFormat;
 use crate::util::datafix::data_fixers::DataFixer;
 use crate::util::datafix::serialization::dynamic::Dynamic;
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum DataFixTypes {
     Level,
     Player,
@@ -24,7 +26,7 @@ pub enum DataFixTypes {
     EntityChunk,
 }
 impl DataFixTypes {
-    pub fn update<T: Clone>(
+    pub fn update<T: FixFormat>(
         &self,
         data_fixer: &DataFixer,
         input: Dynamic<T>,
@@ -34,7 +36,7 @@ impl DataFixTypes {
         data_fixer.update(self, input, version, new_version)
     }
 
-    pub fn update_to_current_version<T: Clone>(
+    pub fn update_to_current_version<T: FixFormat>(
         &self,
         data_fixer: &DataFixer,
         input: Dynamic<T>,
@@ -2,8 +2,25 @@
 //! are on a different version than the current server.
 
 use crate::shared_constants;
-use crate::util::datafix::data_fix::DataFix;
+use crate::util::datafix::data_fix::{DataFix, FixFormat};
 use crate::util::datafix::data_fix_types::DataFixTypes;
+use crate::util::datafix::data_fixers::data_fixer_builder::State;
+use crate::util::datafix::fixes::biome_rename_fix::BiomeRenameFix;
+use crate::util::datafix::fixes::blending_data_fix::BlendingDataFix;
+use crate::util::datafix::fixes::boat_split_fix::BoatSplitFix;
+use crate::util::datafix::fixes::chunk_height_and_biome_fix::ChunkHeightAndBiomeFix;
+use crate::util::datafix::fixes::chunk_renames_fix::ChunkRenamesFix;
+use crate::util::datafix::fixes::entity_rename_fix::SimpleEntityRenameFix;
+use crate::util::datafix::fixes::feature_flag_remove_fix::FeatureFlagRemoveFix;
+use crate::util::datafix::fixes::item_stack_componentization_fix::ItemStackComponentizationFix;
+use crate::util::datafix::fixes::legacy_dragon_fight_fix::LegacyDragonFightFix;
+use crate::util::datafix::fixes::poi_type_remove_fix::PoiTypeRemoveFix;
+use crate::util::datafix::fixes::poi_type_rename_fix::PoiTypeRenameFix;
+use crate::util::datafix::fixes::stats_rename_fix::StatsRenameFix;
+use crate::util::datafix::fixes::walkers::{
+    ChunkBlockEntitiesFix, ChunkEntitiesFix, HeldItemsFix,
+};
+use crate::util::datafix::schema;
 use crate::util::datafix::schema::Schema;
 use crate::util::datafix::serialization::dynamic::Dynamic;
 use bon::Builder;
@@ -11,14 +28,206 @@ use std::collections::{BTreeSet, HashMap};
 use std::sync::{Arc, LazyLock};
 
 pub static DATA_FIXER: LazyLock<Arc<DataFixer>> = LazyLock::new(|| {
-    // TODO: Add fixers
+    let mut builder = DataFixer::builder();
+    add_fixers(&mut builder);
     Arc::new(
-        DataFixer::builder()
+        builder
             .data_version(shared_constants::get_current_data_version())
             .build(),
     )
 });
 
+const ENTITY_RENAMES_1510: [(&str, &str); 12] = [
+    ("minecraft:commandblock_minecart", "minecraft:command_block_minecart"),
+    ("minecraft:ender_crystal", "minecraft:end_crystal"),
+    ("minecraft:snowman", "minecraft:snow_golem"),
+    ("minecraft:evocation_illager", "minecraft:evoker"),
+    ("minecraft:evocation_fangs", "minecraft:evoker_fangs"),
+    ("minecraft:illusion_illager", "minecraft:illusioner"),
+    ("minecraft:vindication_illager", "minecraft:vindicator"),
+    ("minecraft:villager_golem", "minecraft:iron_golem"),
+    ("minecraft:xp_orb", "minecraft:experience_orb"),
+    ("minecraft:xp_bottle", "minecraft:experience_bottle"),
+    ("minecraft:eye_of_ender_signal", "minecraft:eye_of_ender"),
+    ("minecraft:fireworks_rocket", "minecraft:firework_rocket"),
+];
+
+const BIOME_RENAMES_2838: [(&str, &str); 38] = [
+    ("minecraft:badlands_plateau", "minecraft:badlands"),
+    ("minecraft:bamboo_jungle_hills", "minecraft:bamboo_jungle"),
+    ("minecraft:birch_forest_hills", "minecraft:birch_forest"),
+    ("minecraft:dark_forest_hills", "minecraft:dark_forest"),
+    ("minecraft:desert_hills", "minecraft:desert"),
+    ("minecraft:desert_lakes", "minecraft:desert"),
+    ("minecraft:giant_spruce_taiga_hills", "minecraft:old_growth_spruce_taiga"),
+    ("minecraft:giant_spruce_taiga", "minecraft:old_growth_spruce_taiga"),
+    ("minecraft:giant_tree_taiga_hills", "minecraft:old_growth_pine_taiga"),
+    ("minecraft:giant_tree_taiga", "minecraft:old_growth_pine_taiga"),
+    ("minecraft:gravelly_mountains", "minecraft:windswept_gravelly_hills"),
+    ("minecraft:jungle_edge", "minecraft:sparse_jungle"),
+    ("minecraft:jungle_hills", "minecraft:jungle"),
+    ("minecraft:modified_badlands_plateau", "minecraft:badlands"),
+    ("minecraft:modified_gravelly_mountains", "minecraft:windswept_gravelly_hills"),
+    ("minecraft:modified_jungle_edge", "minecraft:sparse_jungle"),
+    ("minecraft:modified_jungle", "minecraft:jungle"),
+    ("minecraft:modified_wooded_badlands_plateau", "minecraft:wooded_badlands"),
+    ("minecraft:mountain_edge", "minecraft:windswept_hills"),
+    ("minecraft:mountains", "minecraft:windswept_hills"),
+    ("minecraft:mushroom_field_shore", "minecraft:mushroom_fields"),
+    ("minecraft:shattered_savanna", "minecraft:windswept_savanna"),
+    ("minecraft:shattered_savanna_plateau", "minecraft:windswept_savanna"),
+    ("minecraft:snowy_mountains", "minecraft:snowy_plains"),
+    ("minecraft:snowy_taiga_hills", "minecraft:snowy_taiga"),
+    ("minecraft:snowy_taiga_mountains", "minecraft:snowy_taiga"),
+    ("minecraft:snowy_tundra", "minecraft:snowy_plains"),
+    ("minecraft:stone_shore", "minecraft:stony_shore"),
+    ("minecraft:swamp_hills", "minecraft:swamp"),
+    ("minecraft:taiga_hills", "minecraft:taiga"),
+    ("minecraft:taiga_mountains", "minecraft:taiga"),
+    ("minecraft:tall_birch_forest", "minecraft:old_growth_birch_forest"),
+    ("minecraft:tall_birch_hills", "minecraft:old_growth_birch_forest"),
+    ("minecraft:wooded_badlands_plateau", "minecraft:wooded_badlands"),
+    ("minecraft:wooded_hills", "minecraft:forest"),
+    ("minecraft:wooded_mountains", "minecraft:windswept_forest"),
+    ("minecraft:lofty_peaks", "minecraft:jagged_peaks"),
+    ("minecraft:snowcapped_peaks", "minecraft:frozen_peaks"),
+];
+
+/// Registers fixes in version order. Only fixes from recent releases are ported, so data from
+/// before the oldest schema here is passed through as is.
+///
+/// POI chunks need no fix for the 1.18 height change, as their sections are keyed by absolute
+/// section Y, which stays the same when the overworld is extended down.
+fn add_fixers<S: State>(builder: &mut DataFixerBuilder<S>) {
+    let schema = builder.add_schema(1510, 0);
+    for fix_type in [DataFixTypes::Chunk, DataFixTypes::EntityChunk] {
+        builder.add_fixer(DataFix::new(
+            &schema,
+            "EntityTheRenameningFix",
+            fix_type,
+            ChunkEntitiesFix(SimpleEntityRenameFix::new(&ENTITY_RENAMES_1510)),
+        ));
+    }
+
+    let schema = builder.add_schema(2209, 0);
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "Rename POI types",
+        DataFixTypes::PoiChunk,
+        PoiTypeRenameFix::new(&[("minecraft:bee_hive", "minecraft:beehive")]),
+    ));
+
+    let schema = builder.add_schema(2710, 0);
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "Rename play one minute",
+        DataFixTypes::Stats,
+        StatsRenameFix::new(&[("minecraft:play_one_minute", "minecraft:play_time")]),
+    ));
+
+    let schema = builder.add_schema(2832, 0);
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "ChunkHeightAndBiomeFix",
+        DataFixTypes::Chunk,
+        ChunkHeightAndBiomeFix,
+    ));
+
+    let schema = builder.add_schema(2838, 0);
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "Rename Biomes",
+        DataFixTypes::Chunk,
+        BiomeRenameFix::new(&BIOME_RENAMES_2838),
+    ));
+
+    let schema = builder.add_schema(2842, 0);
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "ChunkRenamesFix",
+        DataFixTypes::Chunk,
+        ChunkRenamesFix,
+    ));
+
+    let schema = builder.add_schema(3088, 0);
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "BlendingDataFix",
+        DataFixTypes::Chunk,
+        BlendingDataFix,
+    ));
+
+    let schema = builder.add_schema(3097, 0);
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "Remove unpopulated villager PoI types",
+        DataFixTypes::PoiChunk,
+        PoiTypeRemoveFix::new(&["minecraft:unemployed", "minecraft:nitwit"]),
+    ));
+
+    let schema = builder.add_schema(3685, 0);
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "LegacyDragonFightFix",
+        DataFixTypes::Level,
+        LegacyDragonFightFix,
+    ));
+
+    let schema = builder.add_schema(3818, 3);
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "ItemStackComponentizationFix",
+        DataFixTypes::Chunk,
+        ChunkBlockEntitiesFix(HeldItemsFix(ItemStackComponentizationFix)),
+    ));
+    for fix_type in [DataFixTypes::Chunk, DataFixTypes::EntityChunk] {
+        builder.add_fixer(DataFix::new(
+            &schema,
+            "ItemStackComponentizationFix",
+            fix_type,
+            ChunkEntitiesFix(HeldItemsFix(ItemStackComponentizationFix)),
+        ));
+    }
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "ItemStackComponentizationFix",
+        DataFixTypes::Player,
+        HeldItemsFix(ItemStackComponentizationFix),
+    ));
+
+    let schema = builder.add_schema(3945, 0);
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "Remove 1.21 feature toggle",
+        DataFixTypes::Level,
+        FeatureFlagRemoveFix::new(&["minecraft:update_1_21"]),
+    ));
+
+    let schema = builder.add_schema(4067, 0);
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "Remove Bundle experimental feature flag",
+        DataFixTypes::Level,
+        FeatureFlagRemoveFix::new(&["minecraft:bundle"]),
+    ));
+    for fix_type in [DataFixTypes::Chunk, DataFixTypes::EntityChunk] {
+        builder.add_fixer(DataFix::new(
+            &schema,
+            "BoatSplitFix",
+            fix_type,
+            ChunkEntitiesFix(BoatSplitFix),
+        ));
+    }
+
+    let schema = builder.add_schema(4180, 0);
+    builder.add_fixer(DataFix::new(
+        &schema,
+        "Remove Winter Drop toggle",
+        DataFixTypes::Level,
+        FeatureFlagRemoveFix::new(&["minecraft:winter_drop"]),
+    ));
+}
+
 #[derive(Builder, Debug)]
 pub struct DataFixer {
     #[builder(field)]
@@ -29,22 +238,216 @@ pub struct DataFixer {
     fixer_versions: BTreeSet<u32>,
     data_version: i32,
 }
+impl<S: State> DataFixerBuilder<S> {
+    /// Adds the schema for a version, chained to the latest schema before it
+    pub fn add_schema(&mut self, version: u32, sub_version: u32) -> Schema {
+        let key = schema::make_key(version, sub_version);
+        let parent = self.schemas.keys().filter(|e| **e < key).max().copied();
+        let schema = Schema::new(key, parent);
+        self.schemas.insert(key, schema);
+        schema
+    }
+
+    pub fn add_fixer(&mut self, fix: DataFix) {
+        self.fixer_versions.insert(fix.version_key());
+        self.global_list.push(fix);
+    }
+}
 impl DataFixer {
-    pub fn update<T: Clone>(
+    /// Applies every fix for `data_fix_type` after `version`, up to and including `new_version`,
+    /// in version order
+    pub fn update<T: FixFormat>(
         &self,
         data_fix_type: &DataFixTypes,
         input: Dynamic<T>,
         version: i32,
         new_version: i32,
     ) -> Dynamic<T> {
-        if version < new_version {
-            todo!("Data Fixer is not yet supported");
-        } else {
-            input
+        if version >= new_version {
+            return input;
         }
+
+        // Data saved at a version already went through all of its sub-versions
+        let from = schema::make_key(version.max(0) as u32 + 1, 0);
+        let to = schema::make_key(new_version as u32 + 1, 0);
+        self.fixer_versions
+            .range(from..to)
+            .flat_map(|key| {
+                self.global_list
+                    .iter()
+                    .filter(move |fix| fix.version_key() == *key)
+            })
+            .filter(|fix| fix.fix_type() == *data_fix_type)
+            .fold(input, |input, fix| fix.fix(input))
+    }
+
+    pub fn get_schema(&self, version_key: u32) -> Option<&Schema> {
+        self.schemas.get(&version_key)
+    }
+
+    pub fn data_version(&self) -> i32 {
+        self.data_version
     }
 }
 
 pub fn get_data_fixer() -> Arc<DataFixer> {
     Arc::clone(&DATA_FIXER)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::tag::Tag;
+    use crate::nbt::{nbt_ops, tag_parser};
+
+    /// Fixes data parsed from SNBT to the current version, with `context` as the `__context` the
+    /// chunk loader adds, and checks it against the expected SNBT
+    fn assert_fixed(
+        fix_type: DataFixTypes,
+        version: i32,
+        context: &str,
+        input: &str,
+        expected: &str,
+    ) {
+        let input = Dynamic::new(
+            nbt_ops::INSTANCE.clone(),
+            tag_parser::parse_as_tag(input).unwrap(),
+        );
+        let context = input.create(tag_parser::parse_as_tag(context).unwrap());
+        let fixed = fix_type
+            .update_to_current_version(&DATA_FIXER, input.set("__context", context), version)
+            .remove("__context")
+            .into_value();
+        let expected: Tag = tag_parser::parse_as_tag(expected).unwrap();
+        assert!(
+            fixed == expected,
+            "expected {}\nbut got {}",
+            expected,
+            fixed
+        );
+    }
+
+    fn empty_section(y: i32, biome: &str) -> String {
+        format!(
+            r#"{{Y:{}b,block_states:{{palette:[{{Name:"minecraft:air"}}]}},biomes:{{palette:["{}"]}}}}"#,
+            y, biome
+        )
+    }
+
+    #[test]
+    fn pre_1_18_overworld_chunk() {
+        // Bedrock floor with air in the first row of the bottom layer
+        let mut block_states = vec!["0L".to_string(); 256];
+        block_states[0] = format!("{}L", 0x1111_1111_1111_1111_i64);
+        let block_states = block_states.join(",");
+        let palette = r#"[{Name:"minecraft:bedrock"},{Name:"minecraft:air"}]"#;
+        let input = format!(
+            r#"{{Level:{{xPos:1,zPos:2,Status:"full",Heightmaps:{{}},TileEntities:[],
+                Sections:[{{Y:0b,Palette:{},BlockStates:[L;{}]}}]}}}}"#,
+            palette, block_states
+        );
+        let sections: Vec<String> = (-4..20)
+            .map(|y| match y {
+                0 => format!(
+                    r#"{{Y:0b,block_states:{{palette:{},data:[L;{}]}},
+                        biomes:{{palette:["minecraft:plains"]}}}}"#,
+                    palette, block_states
+                ),
+                _ => empty_section(y, "minecraft:plains"),
+            })
+            .collect();
+        let expected = format!(
+            r#"{{xPos:1,zPos:2,yPos:-4,Status:"empty",isLightOn:0b,block_entities:[],
+                sections:[{}],
+                below_zero_retrogen:{{target_status:"heightmaps",missing_bedrock:[L;65535L]}},
+                blending_data:{{min_section:0,max_section:16}}}}"#,
+            sections.join(",")
+        );
+        assert_fixed(
+            DataFixTypes::Chunk,
+            2730,
+            r#"{dimension:"minecraft:overworld",generator:"minecraft:noise"}"#,
+            &input,
+            &expected,
+        );
+    }
+
+    #[test]
+    fn pre_1_18_nether_chunk() {
+        // Nether wastes in the bottom biome layer, soul sand valley everywhere above it
+        let mut biomes = vec!["170"; 1024];
+        biomes[..16].fill("8");
+        let input = format!(
+            r#"{{Level:{{xPos:0,zPos:0,Status:"full",Heightmaps:{{}},Biomes:[I;{}],
+                Sections:[{{Y:3b,Palette:[{{Name:"minecraft:netherrack"}}]}}]}}}}"#,
+            biomes.join(",")
+        );
+        let sections: Vec<String> = (0..16)
+            .map(|y| match y {
+                0 => format!(
+                    r#"{{Y:0b,block_states:{{palette:[{{Name:"minecraft:air"}}]}},
+                        biomes:{{palette:["minecraft:nether_wastes","minecraft:soul_sand_valley"],
+                        data:[L;{}L]}}}}"#,
+                    0xFFFF_FFFF_FFFF_0000_u64 as i64
+                ),
+                3 => r#"{Y:3b,block_states:{palette:[{Name:"minecraft:netherrack"}]},
+                    biomes:{palette:["minecraft:soul_sand_valley"]}}"#
+                    .to_string(),
+                _ => empty_section(y, "minecraft:soul_sand_valley"),
+            })
+            .collect();
+        let expected = format!(
+            r#"{{xPos:0,zPos:0,yPos:0,Status:"full",Heightmaps:{{}},sections:[{}]}}"#,
+            sections.join(",")
+        );
+        assert_fixed(
+            DataFixTypes::Chunk,
+            2730,
+            r#"{dimension:"minecraft:the_nether",generator:"minecraft:noise"}"#,
+            &input,
+            &expected,
+        );
+    }
+
+    #[test]
+    fn chunk_block_entity_items_to_components() {
+        let input = r#"{xPos:0,zPos:0,Status:"minecraft:full",block_entities:[
+            {id:"minecraft:chest",x:0,y:64,z:0,Items:[
+                {Slot:0b,id:"minecraft:diamond_sword",Count:1b,
+                    tag:{Damage:5,display:{Name:'{"text":"Blade"}'},Custom:1b}},
+                {Slot:1b,id:"minecraft:stone",Count:64b}]}]}"#;
+        let expected = r#"{xPos:0,zPos:0,Status:"minecraft:full",block_entities:[
+            {id:"minecraft:chest",x:0,y:64,z:0,Items:[
+                {Slot:0b,id:"minecraft:diamond_sword",count:1,components:{
+                    "minecraft:damage":5,
+                    "minecraft:custom_name":'{"text":"Blade"}',
+                    "minecraft:custom_data":{Custom:1b}}},
+                {Slot:1b,id:"minecraft:stone",count:64}]}]}"#;
+        assert_fixed(
+            DataFixTypes::Chunk,
+            3700,
+            r#"{dimension:"minecraft:overworld",generator:"minecraft:noise"}"#,
+            input,
+            expected,
+        );
+    }
+
+    #[test]
+    fn entity_chunk_renamed_entities() {
+        let input = r#"{Entities:[
+            {id:"minecraft:snowman",Passengers:[{id:"minecraft:xp_orb",Value:3s}]},
+            {id:"minecraft:villager_golem",HandItems:[{id:"minecraft:poppy",Count:1b},{}]},
+            {id:"minecraft:zombie"}]}"#;
+        let expected = r#"{Entities:[
+            {id:"minecraft:snow_golem",Passengers:[{id:"minecraft:experience_orb",Value:3s}]},
+            {id:"minecraft:iron_golem",HandItems:[{id:"minecraft:poppy",count:1},{}]},
+            {id:"minecraft:zombie"}]}"#;
+        assert_fixed(
+            DataFixTypes::EntityChunk,
+            1500,
+            r#"{dimension:"minecraft:overworld"}"#,
+            input,
+            expected,
+        );
+    }
+}
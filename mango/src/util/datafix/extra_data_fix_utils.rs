@@ -0,0 +1,13 @@
+use crate::util::datafix::serialization::dynamic::Dynamic;
+
+/// Converts a block position stored as a map of `X`, `Y` and `Z` to an int list. Returns the input
+/// unchanged if any coordinate is missing.
+pub fn fix_block_pos<T: Clone>(input: Dynamic<T>) -> Dynamic<T> {
+    let x = input.get("X").as_number();
+    let y = input.get("Y").as_number();
+    let z = input.get("Z").as_number();
+    match (x, y, z) {
+        (Ok(x), Ok(y), Ok(z)) => input.create_int_list(vec![x.as_i32(), y.as_i32(), z.as_i32()]),
+        _ => input,
+    }
+}
@@ -0,0 +1,47 @@
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+use std::collections::HashMap;
+
+/// Renames biomes in the biome palettes of chunk sections
+pub struct BiomeRenameFix {
+    renames: HashMap<String, String>,
+}
+impl BiomeRenameFix {
+    pub fn new(renames: &[(&str, &str)]) -> Self {
+        Self {
+            renames: renames
+                .iter()
+                .map(|(old, new)| (old.to_string(), new.to_string()))
+                .collect(),
+        }
+    }
+
+    fn fix_section<T: Clone>(&self, section: Dynamic<T>) -> Dynamic<T> {
+        section.update("biomes", |biomes| {
+            biomes.update("palette", |palette| match palette.as_list() {
+                Ok(names) => palette.create_list(names.into_iter().map(|name| {
+                    match self.renames.get(&name.as_string_or("")) {
+                        Some(new_name) => name.create_string(new_name),
+                        None => name,
+                    }
+                })),
+                Err(_) => palette,
+            })
+        })
+    }
+
+    fn fix_sections<T: Clone>(&self, input: Dynamic<T>, key: &str) -> Dynamic<T> {
+        input.update(key, |sections| match sections.as_list() {
+            Ok(list) => {
+                sections.create_list(list.into_iter().map(|section| self.fix_section(section)))
+            }
+            Err(_) => sections,
+        })
+    }
+}
+impl FixRule for BiomeRenameFix {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        let input = input.update("Level", |level| self.fix_sections(level, "Sections"));
+        self.fix_sections(input, "sections")
+    }
+}
@@ -0,0 +1,60 @@
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+
+/// Statuses of proto chunks from before any terrain is generated, which new terrain isn't
+/// blended with
+const STATUSES_TO_SKIP_BLENDING: [&str; 4] = [
+    "minecraft:empty",
+    "minecraft:structure_starts",
+    "minecraft:structure_references",
+    "minecraft:biomes",
+];
+
+/// Replaces the `old_noise` flag of a chunk's blending data with the section range of the old
+/// terrain new terrain is blended with. Only overworld chunks are blended, and only those that
+/// had terrain generated, either in the chunk or below y 0 by retrogen.
+///
+/// The dimension is read from the `__context` entry the chunk loader adds before fixing.
+pub struct BlendingDataFix;
+impl FixRule for BlendingDataFix {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        let is_overworld =
+            input.get("__context").get("dimension").as_string_or("") == "minecraft:overworld";
+        let chunk = input.remove("blending_data");
+        let Ok(status) = chunk.get("Status").as_string() else {
+            return chunk;
+        };
+        if !is_overworld {
+            return chunk;
+        }
+        let blending_data =
+            if !STATUSES_TO_SKIP_BLENDING.contains(&ensure_namespaced(&status).as_str()) {
+                Some((-4, 20))
+            } else {
+                let target_status = chunk
+                    .get("below_zero_retrogen")
+                    .get("target_status")
+                    .as_string_or("empty");
+                (!STATUSES_TO_SKIP_BLENDING.contains(&ensure_namespaced(&target_status).as_str()))
+                    .then_some((0, 16))
+            };
+        match blending_data {
+            Some((min_section, max_section)) => {
+                let blending_data = chunk
+                    .empty_map()
+                    .set("min_section", chunk.create_int(min_section))
+                    .set("max_section", chunk.create_int(max_section));
+                chunk.set("blending_data", blending_data)
+            }
+            None => chunk,
+        }
+    }
+}
+
+fn ensure_namespaced(id: &str) -> String {
+    if id.contains(':') {
+        id.to_string()
+    } else {
+        format!("minecraft:{}", id)
+    }
+}
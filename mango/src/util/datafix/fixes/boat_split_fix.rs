@@ -0,0 +1,37 @@
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+
+const BOAT_TYPES: [&str; 8] = [
+    "oak", "spruce", "birch", "jungle", "acacia", "cherry", "dark_oak", "mangrove",
+];
+
+/// Splits boats into an entity per wood type, replacing the `Type` field. Applies to a single
+/// entity.
+pub struct BoatSplitFix;
+impl FixRule for BoatSplitFix {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        let chest = match input.get("id").as_string_or("").as_str() {
+            "minecraft:boat" => false,
+            "minecraft:chest_boat" => true,
+            _ => return input,
+        };
+        let boat_type = input.get("Type").as_string_or("oak");
+        let new_id = match (boat_type.as_str(), chest) {
+            ("bamboo", false) => "minecraft:bamboo_raft".to_string(),
+            ("bamboo", true) => "minecraft:bamboo_chest_raft".to_string(),
+            (boat_type, chest) => {
+                let boat_type = if BOAT_TYPES.contains(&boat_type) {
+                    boat_type
+                } else {
+                    "oak"
+                };
+                match chest {
+                    false => format!("minecraft:{}_boat", boat_type),
+                    true => format!("minecraft:{}_chest_boat", boat_type),
+                }
+            }
+        };
+        let new_id = input.create_string(&new_id);
+        input.remove("Type").set("id", new_id)
+    }
+}
@@ -0,0 +1,432 @@
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Biome names by their numeric ID from before 1.18, before the 1.18 renames
+const BIOMES_BY_ID: [(i32, &str); 79] = [
+    (0, "minecraft:ocean"),
+    (1, "minecraft:plains"),
+    (2, "minecraft:desert"),
+    (3, "minecraft:mountains"),
+    (4, "minecraft:forest"),
+    (5, "minecraft:taiga"),
+    (6, "minecraft:swamp"),
+    (7, "minecraft:river"),
+    (8, "minecraft:nether_wastes"),
+    (9, "minecraft:the_end"),
+    (10, "minecraft:frozen_ocean"),
+    (11, "minecraft:frozen_river"),
+    (12, "minecraft:snowy_tundra"),
+    (13, "minecraft:snowy_mountains"),
+    (14, "minecraft:mushroom_fields"),
+    (15, "minecraft:mushroom_field_shore"),
+    (16, "minecraft:beach"),
+    (17, "minecraft:desert_hills"),
+    (18, "minecraft:wooded_hills"),
+    (19, "minecraft:taiga_hills"),
+    (20, "minecraft:mountain_edge"),
+    (21, "minecraft:jungle"),
+    (22, "minecraft:jungle_hills"),
+    (23, "minecraft:jungle_edge"),
+    (24, "minecraft:deep_ocean"),
+    (25, "minecraft:stone_shore"),
+    (26, "minecraft:snowy_beach"),
+    (27, "minecraft:birch_forest"),
+    (28, "minecraft:birch_forest_hills"),
+    (29, "minecraft:dark_forest"),
+    (30, "minecraft:snowy_taiga"),
+    (31, "minecraft:snowy_taiga_hills"),
+    (32, "minecraft:giant_tree_taiga"),
+    (33, "minecraft:giant_tree_taiga_hills"),
+    (34, "minecraft:wooded_mountains"),
+    (35, "minecraft:savanna"),
+    (36, "minecraft:savanna_plateau"),
+    (37, "minecraft:badlands"),
+    (38, "minecraft:wooded_badlands_plateau"),
+    (39, "minecraft:badlands_plateau"),
+    (40, "minecraft:small_end_islands"),
+    (41, "minecraft:end_midlands"),
+    (42, "minecraft:end_highlands"),
+    (43, "minecraft:end_barrens"),
+    (44, "minecraft:warm_ocean"),
+    (45, "minecraft:lukewarm_ocean"),
+    (46, "minecraft:cold_ocean"),
+    (47, "minecraft:deep_warm_ocean"),
+    (48, "minecraft:deep_lukewarm_ocean"),
+    (49, "minecraft:deep_cold_ocean"),
+    (50, "minecraft:deep_frozen_ocean"),
+    (127, "minecraft:the_void"),
+    (129, "minecraft:sunflower_plains"),
+    (130, "minecraft:desert_lakes"),
+    (131, "minecraft:gravelly_mountains"),
+    (132, "minecraft:flower_forest"),
+    (133, "minecraft:taiga_mountains"),
+    (134, "minecraft:swamp_hills"),
+    (140, "minecraft:ice_spikes"),
+    (149, "minecraft:modified_jungle"),
+    (151, "minecraft:modified_jungle_edge"),
+    (155, "minecraft:tall_birch_forest"),
+    (156, "minecraft:tall_birch_hills"),
+    (157, "minecraft:dark_forest_hills"),
+    (158, "minecraft:snowy_taiga_mountains"),
+    (160, "minecraft:giant_spruce_taiga"),
+    (161, "minecraft:giant_spruce_taiga_hills"),
+    (162, "minecraft:modified_gravelly_mountains"),
+    (163, "minecraft:shattered_savanna"),
+    (164, "minecraft:shattered_savanna_plateau"),
+    (165, "minecraft:eroded_badlands"),
+    (166, "minecraft:modified_wooded_badlands_plateau"),
+    (167, "minecraft:modified_badlands_plateau"),
+    (168, "minecraft:bamboo_jungle"),
+    (169, "minecraft:bamboo_jungle_hills"),
+    (170, "minecraft:soul_sand_valley"),
+    (171, "minecraft:crimson_forest"),
+    (172, "minecraft:warped_forest"),
+    (173, "minecraft:basalt_deltas"),
+];
+const OLD_SECTION_COUNT: i32 = 16;
+const BIOME_CELLS_PER_SECTION: usize = 64;
+/// Proto chunk statuses from before any terrain is generated, which new terrain isn't blended with
+const STATUSES_TO_SKIP_BLENDING: [&str; 4] = [
+    "empty",
+    "structure_starts",
+    "structure_references",
+    "biomes",
+];
+/// Blocks that noise generation and surface building place, so a proto chunk with only these
+/// hasn't reached the carvers yet
+const BLOCKS_BEFORE_FEATURE_STATUS: [&str; 35] = [
+    "minecraft:air",
+    "minecraft:basalt",
+    "minecraft:bedrock",
+    "minecraft:blackstone",
+    "minecraft:calcite",
+    "minecraft:cave_air",
+    "minecraft:coarse_dirt",
+    "minecraft:crimson_nylium",
+    "minecraft:dirt",
+    "minecraft:end_stone",
+    "minecraft:grass_block",
+    "minecraft:gravel",
+    "minecraft:ice",
+    "minecraft:lava",
+    "minecraft:mycelium",
+    "minecraft:nether_wart_block",
+    "minecraft:netherrack",
+    "minecraft:orange_terracotta",
+    "minecraft:packed_ice",
+    "minecraft:podzol",
+    "minecraft:powder_snow",
+    "minecraft:red_sand",
+    "minecraft:red_sandstone",
+    "minecraft:sand",
+    "minecraft:sandstone",
+    "minecraft:snow_block",
+    "minecraft:soul_sand",
+    "minecraft:soul_soil",
+    "minecraft:stone",
+    "minecraft:terracotta",
+    "minecraft:tuff",
+    "minecraft:warped_nylium",
+    "minecraft:warped_wart_block",
+    "minecraft:water",
+    "minecraft:white_terracotta",
+];
+
+/// Converts chunks to the 1.18 section format: block states and biomes become a paletted
+/// container per section, and overworld chunks are extended down to y -64 with empty sections.
+///
+/// Overworld chunks of noise generated worlds are marked for blending with the new terrain, and
+/// those with a bedrock floor are marked to have the terrain below y 0 generated when loaded.
+///
+/// The dimension and chunk generator are read from the `__context` entry the chunk loader adds
+/// before fixing, and chunks without one are treated as not being in the overworld.
+pub struct ChunkHeightAndBiomeFix;
+impl FixRule for ChunkHeightAndBiomeFix {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        let Ok(level) = input.get("Level").result() else {
+            return input;
+        };
+        let dimension = input.get("__context").get("dimension").as_string_or("");
+        let generator = input.get("__context").get("generator").as_string_or("");
+        let is_overworld = dimension == "minecraft:overworld";
+        let min_section = if is_overworld { -4 } else { 0 };
+        let max_section = if is_overworld { 20 } else { OLD_SECTION_COUNT };
+        let default_biome = match dimension.as_str() {
+            "minecraft:the_nether" => "minecraft:nether_wastes",
+            "minecraft:the_end" => "minecraft:the_end",
+            _ => "minecraft:plains",
+        };
+        let biomes = level.get("Biomes").as_int_list().ok();
+
+        let mut old_sections: BTreeMap<i32, Dynamic<T>> = level
+            .get("Sections")
+            .as_list()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|section| (section.get("Y").as_int(0), section))
+            .collect();
+        let block_names: BTreeSet<String> = old_sections
+            .values()
+            .flat_map(|section| section.get("Palette").as_list().unwrap_or_default())
+            .map(|state| state.get("Name").as_string_or("minecraft:air"))
+            .collect();
+        // Block states of the old bottom section, to find where its bedrock floor is
+        let mut bottom_block_states = None;
+        let mut sections = Vec::new();
+        for y in min_section..max_section {
+            let old_section = old_sections.remove(&y);
+            let is_old_bottom = y == 0 && old_section.is_some();
+            let section = old_section
+                .unwrap_or_else(|| level.empty_map().set("Y", level.create_byte(y as i8)));
+            let section = fix_block_states(section);
+            if is_old_bottom {
+                bottom_block_states = section.get("block_states").result().ok();
+            }
+            let section_biomes = make_biome_container(&level, biomes.as_deref(), y, default_biome);
+            sections.push((y, section.set("biomes", section_biomes)));
+        }
+        // Sections outside the new height only hold light, so are kept as they are
+        sections.extend(old_sections);
+        sections.sort_by_key(|(y, _)| *y);
+
+        let mut level = level
+            .set(
+                "Sections",
+                input.create_list(sections.into_iter().map(|(_, section)| section)),
+            )
+            .remove("Biomes")
+            .set("yPos", input.create_int(min_section))
+            .update("CarvingMasks", |masks| {
+                fix_carving_masks(masks, is_overworld)
+            });
+        if is_overworld {
+            // Heights are relative to the bottom of the world, so they are recomputed on load
+            level = level.remove("Heightmaps");
+            level = predict_chunk_status_before_surface(level, block_names);
+            if generator == "minecraft:noise" {
+                level = add_blending_data(level, bottom_block_states.as_ref());
+            }
+        }
+        input.set("Level", level)
+    }
+}
+
+/// Proto chunks saved at the noise status that already have blocks later statuses place were
+/// carved, so they continue from liquid carvers instead of being generated again
+fn predict_chunk_status_before_surface<T: Clone>(
+    level: Dynamic<T>,
+    block_names: BTreeSet<String>,
+) -> Dynamic<T> {
+    level.update("Status", |status| {
+        if status.as_string_or("empty") != "noise" {
+            return status;
+        }
+        let is_carved = block_names
+            .iter()
+            .any(|e| !BLOCKS_BEFORE_FEATURE_STATUS.contains(&e.as_str()));
+        if is_carved {
+            status.create_string("liquid_carvers")
+        } else {
+            status
+        }
+    })
+}
+
+/// Marks an overworld chunk for blending with the terrain generated next to it. If the old
+/// bottom section has a bedrock floor with gaps, the terrain below it is generated when the chunk
+/// is next loaded, with `missing_bedrock` holding the columns the floor is missing in.
+fn add_blending_data<T: Clone>(
+    level: Dynamic<T>,
+    bottom_block_states: Option<&Dynamic<T>>,
+) -> Dynamic<T> {
+    let Ok(status) = level.get("Status").result() else {
+        return level;
+    };
+    let status_name = status.as_string_or("");
+    if status_name == "empty" {
+        return level;
+    }
+    let old_noise = level.empty_map().set(
+        "old_noise",
+        level.create_boolean(STATUSES_TO_SKIP_BLENDING.contains(&status_name.as_str())),
+    );
+    let mut level = level.set("blending_data", old_noise);
+    let Some(bottom_block_states) = bottom_block_states else {
+        return level;
+    };
+
+    let bottom_layer = bottom_layer_block_names(bottom_block_states);
+    let mut has_air = status_name == "noise";
+    let mut missing_bedrock = [0u64; 4];
+    for (index, name) in bottom_layer.iter().enumerate() {
+        if name.as_deref() == Some("minecraft:air") {
+            has_air = true;
+        }
+        if name.as_deref() != Some("minecraft:bedrock") {
+            missing_bedrock[index / 64] |= 1 << (index % 64);
+        }
+    }
+    let is_all_missing = missing_bedrock.iter().all(|e| *e == u64::MAX);
+    if has_air && !is_all_missing {
+        let target_status = if status_name == "full" {
+            level.create_string("heightmaps")
+        } else {
+            status
+        };
+        // Trailing empty longs are left out, like Java's BitSet.toLongArray
+        let len = missing_bedrock
+            .iter()
+            .rposition(|e| *e != 0)
+            .map_or(0, |e| e + 1);
+        let missing_bedrock = missing_bedrock[..len].iter().map(|e| *e as i64).collect();
+        let below_zero_retrogen = level
+            .empty_map()
+            .set("target_status", target_status)
+            .set("missing_bedrock", level.create_long_list(missing_bedrock));
+        let empty = level.create_string("empty");
+        level = level
+            .set("below_zero_retrogen", below_zero_retrogen)
+            .set("Status", empty);
+    }
+    let is_light_on = level.create_boolean(false);
+    level.set("isLightOn", is_light_on)
+}
+
+/// Names of the blocks in the bottom layer of a section's block states, indexed by `z << 4 | x`.
+/// Entries are `None` where the data doesn't cover the block.
+fn bottom_layer_block_names<T: Clone>(block_states: &Dynamic<T>) -> Vec<Option<String>> {
+    let palette: Vec<String> = block_states
+        .get("palette")
+        .as_list()
+        .unwrap_or_default()
+        .iter()
+        .map(|state| state.get("Name").as_string_or(""))
+        .collect();
+    let data = block_states
+        .get("data")
+        .result()
+        .and_then(|e| e.as_long_list())
+        .unwrap_or_default();
+    let bits = ceil_log2(palette.len()).max(4);
+    let values_per_long = (64 / bits) as usize;
+    (0..256)
+        .map(|index| match palette.len() {
+            0 => None,
+            1 => Some(palette[0].clone()),
+            _ => {
+                let long = *data.get(index / values_per_long)? as u64;
+                let shift = (index % values_per_long) as u32 * bits;
+                let value = (long >> shift & ((1 << bits) - 1)) as usize;
+                palette.get(value).cloned()
+            }
+        })
+        .collect()
+}
+
+fn fix_block_states<T: Clone>(section: Dynamic<T>) -> Dynamic<T> {
+    let palette = match section.get("Palette").result() {
+        Ok(palette) => palette,
+        Err(_) => {
+            let air = section.empty_map();
+            let air = air.clone().set("Name", air.create_string("minecraft:air"));
+            section.create_list([air])
+        }
+    };
+    let mut block_states = section.empty_map().set("palette", palette);
+    if let Ok(data) = section.get("BlockStates").result() {
+        // Already packed without spanning longs since 1.16, which is what 1.18 uses
+        block_states = block_states.set("data", data);
+    }
+    section
+        .remove("Palette")
+        .remove("BlockStates")
+        .set("block_states", block_states)
+}
+
+/// Builds the biome paletted container of a section from the old biome array, which holds 4x4x4
+/// cells from the bottom of the old height. Sections outside of it repeat the nearest layer.
+fn make_biome_container<T: Clone>(
+    level: &Dynamic<T>,
+    biomes: Option<&[i32]>,
+    section_y: i32,
+    default_biome: &str,
+) -> Dynamic<T> {
+    let mut palette: Vec<String> = Vec::new();
+    let mut indices = Vec::with_capacity(BIOME_CELLS_PER_SECTION);
+    for cell in 0..BIOME_CELLS_PER_SECTION {
+        let name = match biomes {
+            Some(biomes) if biomes.len() >= 16 => {
+                let layers = (biomes.len() / 16) as i32;
+                let layer = (section_y * 4 + (cell >> 4) as i32).clamp(0, layers - 1) as usize;
+                biome_name(biomes[layer << 4 | (cell & 15)]).unwrap_or(default_biome)
+            }
+            _ => default_biome,
+        };
+        let index = match palette.iter().position(|e| e == name) {
+            Some(index) => index,
+            None => {
+                palette.push(name.to_string());
+                palette.len() - 1
+            }
+        };
+        indices.push(index as u64);
+    }
+
+    let container = level.empty_map().set(
+        "palette",
+        level.create_list(palette.iter().map(|e| level.create_string(e))),
+    );
+    let bits = ceil_log2(palette.len());
+    if bits == 0 {
+        return container;
+    }
+    container.set("data", level.create_long_list(pack(&indices, bits)))
+}
+
+fn biome_name(id: i32) -> Option<&'static str> {
+    BIOMES_BY_ID
+        .iter()
+        .find_map(|(biome_id, name)| (*biome_id == id).then_some(*name))
+}
+
+fn ceil_log2(value: usize) -> u32 {
+    usize::BITS - value.saturating_sub(1).leading_zeros()
+}
+
+/// Packs values into longs without spanning values across longs
+fn pack(values: &[u64], bits: u32) -> Vec<i64> {
+    let values_per_long = (64 / bits) as usize;
+    values
+        .chunks(values_per_long)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0u64, |long, (i, value)| long | value << (i as u32 * bits)) as i64
+        })
+        .collect()
+}
+
+/// Carving masks are stored as long arrays from 1.18 and start at the new bottom of the world
+fn fix_carving_masks<T: Clone>(masks: Dynamic<T>, is_overworld: bool) -> Dynamic<T> {
+    let Ok(entries) = masks.as_map() else {
+        return masks;
+    };
+    entries
+        .into_iter()
+        .fold(masks.clone(), |masks, (key, mask)| {
+            let Ok(bytes) = mask.as_byte_list() else {
+                return masks;
+            };
+            // 64 blocks of 16x16 columns below the old bottom
+            let mut longs = vec![0; if is_overworld { 64 * 256 / 64 } else { 0 }];
+            longs.extend(bytes.chunks(8).map(|chunk| {
+                chunk.iter().enumerate().fold(0u64, |long, (i, byte)| {
+                    long | (*byte as u8 as u64) << (i * 8)
+                }) as i64
+            }));
+            let longs = mask.create_long_list(longs);
+            masks.set(&key, longs)
+        })
+}
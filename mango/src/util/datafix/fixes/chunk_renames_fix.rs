@@ -0,0 +1,28 @@
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+
+/// Moves chunk data out of the `Level` compound to the root and renames fields to the 1.18 format
+pub struct ChunkRenamesFix;
+impl FixRule for ChunkRenamesFix {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        let Ok(level) = input.get("Level").result() else {
+            return input;
+        };
+        let level = level
+            .rename_field("Sections", "sections")
+            .rename_field("TileEntities", "block_entities")
+            .rename_field("TileTicks", "block_ticks")
+            .rename_field("LiquidTicks", "fluid_ticks")
+            .rename_field("Entities", "entities")
+            .rename_field("Structures", "structures")
+            .update("structures", |structures| {
+                structures.rename_field("Starts", "starts")
+            });
+        let entries = level.as_map().unwrap_or_default();
+        entries
+            .into_iter()
+            .fold(input.remove("Level"), |input, (key, value)| {
+                input.set(&key, value)
+            })
+    }
+}
@@ -0,0 +1,30 @@
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+use std::collections::HashMap;
+
+/// Renames entity IDs. Applies to a single entity, see
+/// [crate::util::datafix::fixes::walkers::ChunkEntitiesFix].
+pub struct SimpleEntityRenameFix {
+    renames: HashMap<String, String>,
+}
+impl SimpleEntityRenameFix {
+    pub fn new(renames: &[(&str, &str)]) -> Self {
+        Self {
+            renames: renames
+                .iter()
+                .map(|(old, new)| (old.to_string(), new.to_string()))
+                .collect(),
+        }
+    }
+}
+impl FixRule for SimpleEntityRenameFix {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        match self.renames.get(&input.get("id").as_string_or("")) {
+            Some(new_id) => {
+                let new_id = input.create_string(new_id);
+                input.set("id", new_id)
+            }
+            None => input,
+        }
+    }
+}
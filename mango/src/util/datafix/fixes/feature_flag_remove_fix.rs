@@ -0,0 +1,40 @@
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+use std::collections::HashSet;
+
+/// Removes feature flags that no longer exist, usually experiments that became part of the game,
+/// from the enabled features of the level. Removed flags are recorded in `removed_features`.
+pub struct FeatureFlagRemoveFix {
+    flags_to_remove: HashSet<String>,
+}
+impl FeatureFlagRemoveFix {
+    pub fn new(flags_to_remove: &[&str]) -> Self {
+        Self {
+            flags_to_remove: flags_to_remove.iter().map(|e| e.to_string()).collect(),
+        }
+    }
+}
+impl FixRule for FeatureFlagRemoveFix {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        let Ok(enabled_features) = input.get("enabled_features").as_list() else {
+            return input;
+        };
+        let (removed, kept): (Vec<_>, Vec<_>) = enabled_features
+            .into_iter()
+            .partition(|e| self.flags_to_remove.contains(&e.as_string_or("")));
+        if removed.is_empty() {
+            return input;
+        }
+
+        let mut removed_features = input
+            .get("removed_features")
+            .as_list()
+            .unwrap_or_default();
+        removed_features.extend(removed);
+        let enabled_features = input.create_list(kept);
+        let removed_features = input.create_list(removed_features);
+        input
+            .set("enabled_features", enabled_features)
+            .set("removed_features", removed_features)
+    }
+}
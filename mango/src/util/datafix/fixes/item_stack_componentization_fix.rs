@@ -0,0 +1,212 @@
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+
+const HIDE_ENCHANTMENTS: i32 = 1;
+const HIDE_UNBREAKABLE: i32 = 4;
+const HIDE_STORED_ENCHANTMENTS: i32 = 32;
+const HIDE_DYE: i32 = 64;
+
+/// Converts item stacks from the `tag` compound to 1.20.5 data components. Applies to a single
+/// item stack, see [crate::util::datafix::fixes::walkers::HeldItemsFix].
+///
+/// Tag entries without a component ported here are kept in `minecraft:custom_data` so no data is
+/// lost.
+pub struct ItemStackComponentizationFix;
+impl FixRule for ItemStackComponentizationFix {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        if !input.get("id").is_present() || !input.get("Count").is_present() {
+            return input;
+        }
+        let count = input.create_int(input.get("Count").as_int(1));
+        let mut item = ItemStackData {
+            components: input.empty_map(),
+            tag: input.get("tag").or_empty_map(),
+        };
+        item.fix();
+
+        let mut input = input
+            .remove("Count")
+            .remove("tag")
+            .set("count", count);
+        if !item.tag.as_map().unwrap_or_default().is_empty() {
+            let custom_data = item.tag.clone();
+            item.set_component("minecraft:custom_data", custom_data);
+        }
+        if !item.components.as_map().unwrap_or_default().is_empty() {
+            input = input.set("components", item.components);
+        }
+        input
+    }
+}
+
+struct ItemStackData<T: Clone> {
+    components: Dynamic<T>,
+    /// What's left of the old tag
+    tag: Dynamic<T>,
+}
+impl<T: Clone> ItemStackData<T> {
+    fn fix(&mut self) {
+        let hide_flags = self.take("HideFlags").map_or(0, |e| e.as_int(0));
+
+        self.move_to_component("Damage", "minecraft:damage", |e| {
+            (e.as_int(0) != 0).then_some(e)
+        });
+        self.move_to_component("RepairCost", "minecraft:repair_cost", |e| {
+            (e.as_int(0) != 0).then_some(e)
+        });
+        self.move_to_component("CustomModelData", "minecraft:custom_model_data", Some);
+        self.move_to_component("Unbreakable", "minecraft:unbreakable", |e| {
+            e.as_bool(false)
+                .then(|| with_tooltip(e.empty_map(), hide_flags, HIDE_UNBREAKABLE))
+        });
+        self.move_to_component("Enchantments", "minecraft:enchantments", |e| {
+            Some(fix_enchantments(e, hide_flags, HIDE_ENCHANTMENTS))
+        });
+        self.move_to_component("StoredEnchantments", "minecraft:stored_enchantments", |e| {
+            Some(fix_enchantments(e, hide_flags, HIDE_STORED_ENCHANTMENTS))
+        });
+        self.move_to_component("EntityTag", "minecraft:entity_data", Some);
+        self.move_to_component("SkullOwner", "minecraft:profile", |e| match e.as_string() {
+            Ok(name) => Some(e.empty_map().set("name", e.create_string(&name))),
+            Err(_) => Some(e.rename_field("Name", "name")),
+        });
+        self.fix_display(hide_flags);
+        self.fix_potion();
+        self.fix_block_entity_tag();
+        self.fix_books();
+    }
+
+    fn take(&mut self, key: &str) -> Option<Dynamic<T>> {
+        let value = self.tag.get(key).result().ok()?;
+        self.tag = self.tag.clone().remove(key);
+        Some(value)
+    }
+
+    fn set_component(&mut self, name: &str, value: Dynamic<T>) {
+        self.components = self.components.clone().set(name, value);
+    }
+
+    fn move_to_component(
+        &mut self,
+        key: &str,
+        name: &str,
+        fix_fn: impl FnOnce(Dynamic<T>) -> Option<Dynamic<T>>,
+    ) {
+        if let Some(value) = self.take(key).and_then(fix_fn) {
+            self.set_component(name, value);
+        }
+    }
+
+    fn fix_display(&mut self, hide_flags: i32) {
+        let Some(mut display) = self.take("display") else {
+            return;
+        };
+        for (key, name) in [("Name", "minecraft:custom_name"), ("Lore", "minecraft:lore")] {
+            if let Ok(value) = display.get(key).result() {
+                display = display.remove(key);
+                self.set_component(name, value);
+            }
+        }
+        if let Ok(color) = display.get("color").result() {
+            display = display.remove("color");
+            let dyed_color = color.empty_map().set("rgb", color);
+            self.set_component("minecraft:dyed_color", with_tooltip(dyed_color, hide_flags, HIDE_DYE));
+        }
+        if !display.as_map().unwrap_or_default().is_empty() {
+            self.tag = self.tag.clone().set("display", display);
+        }
+    }
+
+    fn fix_potion(&mut self) {
+        let mut contents = self.components.empty_map();
+        for (key, name) in [
+            ("Potion", "potion"),
+            ("CustomPotionColor", "custom_color"),
+            ("custom_potion_effects", "custom_effects"),
+        ] {
+            if let Some(value) = self.take(key) {
+                contents = contents.set(name, value);
+            }
+        }
+        if !contents.as_map().unwrap_or_default().is_empty() {
+            self.set_component("minecraft:potion_contents", contents);
+        }
+    }
+
+    /// Items of containers become the container component, fixing each stack
+    fn fix_block_entity_tag(&mut self) {
+        let Some(mut block_entity) = self.take("BlockEntityTag") else {
+            return;
+        };
+        if let Ok(items) = block_entity.get("Items").as_list() {
+            block_entity = block_entity.remove("Items");
+            let container = self.components.create_list(items.into_iter().map(|item| {
+                let slot = item.get("Slot").as_int(0);
+                let entry = item.empty_map().set("slot", item.create_int(slot));
+                let item = ItemStackComponentizationFix.fix(item.remove("Slot"));
+                entry.set("item", item)
+            }));
+            self.set_component("minecraft:container", container);
+        }
+        if !block_entity.as_map().unwrap_or_default().is_empty() {
+            self.set_component("minecraft:block_entity_data", block_entity);
+        }
+    }
+
+    /// Pages and titles of books become filterable text, with the raw text under `raw`
+    fn fix_books(&mut self) {
+        let Some(pages) = self.take("pages") else {
+            return;
+        };
+        let pages = self.components.create_list(
+            pages
+                .as_list()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|page| page.empty_map().set("raw", page)),
+        );
+        let mut content = self.components.empty_map().set("pages", pages);
+        match self.take("title") {
+            Some(title) => {
+                content = content.set("title", title.empty_map().set("raw", title));
+                for key in ["author", "generation", "resolved"] {
+                    if let Some(value) = self.take(key) {
+                        content = content.set(key, value);
+                    }
+                }
+                self.set_component("minecraft:written_book_content", content);
+            }
+            None => self.set_component("minecraft:writable_book_content", content),
+        }
+    }
+}
+
+fn with_tooltip<T: Clone>(component: Dynamic<T>, hide_flags: i32, flag: i32) -> Dynamic<T> {
+    if hide_flags & flag != 0 {
+        let show = component.create_boolean(false);
+        component.set("show_in_tooltip", show)
+    } else {
+        component
+    }
+}
+
+/// Enchantments go from a list of IDs and levels to a map of levels by ID
+fn fix_enchantments<T: Clone>(enchantments: Dynamic<T>, hide_flags: i32, flag: i32) -> Dynamic<T> {
+    let levels = enchantments
+        .as_list()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|e| {
+            let id = e.get("id").as_string().ok()?;
+            Some((id, e.get("lvl").as_int(1)))
+        })
+        .fold(enchantments.empty_map(), |levels, (id, level)| {
+            let level = levels.create_int(level);
+            levels.set(&id, level)
+        });
+    with_tooltip(
+        enchantments.empty_map().set("levels", levels),
+        hide_flags,
+        flag,
+    )
+}
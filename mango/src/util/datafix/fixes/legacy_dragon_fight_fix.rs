@@ -0,0 +1,24 @@
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::extra_data_fix_utils;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+
+/// Moves the End's dragon fight out of the legacy per-dimension data into the level data, and
+/// stores the exit portal position as an int list
+pub struct LegacyDragonFightFix;
+impl FixRule for LegacyDragonFightFix {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        if input.get("DragonFight").is_present() {
+            return input;
+        }
+        let legacy = input
+            .get("DimensionData")
+            .get("1")
+            .get("DragonFight")
+            .or_empty_map();
+        input.set("DragonFight", fix_dragon_fight(legacy))
+    }
+}
+
+fn fix_dragon_fight<T: Clone>(input: Dynamic<T>) -> Dynamic<T> {
+    input.update("ExitPortalLocation", extra_data_fix_utils::fix_block_pos)
+}
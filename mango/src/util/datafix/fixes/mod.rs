@@ -0,0 +1,13 @@
+pub mod biome_rename_fix;
+pub mod blending_data_fix;
+pub mod boat_split_fix;
+pub mod chunk_height_and_biome_fix;
+pub mod chunk_renames_fix;
+pub mod entity_rename_fix;
+pub mod feature_flag_remove_fix;
+pub mod item_stack_componentization_fix;
+pub mod legacy_dragon_fight_fix;
+pub mod poi_type_remove_fix;
+pub mod poi_type_rename_fix;
+pub mod stats_rename_fix;
+pub mod walkers;
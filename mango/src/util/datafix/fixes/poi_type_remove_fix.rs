@@ -0,0 +1,27 @@
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::fixes::walkers;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+
+/// Removes the POI records of types that no longer exist from a POI chunk
+pub struct PoiTypeRemoveFix {
+    removed_types: &'static [&'static str],
+}
+impl PoiTypeRemoveFix {
+    pub fn new(removed_types: &'static [&'static str]) -> Self {
+        Self { removed_types }
+    }
+}
+impl FixRule for PoiTypeRemoveFix {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        walkers::update_poi_records(input, |records| {
+            records
+                .into_iter()
+                .filter(|record| {
+                    !self
+                        .removed_types
+                        .contains(&record.get("type").as_string_or("").as_str())
+                })
+                .collect()
+        })
+    }
+}
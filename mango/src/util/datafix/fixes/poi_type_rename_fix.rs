@@ -0,0 +1,37 @@
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::fixes::walkers;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+use std::collections::HashMap;
+
+/// Renames the types of the POI records in a POI chunk
+pub struct PoiTypeRenameFix {
+    renames: HashMap<String, String>,
+}
+impl PoiTypeRenameFix {
+    pub fn new(renames: &[(&str, &str)]) -> Self {
+        Self {
+            renames: renames
+                .iter()
+                .map(|(old, new)| (old.to_string(), new.to_string()))
+                .collect(),
+        }
+    }
+}
+impl FixRule for PoiTypeRenameFix {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        walkers::update_poi_records(input, |records| {
+            records
+                .into_iter()
+                .map(
+                    |record| match self.renames.get(&record.get("type").as_string_or("")) {
+                        Some(new_type) => {
+                            let new_type = record.create_string(new_type);
+                            record.set("type", new_type)
+                        }
+                        None => record,
+                    },
+                )
+                .collect()
+        })
+    }
+}
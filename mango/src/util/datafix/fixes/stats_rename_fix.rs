@@ -0,0 +1,29 @@
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+use std::collections::HashMap;
+
+/// Renames custom stats, the ones under `minecraft:custom` in a stats file
+pub struct StatsRenameFix {
+    renames: HashMap<String, String>,
+}
+impl StatsRenameFix {
+    pub fn new(renames: &[(&str, &str)]) -> Self {
+        Self {
+            renames: renames
+                .iter()
+                .map(|(old, new)| (old.to_string(), new.to_string()))
+                .collect(),
+        }
+    }
+}
+impl FixRule for StatsRenameFix {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        input.update("stats", |stats| {
+            stats.update("minecraft:custom", |custom| {
+                self.renames
+                    .iter()
+                    .fold(custom, |custom, (old, new)| custom.rename_field(old, new))
+            })
+        })
+    }
+}
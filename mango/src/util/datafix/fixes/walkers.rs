@@ -0,0 +1,82 @@
+//! Adapters that find the nested data a [FixRule] applies to, since fixes here work on untyped
+//! data rather than DataFixerUpper's typed schemas. Walkers check every place the data was stored
+//! across chunk formats, so the same walker can be registered for any version.
+
+use crate::util::datafix::data_fix::FixRule;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+
+/// Applies `rule` to every element of a list entry, if it exists
+pub fn update_list<T: Clone>(
+    input: Dynamic<T>,
+    key: &str,
+    rule: &impl FixRule,
+) -> Dynamic<T> {
+    input.update(key, |list| match list.as_list() {
+        Ok(elements) => list.create_list(elements.into_iter().map(|e| rule.fix(e))),
+        Err(_) => list,
+    })
+}
+
+/// Applies a rule to each entity of a chunk, entity chunk or proto chunk, including passengers
+pub struct ChunkEntitiesFix<R: FixRule>(pub R);
+impl<R: FixRule> FixRule for ChunkEntitiesFix<R> {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        let rule = EntityFix(&self.0);
+        let input = input.update("Level", |level| update_list(level, "Entities", &rule));
+        let input = update_list(input, "Entities", &rule);
+        update_list(input, "entities", &rule)
+    }
+}
+
+struct EntityFix<'a, R: FixRule>(&'a R);
+impl<R: FixRule> FixRule for EntityFix<'_, R> {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        let input = self.0.fix(input);
+        update_list(input, "Passengers", self)
+    }
+}
+
+/// Applies a rule to each block entity of a chunk, before or after the 1.18 chunk format
+pub struct ChunkBlockEntitiesFix<R: FixRule>(pub R);
+impl<R: FixRule> FixRule for ChunkBlockEntitiesFix<R> {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        let input = input.update("Level", |level| update_list(level, "TileEntities", &self.0));
+        update_list(input, "block_entities", &self.0)
+    }
+}
+
+/// Applies a rule to each item stack held by an entity or block entity
+pub struct HeldItemsFix<R: FixRule>(pub R);
+impl<R: FixRule> FixRule for HeldItemsFix<R> {
+    fn fix<T: Clone>(&self, input: Dynamic<T>) -> Dynamic<T> {
+        let input = ["Items", "HandItems", "ArmorItems", "Inventory", "EnderItems"]
+            .into_iter()
+            .fold(input, |input, key| update_list(input, key, &self.0));
+        ["Item", "RecordItem", "Book", "SaddleItem", "DecorItem", "body_armor_item"]
+            .into_iter()
+            .fold(input, |input, key| input.update(key, |item| self.0.fix(item)))
+    }
+}
+
+/// Updates the records of each section of a POI chunk
+pub fn update_poi_records<T: Clone>(
+    input: Dynamic<T>,
+    update_fn: impl Fn(Vec<Dynamic<T>>) -> Vec<Dynamic<T>>,
+) -> Dynamic<T> {
+    input.update("Sections", |sections| {
+        let keys: Vec<String> = sections
+            .as_map()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        keys.iter().fold(sections, |sections, key| {
+            sections.update(key, |section| {
+                section.update("Records", |records| match records.as_list() {
+                    Ok(elements) => records.create_list(update_fn(elements)),
+                    Err(_) => records,
+                })
+            })
+        })
+    })
+}
@@ -1,5 +1,7 @@
-mod data_fix;
+pub mod data_fix;
 pub mod data_fix_types;
 pub mod data_fixers;
-mod schema;
+pub mod extra_data_fix_utils;
+pub mod fixes;
+pub mod schema;
 pub mod serialization;
@@ -1,2 +1,36 @@
-#[derive(Debug)]
-pub struct Schema {}
+/// Packs a data version and sub-version into a single key, so fixes within the same data version
+/// can be ordered
+pub fn make_key(version: u32, sub_version: u32) -> u32 {
+    version * 10 + sub_version
+}
+
+/// Data version of a key from [make_key]
+pub fn get_version(key: u32) -> u32 {
+    key / 10
+}
+
+/// Layout of the data at a version key, chained to the schema before it. Fixes here work on
+/// untyped [crate::util::datafix::serialization::dynamic::Dynamic] values, so unlike
+/// DataFixerUpper's schemas this doesn't describe the types themselves.
+#[derive(Copy, Clone, Debug)]
+pub struct Schema {
+    version_key: u32,
+    parent: Option<u32>,
+}
+impl Schema {
+    pub fn new(version_key: u32, parent: Option<u32>) -> Self {
+        Self {
+            version_key,
+            parent,
+        }
+    }
+
+    pub fn version_key(&self) -> u32 {
+        self.version_key
+    }
+
+    /// Version key of the previous schema
+    pub fn parent(&self) -> Option<u32> {
+        self.parent
+    }
+}
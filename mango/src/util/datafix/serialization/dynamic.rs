@@ -1,18 +1,245 @@
-use crate::util::datafix::serialization::dynamic_ops::DynamicOps;
+use crate::util::datafix::serialization::dynamic_ops::{DynamicOps, Number};
+use anyhow::Result;
 use std::sync::Arc;
 
-// TODO: Can we avoid this and do better? It's way too messy in vanilla code
+/// A value of some serialized format along with the ops to work with it, so code can read and
+/// modify data without knowing whether it is NBT or JSON
 #[derive(Clone)]
 pub struct Dynamic<T: Clone> {
-    ops: Arc<dyn DynamicOps>,
+    ops: Arc<dyn DynamicOps<T>>,
     pub value: T,
 }
 impl<T: Clone> Dynamic<T> {
-    pub fn new(ops: Arc<dyn DynamicOps>, value: T) -> Self {
+    pub fn new(ops: Arc<dyn DynamicOps<T>>, value: T) -> Self {
         Self { ops, value }
     }
 
-    pub fn update(self, key: &str, update_fn: impl FnOnce(T) -> T) -> Self {
-        todo!();
+    pub fn ops(&self) -> &Arc<dyn DynamicOps<T>> {
+        &self.ops
+    }
+
+    pub fn into_value(self) -> T {
+        self.value
+    }
+
+    /// Wraps another value of the same format
+    pub fn create(&self, value: T) -> Self {
+        Self::new(self.ops.clone(), value)
+    }
+
+    pub fn empty_map(&self) -> Self {
+        self.create(self.ops.empty_map())
+    }
+
+    pub fn empty_list(&self) -> Self {
+        self.create(self.ops.empty_list())
+    }
+
+    pub fn create_byte(&self, value: i8) -> Self {
+        self.create(self.ops.create_byte(value))
+    }
+
+    pub fn create_int(&self, value: i32) -> Self {
+        self.create(self.ops.create_int(value))
+    }
+
+    pub fn create_long(&self, value: i64) -> Self {
+        self.create(self.ops.create_long(value))
+    }
+
+    pub fn create_boolean(&self, value: bool) -> Self {
+        self.create(self.ops.create_boolean(value))
+    }
+
+    pub fn create_string(&self, value: &str) -> Self {
+        self.create(self.ops.create_string(value))
+    }
+
+    pub fn create_int_list(&self, values: Vec<i32>) -> Self {
+        self.create(self.ops.create_int_list(values))
+    }
+
+    pub fn create_long_list(&self, values: Vec<i64>) -> Self {
+        self.create(self.ops.create_long_list(values))
+    }
+
+    pub fn create_list(&self, values: impl IntoIterator<Item = Dynamic<T>>) -> Self {
+        self.create(
+            self.ops
+                .create_list(values.into_iter().map(Dynamic::into_value).collect()),
+        )
+    }
+
+    pub fn get(&self, key: &str) -> OptionalDynamic<T> {
+        OptionalDynamic {
+            ops: self.ops.clone(),
+            delegate: self.ops.get(&self.value, key).map_err(Arc::new),
+        }
+    }
+
+    pub fn set(self, key: &str, value: Dynamic<T>) -> Self {
+        let value = self.ops.set(self.value, key, value.value);
+        Self::new(self.ops, value)
+    }
+
+    pub fn remove(self, key: &str) -> Self {
+        let value = self.ops.remove(self.value, key);
+        Self::new(self.ops, value)
+    }
+
+    /// Replaces a map entry with the result of `update_fn`, if the entry exists
+    pub fn update(self, key: &str, update_fn: impl FnOnce(Dynamic<T>) -> Dynamic<T>) -> Self {
+        match self.get(key).result() {
+            Ok(value) => self.set(key, update_fn(value)),
+            Err(_) => self,
+        }
+    }
+
+    /// Moves a map entry to a new key, if it exists
+    pub fn rename_field(self, old_key: &str, new_key: &str) -> Self {
+        match self.get(old_key).result() {
+            Ok(value) => self.remove(old_key).set(new_key, value),
+            Err(_) => self,
+        }
+    }
+
+    pub fn as_number(&self) -> Result<Number> {
+        self.ops.get_number_value(&self.value)
+    }
+
+    pub fn as_int(&self, default: i32) -> i32 {
+        self.as_number().map_or(default, |e| e.as_i32())
+    }
+
+    pub fn as_long(&self, default: i64) -> i64 {
+        self.as_number().map_or(default, |e| e.as_i64())
+    }
+
+    pub fn as_double(&self, default: f64) -> f64 {
+        self.as_number().map_or(default, |e| e.as_f64())
+    }
+
+    pub fn as_bool(&self, default: bool) -> bool {
+        self.ops.get_boolean_value(&self.value).unwrap_or(default)
+    }
+
+    pub fn as_string(&self) -> Result<String> {
+        self.ops.get_string_value(&self.value)
+    }
+
+    pub fn as_string_or(&self, default: &str) -> String {
+        self.as_string().unwrap_or_else(|_| default.to_string())
+    }
+
+    pub fn as_list(&self) -> Result<Vec<Dynamic<T>>> {
+        Ok(self
+            .ops
+            .get_list(&self.value)?
+            .into_iter()
+            .map(|e| self.create(e))
+            .collect())
+    }
+
+    pub fn as_byte_list(&self) -> Result<Vec<i8>> {
+        self.ops.get_byte_list(&self.value)
+    }
+
+    pub fn as_int_list(&self) -> Result<Vec<i32>> {
+        self.ops.get_int_list(&self.value)
+    }
+
+    pub fn as_long_list(&self) -> Result<Vec<i64>> {
+        self.ops.get_long_list(&self.value)
+    }
+
+    pub fn as_map(&self) -> Result<Vec<(String, Dynamic<T>)>> {
+        Ok(self
+            .ops
+            .get_map_entries(&self.value)?
+            .into_iter()
+            .map(|(key, value)| (key, self.create(value)))
+            .collect())
+    }
+
+    /// Rebuilds the value in another format
+    pub fn convert<U: Clone>(&self, out: Arc<dyn DynamicOps<U>>) -> Dynamic<U> {
+        let value = self.ops.convert_to(out.as_ref(), &self.value);
+        Dynamic::new(out, value)
+    }
+}
+
+/// A map entry that may be missing, from [Dynamic::get]. Getters fall back to their defaults if
+/// the entry is missing or of the wrong type, so nested fields can be read without checks.
+#[derive(Clone)]
+pub struct OptionalDynamic<T: Clone> {
+    ops: Arc<dyn DynamicOps<T>>,
+    delegate: Result<T, Arc<anyhow::Error>>,
+}
+impl<T: Clone> OptionalDynamic<T> {
+    pub fn result(&self) -> Result<Dynamic<T>> {
+        match &self.delegate {
+            Ok(value) => Ok(Dynamic::new(self.ops.clone(), value.clone())),
+            Err(e) => Err(anyhow::anyhow!("{}", e)),
+        }
+    }
+
+    pub fn is_present(&self) -> bool {
+        self.delegate.is_ok()
+    }
+
+    pub fn get(&self, key: &str) -> OptionalDynamic<T> {
+        OptionalDynamic {
+            ops: self.ops.clone(),
+            delegate: match &self.delegate {
+                Ok(value) => self.ops.get(value, key).map_err(Arc::new),
+                Err(e) => Err(e.clone()),
+            },
+        }
+    }
+
+    /// The entry, or an empty map so it can still be read from and written to
+    pub fn or_empty_map(&self) -> Dynamic<T> {
+        self.result()
+            .unwrap_or_else(|_| Dynamic::new(self.ops.clone(), self.ops.empty_map()))
+    }
+
+    pub fn as_number(&self) -> Result<Number> {
+        self.result()?.as_number()
+    }
+
+    pub fn as_int(&self, default: i32) -> i32 {
+        self.result().map_or(default, |e| e.as_int(default))
+    }
+
+    pub fn as_long(&self, default: i64) -> i64 {
+        self.result().map_or(default, |e| e.as_long(default))
+    }
+
+    pub fn as_double(&self, default: f64) -> f64 {
+        self.result().map_or(default, |e| e.as_double(default))
+    }
+
+    pub fn as_bool(&self, default: bool) -> bool {
+        self.result().map_or(default, |e| e.as_bool(default))
+    }
+
+    pub fn as_string(&self) -> Result<String> {
+        self.result()?.as_string()
+    }
+
+    pub fn as_string_or(&self, default: &str) -> String {
+        self.as_string().unwrap_or_else(|_| default.to_string())
+    }
+
+    pub fn as_list(&self) -> Result<Vec<Dynamic<T>>> {
+        self.result()?.as_list()
+    }
+
+    pub fn as_int_list(&self) -> Result<Vec<i32>> {
+        self.result()?.as_int_list()
+    }
+
+    pub fn as_map(&self) -> Result<Vec<(String, Dynamic<T>)>> {
+        self.result()?.as_map()
     }
 }
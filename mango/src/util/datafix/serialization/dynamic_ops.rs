@@ -1 +1,227 @@
-pub trait DynamicOps {}
+use anyhow::Result;
+
+/// Numeric value of any width, like Java's `Number`. Conversions between widths truncate or wrap
+/// like Java's primitive casts.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Number {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+}
+impl Number {
+    pub fn as_i8(&self) -> i8 {
+        self.as_i64() as i8
+    }
+
+    pub fn as_i16(&self) -> i16 {
+        self.as_i64() as i16
+    }
+
+    pub fn as_i32(&self) -> i32 {
+        match self {
+            Number::Float(e) => *e as i32,
+            Number::Double(e) => *e as i32,
+            _ => self.as_i64() as i32,
+        }
+    }
+
+    pub fn as_i64(&self) -> i64 {
+        match self {
+            Number::Byte(e) => *e as i64,
+            Number::Short(e) => *e as i64,
+            Number::Int(e) => *e as i64,
+            Number::Long(e) => *e,
+            Number::Float(e) => *e as i64,
+            Number::Double(e) => *e as i64,
+        }
+    }
+
+    pub fn as_f32(&self) -> f32 {
+        match self {
+            Number::Float(e) => *e,
+            _ => self.as_f64() as f32,
+        }
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Number::Float(e) => *e as f64,
+            Number::Double(e) => *e,
+            _ => self.as_i64() as f64,
+        }
+    }
+}
+
+/// What kind of value an ops' representation holds, so it can be rebuilt by other ops
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ValueKind {
+    Empty,
+    Boolean,
+    Number,
+    String,
+    List,
+    ByteList,
+    IntList,
+    LongList,
+    Map,
+}
+
+/// Creates, reads and modifies values of a serialized format `T` without knowing the format, so
+/// data fixers and codecs can work over both NBT and JSON. Based on DataFixerUpper's DynamicOps.
+///
+/// Values are immutable from the point of view of the ops: modifications take a value and return
+/// the modified one.
+pub trait DynamicOps<T: Clone>: Send + Sync {
+    fn empty(&self) -> T;
+
+    fn empty_map(&self) -> T {
+        self.create_map(Vec::new())
+    }
+
+    fn empty_list(&self) -> T {
+        self.create_list(Vec::new())
+    }
+
+    fn kind(&self, input: &T) -> ValueKind;
+
+    fn create_number(&self, value: Number) -> T;
+
+    fn create_byte(&self, value: i8) -> T {
+        self.create_number(Number::Byte(value))
+    }
+
+    fn create_short(&self, value: i16) -> T {
+        self.create_number(Number::Short(value))
+    }
+
+    fn create_int(&self, value: i32) -> T {
+        self.create_number(Number::Int(value))
+    }
+
+    fn create_long(&self, value: i64) -> T {
+        self.create_number(Number::Long(value))
+    }
+
+    fn create_float(&self, value: f32) -> T {
+        self.create_number(Number::Float(value))
+    }
+
+    fn create_double(&self, value: f64) -> T {
+        self.create_number(Number::Double(value))
+    }
+
+    fn create_boolean(&self, value: bool) -> T {
+        self.create_byte(value as i8)
+    }
+
+    fn create_string(&self, value: &str) -> T;
+
+    fn create_list(&self, values: Vec<T>) -> T;
+
+    fn create_byte_list(&self, values: Vec<i8>) -> T {
+        self.create_list(values.into_iter().map(|e| self.create_byte(e)).collect())
+    }
+
+    fn create_int_list(&self, values: Vec<i32>) -> T {
+        self.create_list(values.into_iter().map(|e| self.create_int(e)).collect())
+    }
+
+    fn create_long_list(&self, values: Vec<i64>) -> T {
+        self.create_list(values.into_iter().map(|e| self.create_long(e)).collect())
+    }
+
+    fn create_map(&self, entries: Vec<(String, T)>) -> T;
+
+    fn get_number_value(&self, input: &T) -> Result<Number>;
+
+    fn get_boolean_value(&self, input: &T) -> Result<bool> {
+        Ok(self.get_number_value(input)?.as_i8() != 0)
+    }
+
+    fn get_string_value(&self, input: &T) -> Result<String>;
+
+    fn get_list(&self, input: &T) -> Result<Vec<T>>;
+
+    fn get_byte_list(&self, input: &T) -> Result<Vec<i8>> {
+        self.get_list(input)?
+            .iter()
+            .map(|e| self.get_number_value(e).map(|n| n.as_i8()))
+            .collect()
+    }
+
+    fn get_int_list(&self, input: &T) -> Result<Vec<i32>> {
+        self.get_list(input)?
+            .iter()
+            .map(|e| self.get_number_value(e).map(|n| n.as_i32()))
+            .collect()
+    }
+
+    fn get_long_list(&self, input: &T) -> Result<Vec<i64>> {
+        self.get_list(input)?
+            .iter()
+            .map(|e| self.get_number_value(e).map(|n| n.as_i64()))
+            .collect()
+    }
+
+    fn get_map_entries(&self, input: &T) -> Result<Vec<(String, T)>>;
+
+    /// Value of a map entry
+    fn get(&self, input: &T, key: &str) -> Result<T> {
+        self.get_map_entries(input)?
+            .into_iter()
+            .find_map(|(k, v)| (k == key).then_some(v))
+            .ok_or_else(|| anyhow::anyhow!("No key {} in map", key))
+    }
+
+    /// Appends a value to a list. The empty value counts as an empty list.
+    fn merge_to_list(&self, list: T, value: T) -> Result<T>;
+
+    /// Puts an entry into a map. The empty value counts as an empty map.
+    fn merge_to_map(&self, map: T, key: &str, value: T) -> Result<T>;
+
+    fn merge_entries_to_map(&self, map: T, entries: Vec<(String, T)>) -> Result<T> {
+        entries
+            .into_iter()
+            .try_fold(map, |map, (key, value)| self.merge_to_map(map, &key, value))
+    }
+
+    /// Removes a map entry, returning the input unchanged if it is not a map
+    fn remove(&self, input: T, key: &str) -> T;
+
+    /// Puts a map entry, returning the input unchanged if it is not a map
+    fn set(&self, input: T, key: &str, value: T) -> T {
+        self.merge_to_map(input.clone(), key, value)
+            .unwrap_or(input)
+    }
+}
+impl<T: Clone> dyn DynamicOps<T> + '_ {
+    /// Rebuilds a value in another format. Formats that can't represent a kind of value fall back
+    /// to the closest one, e.g. NBT stores booleans as bytes.
+    pub fn convert_to<U: Clone>(&self, out: &dyn DynamicOps<U>, input: &T) -> U {
+        let converted = match self.kind(input) {
+            ValueKind::Empty => Ok(out.empty()),
+            ValueKind::Boolean => self.get_boolean_value(input).map(|e| out.create_boolean(e)),
+            ValueKind::Number => self.get_number_value(input).map(|e| out.create_number(e)),
+            ValueKind::String => self.get_string_value(input).map(|e| out.create_string(&e)),
+            ValueKind::List => self.get_list(input).map(|list| {
+                out.create_list(list.iter().map(|e| self.convert_to(out, e)).collect())
+            }),
+            ValueKind::ByteList => self.get_byte_list(input).map(|e| out.create_byte_list(e)),
+            ValueKind::IntList => self.get_int_list(input).map(|e| out.create_int_list(e)),
+            ValueKind::LongList => self.get_long_list(input).map(|e| out.create_long_list(e)),
+            ValueKind::Map => self.get_map_entries(input).map(|entries| {
+                out.create_map(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| (key, self.convert_to(out, &value)))
+                        .collect(),
+                )
+            }),
+        };
+        // The kind always matches the getter, so this can only fail on a broken ops
+        converted.unwrap_or_else(|_| out.empty())
+    }
+}
@@ -0,0 +1,137 @@
+use crate::util::datafix::serialization::dynamic_ops::{DynamicOps, Number, ValueKind};
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+use std::sync::{Arc, LazyLock};
+
+pub static INSTANCE: LazyLock<Arc<dyn DynamicOps<Value>>> = LazyLock::new(|| Arc::new(JsonOps {}));
+
+/// [DynamicOps] over JSON, following DataFixerUpper's JsonOps. Null stands in for an empty value.
+///
+/// JSON has a single number type, so integers read back as ints or longs depending on their size
+/// and everything else as doubles.
+pub struct JsonOps {}
+impl DynamicOps<Value> for JsonOps {
+    fn empty(&self) -> Value {
+        Value::Null
+    }
+
+    fn kind(&self, input: &Value) -> ValueKind {
+        match input {
+            Value::Null => ValueKind::Empty,
+            Value::Bool(_) => ValueKind::Boolean,
+            Value::Number(_) => ValueKind::Number,
+            Value::String(_) => ValueKind::String,
+            Value::Array(_) => ValueKind::List,
+            Value::Object(_) => ValueKind::Map,
+        }
+    }
+
+    fn create_number(&self, value: Number) -> Value {
+        match value {
+            Number::Float(e) => Value::from(e),
+            Number::Double(e) => Value::from(e),
+            _ => Value::from(value.as_i64()),
+        }
+    }
+
+    fn create_boolean(&self, value: bool) -> Value {
+        Value::Bool(value)
+    }
+
+    fn create_string(&self, value: &str) -> Value {
+        Value::String(value.to_string())
+    }
+
+    fn create_list(&self, values: Vec<Value>) -> Value {
+        Value::Array(values)
+    }
+
+    fn create_map(&self, entries: Vec<(String, Value)>) -> Value {
+        Value::Object(entries.into_iter().collect())
+    }
+
+    fn get_number_value(&self, input: &Value) -> Result<Number> {
+        match input {
+            Value::Number(n) => {
+                if let Some(n) = n.as_i64() {
+                    Ok(i32::try_from(n).map_or(Number::Long(n), Number::Int))
+                } else {
+                    n.as_f64()
+                        .map(Number::Double)
+                        .ok_or_else(|| anyhow!("Number out of range: {}", n))
+                }
+            }
+            Value::Bool(e) => Ok(Number::Byte(*e as i8)),
+            _ => Err(anyhow!("Not a number: {}", input)),
+        }
+    }
+
+    fn get_boolean_value(&self, input: &Value) -> Result<bool> {
+        match input {
+            Value::Bool(e) => Ok(*e),
+            _ => Ok(self.get_number_value(input)?.as_i8() != 0),
+        }
+    }
+
+    fn get_string_value(&self, input: &Value) -> Result<String> {
+        match input {
+            Value::String(e) => Ok(e.clone()),
+            _ => Err(anyhow!("Not a string: {}", input)),
+        }
+    }
+
+    fn get_list(&self, input: &Value) -> Result<Vec<Value>> {
+        match input {
+            Value::Array(e) => Ok(e.clone()),
+            _ => Err(anyhow!("Not a list: {}", input)),
+        }
+    }
+
+    fn get_map_entries(&self, input: &Value) -> Result<Vec<(String, Value)>> {
+        match input {
+            Value::Object(e) => Ok(e.iter().map(|(k, v)| (k.clone(), v.clone())).collect()),
+            _ => Err(anyhow!("Not a map: {}", input)),
+        }
+    }
+
+    fn get(&self, input: &Value, key: &str) -> Result<Value> {
+        match input {
+            Value::Object(e) => e
+                .get(key)
+                .cloned()
+                .ok_or_else(|| anyhow!("No key {} in map", key)),
+            _ => Err(anyhow!("Not a map: {}", input)),
+        }
+    }
+
+    fn merge_to_list(&self, list: Value, value: Value) -> Result<Value> {
+        match list {
+            Value::Null => Ok(Value::Array(vec![value])),
+            Value::Array(mut e) => {
+                e.push(value);
+                Ok(Value::Array(e))
+            }
+            list => Err(anyhow!("Not a list: {}", list)),
+        }
+    }
+
+    fn merge_to_map(&self, map: Value, key: &str, value: Value) -> Result<Value> {
+        let mut map = match map {
+            Value::Null => Map::new(),
+            Value::Object(e) => e,
+            map => return Err(anyhow!("Not a map: {}", map)),
+        };
+        map.insert(key.to_string(), value);
+        Ok(Value::Object(map))
+    }
+
+    fn remove(&self, input: Value, key: &str) -> Value {
+        match input {
+            Value::Object(mut e) => {
+                e.remove(key);
+                Value::Object(e)
+            }
+            input => input,
+        }
+    }
+}
@@ -1,2 +1,3 @@
 pub mod dynamic;
 pub mod dynamic_ops;
+pub mod json_ops;
@@ -1,42 +1,187 @@
 use bytes::Bytes;
 use fs4::fs_std::FileExt;
-use std::fs::File;
+use std::fs::{File, OpenOptions};
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::time::Instant;
+use tracing::error;
+
+pub const LOCK_FILE: &str = "session.lock";
 
 static DUMMY: Bytes = Bytes::from_static(b"\xE2\x98\x83");
+/// How often a held lock is retried when waiting for it
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Error)]
+pub enum DirectoryLockError {
+    #[error("The file {0:?} is already locked (possibly by other Minecraft instance?)")]
+    AlreadyLocked(PathBuf),
+    #[error("Timed out after {timeout:?} waiting for the file {path:?} to be unlocked (possibly by other Minecraft instance?)")]
+    TimedOut { path: PathBuf, timeout: Duration },
+    #[error("Failed to lock the file {path:?}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+}
 
+/// Exclusive lock on a directory, held through its session.lock file until dropped, so only one
+/// instance uses a world at a time
 pub struct DirectoryLock {
     lock_file: File,
+    path: PathBuf,
 }
 impl DirectoryLock {
-    pub fn create(path: PathBuf) -> Self {
-        let session_lock = path.join("session.lock");
-        std::fs::create_dir_all(&path).expect("Failed to create session lock path");
-
-        match File::create(&session_lock) {
-            Ok(mut lock_file) => match lock_file.try_lock_exclusive() {
-                Ok(()) => {
-                    lock_file
-                        .write_all(&DUMMY)
-                        .expect("Failed to write to session lock file");
-                    Self { lock_file }
-                }
-                Err(e) => {
-                    panic!("Session file at {:?} already locked (possibly by other Minecraft instance?): {:?}", session_lock, e);
+    /// Locks a directory, creating it if needed, and fails right away if it is already locked
+    pub fn create(path: PathBuf) -> Result<Self, DirectoryLockError> {
+        let session_lock = path.join(LOCK_FILE);
+        let io_error = |source| DirectoryLockError::Io {
+            path: session_lock.clone(),
+            source,
+        };
+        std::fs::create_dir_all(&path).map_err(io_error)?;
+        let mut lock_file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&session_lock)
+            .map_err(io_error)?;
+        match lock_file.try_lock_exclusive() {
+            Ok(()) => {}
+            Err(e) if is_contended(&e) => {
+                return Err(DirectoryLockError::AlreadyLocked(session_lock));
+            }
+            Err(e) => return Err(io_error(e)),
+        }
+        lock_file.write_all(&DUMMY).map_err(io_error)?;
+        Ok(Self {
+            lock_file,
+            path: session_lock,
+        })
+    }
+
+    /// Locks a directory, waiting up to `timeout` for another instance to release it
+    pub async fn create_with_timeout(
+        path: PathBuf,
+        timeout: Duration,
+    ) -> Result<Self, DirectoryLockError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match Self::create(path.clone()) {
+                Err(DirectoryLockError::AlreadyLocked(session_lock)) => {
+                    if Instant::now() >= deadline {
+                        return Err(DirectoryLockError::TimedOut {
+                            path: session_lock,
+                            timeout,
+                        });
+                    }
+                    tokio::time::sleep(RETRY_INTERVAL.min(deadline - Instant::now())).await;
                 }
-            },
-            Err(e) => panic!(
-                "Failed to create session lock file at {:?}: {:?}",
-                session_lock, e
-            ),
+                res => return res,
+            }
+        }
+    }
+
+    /// Whether the lock still guards the directory: session.lock must still be the file that was
+    /// locked, and not have been removed or replaced since
+    pub fn is_valid(&self) -> bool {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let (Ok(locked), Ok(current)) =
+                (self.lock_file.metadata(), std::fs::metadata(&self.path))
+            else {
+                return false;
+            };
+            locked.dev() == current.dev() && locked.ino() == current.ino()
+        }
+        #[cfg(not(unix))]
+        {
+            // File ids aren't exposed on stable Rust, but locks are held per handle here, so the
+            // file at the path can only be locked through a new handle if it isn't the locked one
+            match File::open(&self.path) {
+                Ok(current) => match current.try_lock_exclusive() {
+                    Ok(()) => {
+                        let _ = current.unlock();
+                        false
+                    }
+                    Err(e) => is_contended(&e),
+                },
+                Err(_) => false,
+            }
+        }
+    }
+
+    /// Whether a directory is locked by a running instance, without taking the lock
+    pub fn is_locked(path: &Path) -> std::io::Result<bool> {
+        let lock_file = match File::open(path.join(LOCK_FILE)) {
+            Ok(lock_file) => lock_file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        match lock_file.try_lock_exclusive() {
+            Ok(()) => {
+                lock_file.unlock()?;
+                Ok(false)
+            }
+            Err(e) if is_contended(&e) => Ok(true),
+            Err(e) => Err(e),
         }
     }
 }
 impl Drop for DirectoryLock {
     fn drop(&mut self) {
-        self.lock_file
-            .unlock()
-            .expect("Failed to unlock session lock file");
+        if let Err(e) = self.lock_file.unlock() {
+            error!(?e, "Failed to unlock {:?}", self.path);
+        }
+    }
+}
+
+fn is_contended(e: &std::io::Error) -> bool {
+    e.raw_os_error() == fs4::lock_contended_error().raw_os_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn lock_is_exclusive_until_dropped() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().to_path_buf();
+        let lock = DirectoryLock::create(dir.clone()).unwrap();
+        assert!(lock.is_valid());
+        assert!(DirectoryLock::is_locked(&dir).unwrap());
+        assert!(matches!(
+            DirectoryLock::create(dir.clone()),
+            Err(DirectoryLockError::AlreadyLocked(_))
+        ));
+        assert!(matches!(
+            DirectoryLock::create_with_timeout(dir.clone(), Duration::from_millis(250)).await,
+            Err(DirectoryLockError::TimedOut { .. })
+        ));
+
+        drop(lock);
+        assert!(!DirectoryLock::is_locked(&dir).unwrap());
+        let lock = DirectoryLock::create_with_timeout(dir.clone(), Duration::from_millis(250))
+            .await
+            .unwrap();
+        assert!(lock.is_valid());
+    }
+
+    // Windows keeps a removed file's name taken until every handle to it is closed
+    #[cfg(unix)]
+    #[test]
+    fn lock_is_invalid_once_the_lock_file_is_replaced() {
+        let temp_dir = TempDir::new().unwrap();
+        let dir = temp_dir.path().to_path_buf();
+        let lock = DirectoryLock::create(dir.clone()).unwrap();
+        std::fs::remove_file(dir.join(LOCK_FILE)).unwrap();
+        assert!(!lock.is_valid());
+        std::fs::write(dir.join(LOCK_FILE), "").unwrap();
+        assert!(!lock.is_valid());
     }
 }
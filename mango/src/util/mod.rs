@@ -1,11 +1,16 @@
 use crate::resources::resource_location::ResourceLocation;
+use anyhow::{anyhow, Result};
+use jiff::Zoned;
 use regex::Regex;
 use serde::{Deserialize, Deserializer};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
 
 pub mod datafix;
 pub mod directory_lock;
 pub mod mth;
 pub mod resource_location_pattern;
+pub mod worldupdate;
 
 pub fn make_description_id(id: &str, location: ResourceLocation) -> String {
     format!(
@@ -16,6 +21,72 @@ pub fn make_description_id(id: &str, location: ResourceLocation) -> String {
     )
 }
 
+/// Moves `source` to `target`, first moving any existing `target` to `backup`. If `source` can't be
+/// moved, the backup is moved back to `target`.
+pub fn safe_replace_or_move_file(target: &Path, source: &Path, backup: &Path) -> Result<()> {
+    if !source.exists() {
+        return Err(anyhow!("Can't replace {:?} as {:?} doesn't exist", target, source));
+    }
+    let backed_up = target.exists();
+    if backed_up {
+        if backup.exists() {
+            std::fs::remove_file(backup)?;
+        }
+        std::fs::rename(target, backup)?;
+    }
+    if let Err(e) = std::fs::rename(source, target) {
+        if backed_up {
+            std::fs::rename(backup, target)?;
+        }
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// A unique `<file name>-<nanos>.tmp` next to a file, which its new contents are written to before
+/// they replace it
+pub fn temp_file_for(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!("-{}.tmp", Zoned::now().timestamp().as_nanosecond()));
+    path.with_file_name(file_name)
+}
+
+/// Swaps in a file's new contents from the temporary file they were `written` to, keeping the
+/// previous file as `backup` if given. The temporary file is removed if writing it failed.
+pub fn replace_with_temp_file(
+    target: &Path,
+    temp_file: &Path,
+    backup: Option<&Path>,
+    written: Result<()>,
+) -> Result<()> {
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(temp_file);
+        return Err(e);
+    }
+    match backup {
+        Some(backup) => safe_replace_or_move_file(target, temp_file, backup),
+        None => Ok(std::fs::rename(temp_file, target)?),
+    }
+}
+
+/// Writes a file through a synced temporary file next to it, which then replaces the file, so the
+/// file is never left partially written
+pub async fn write_file_atomically(
+    path: &Path,
+    contents: impl AsRef<[u8]>,
+    backup: Option<&Path>,
+) -> Result<()> {
+    let temp_file = temp_file_for(path);
+    let written = async {
+        let mut file = tokio::fs::File::create(&temp_file).await?;
+        file.write_all(contents.as_ref()).await?;
+        file.sync_all().await?;
+        Ok(())
+    }
+    .await;
+    replace_with_temp_file(path, &temp_file, backup, written)
+}
+
 fn deserialize_regex<'de, D>(deserializer: D) -> Result<Regex, D::Error>
 where
     D: Deserializer<'de>,
@@ -0,0 +1,455 @@
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::nbt_accounter::NbtAccounter;
+use crate::nbt::nbt_utils::NO_DATA_VERSION;
+use crate::nbt::tag::Tag;
+use crate::nbt::{nbt_io, nbt_ops, nbt_utils};
+use crate::resources::resource_location::ResourceLocation;
+use crate::shared_constants;
+use crate::util;
+use crate::util::datafix::data_fix_types::DataFixTypes;
+use crate::util::datafix::data_fixers::DataFixer;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+use crate::util::datafix::serialization::json_ops;
+use crate::world::level::chunk_pos::ChunkPos;
+use crate::world::level::storage::level_resource::LevelResource;
+use crate::world::level::storage::level_storage_source::LevelStorageAccess;
+use crate::world::level::storage::region_file_storage::RegionFileStorage;
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use tracing::{error, info, warn};
+
+static REGION_FILE_NAME: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^r\.(-?[0-9]+)\.(-?[0-9]+)\.mca$").unwrap());
+
+/// How a file is stored and which data fixes apply to it
+#[derive(Clone, Debug)]
+enum FileKind {
+    /// Gzip compressed NBT, defaulting to `default_version` when it has no data version
+    Nbt {
+        fix_type: DataFixTypes,
+        default_version: i32,
+    },
+    Json(DataFixTypes),
+    /// Region file of chunks of a dimension
+    Region {
+        dimension: ResourceLocation,
+        fix_type: DataFixTypes,
+    },
+}
+
+struct UpgradeFile {
+    path: PathBuf,
+    kind: FileKind,
+}
+
+/// Outcome of an upgrade, by file
+#[derive(Copy, Clone, Debug, Default)]
+pub struct UpgradeStatus {
+    pub total_files: usize,
+    pub converted: usize,
+    /// Files that were already at the current version or failed to upgrade
+    pub skipped: usize,
+    pub failed: usize,
+}
+impl UpgradeStatus {
+    pub fn progress(&self) -> f32 {
+        if self.total_files == 0 {
+            1.0
+        } else {
+            (self.converted + self.skipped) as f32 / self.total_files as f32
+        }
+    }
+}
+
+/// Upgrades a whole world to the current data version ahead of time, rewriting every file that
+/// the data fixer applies to, like vanilla's `--forceUpgrade`. level.dat is upgraded whenever it
+/// is loaded, so it isn't touched here.
+pub struct WorldUpgrader {
+    dimensions: Vec<(ResourceLocation, PathBuf)>,
+    /// Chunk generator type of each dimension in the world's generation settings
+    generators: HashMap<String, String>,
+    player_data_dir: PathBuf,
+    stats_dir: PathBuf,
+    advancements_dir: PathBuf,
+    data_fixer: Arc<DataFixer>,
+    erase_cache: bool,
+    status: UpgradeStatus,
+}
+impl WorldUpgrader {
+    /// `level_data` is the world's fixed level.dat, used for the generators of its dimensions
+    pub fn new(
+        level_storage_access: &mut LevelStorageAccess,
+        level_data: Option<&Dynamic<Tag>>,
+        data_fixer: Arc<DataFixer>,
+        erase_cache: bool,
+    ) -> Self {
+        let dimensions = level_storage_access
+            .list_dimensions()
+            .into_iter()
+            .map(|dimension| {
+                let path = level_storage_access.get_dimension_path(&dimension);
+                (dimension, path)
+            })
+            .collect();
+        let generators = level_data
+            .and_then(|e| e.get("WorldGenSettings").get("dimensions").as_map().ok())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(dimension, settings)| {
+                let generator = settings.get("generator").get("type").as_string().ok()?;
+                Some((dimension, generator))
+            })
+            .collect();
+        Self {
+            dimensions,
+            generators,
+            player_data_dir: level_storage_access.get_level_path(LevelResource::PlayerDataDir),
+            stats_dir: level_storage_access.get_level_path(LevelResource::PlayerStatsDir),
+            advancements_dir: level_storage_access
+                .get_level_path(LevelResource::PlayerAdvancementsDir),
+            data_fixer,
+            erase_cache,
+            status: UpgradeStatus::default(),
+        }
+    }
+
+    pub async fn work(mut self) -> Result<UpgradeStatus> {
+        let files = self.collect_files()?;
+        self.status.total_files = files.len();
+        info!("Upgrading {} files to the current version", files.len());
+
+        let mut last_percent = 0;
+        for file in files {
+            match self.upgrade_file(&file).await {
+                Ok(true) => self.status.converted += 1,
+                Ok(false) => self.status.skipped += 1,
+                Err(e) => {
+                    error!(?e, "Failed to upgrade {:?}", file.path);
+                    self.status.skipped += 1;
+                    self.status.failed += 1;
+                }
+            }
+            let percent = (self.status.progress() * 100.0) as u32;
+            if percent / 10 > last_percent / 10 {
+                info!(
+                    "{}% completed ({} / {} files)",
+                    percent,
+                    self.status.converted + self.status.skipped,
+                    self.status.total_files
+                );
+            }
+            last_percent = percent;
+        }
+
+        info!(
+            "World upgrade finished: {} converted, {} skipped, {} failed",
+            self.status.converted, self.status.skipped, self.status.failed
+        );
+        Ok(self.status)
+    }
+
+    fn collect_files(&self) -> Result<Vec<UpgradeFile>> {
+        let mut files = Vec::new();
+        let player = FileKind::Nbt {
+            fix_type: DataFixTypes::Player,
+            default_version: -1,
+        };
+        files.extend(
+            list_files(&self.player_data_dir, "dat")?
+                .into_iter()
+                .map(|path| UpgradeFile {
+                    path,
+                    kind: player.clone(),
+                }),
+        );
+        for (dir, fix_type) in [
+            (&self.stats_dir, DataFixTypes::Stats),
+            (&self.advancements_dir, DataFixTypes::Advancements),
+        ] {
+            files.extend(
+                list_files(dir, "json")?
+                    .into_iter()
+                    .map(|path| UpgradeFile {
+                        path,
+                        kind: FileKind::Json(fix_type),
+                    }),
+            );
+        }
+
+        for (dimension, path) in &self.dimensions {
+            for path in list_files(&path.join("data"), "dat")? {
+                let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                match get_saved_data_type(&name) {
+                    Some(fix_type) => files.push(UpgradeFile {
+                        kind: FileKind::Nbt {
+                            fix_type,
+                            default_version: NO_DATA_VERSION,
+                        },
+                        path,
+                    }),
+                    None => warn!("Skipping unknown saved data {:?} in {}", path, dimension),
+                }
+            }
+            for (dir, fix_type) in [
+                ("region", DataFixTypes::Chunk),
+                ("entities", DataFixTypes::EntityChunk),
+                ("poi", DataFixTypes::PoiChunk),
+            ] {
+                files.extend(list_files(&path.join(dir), "mca")?.into_iter().map(|path| {
+                    UpgradeFile {
+                        path,
+                        kind: FileKind::Region {
+                            dimension: dimension.clone(),
+                            fix_type,
+                        },
+                    }
+                }));
+            }
+        }
+        Ok(files)
+    }
+
+    /// Rewrites a file at the current version, returning whether it needed upgrading
+    async fn upgrade_file(&self, file: &UpgradeFile) -> Result<bool> {
+        let current_version = shared_constants::get_current_data_version();
+        match &file.kind {
+            &FileKind::Nbt {
+                fix_type,
+                default_version,
+            } => {
+                let tag =
+                    nbt_io::read_compressed(file.path.clone(), NbtAccounter::unlimited_heap())
+                        .await?;
+                let version = nbt_utils::get_data_version(&tag, default_version);
+                if version >= current_version {
+                    return Ok(false);
+                }
+                let mut tag = fix_type
+                    .update_to_current_version(
+                        self.data_fixer.as_ref(),
+                        Dynamic::new(nbt_ops::INSTANCE.clone(), Tag::CompoundTag(tag)),
+                        version,
+                    )
+                    .into_value()
+                    .try_as_compound_tag()
+                    .ok_or_else(|| anyhow!("Fixed data is not a compound"))?;
+                nbt_utils::add_current_data_version(&mut tag);
+                nbt_io::write_compressed_atomically(&tag, &file.path, &old_file(&file.path))
+                    .await?;
+            }
+            &FileKind::Json(fix_type) => {
+                let value: Value =
+                    serde_json::from_str(&tokio::fs::read_to_string(&file.path).await?)?;
+                let dynamic = Dynamic::new(json_ops::INSTANCE.clone(), value);
+                let version = nbt_utils::get_dynamic_data_version(&dynamic, NO_DATA_VERSION);
+                if version >= current_version {
+                    return Ok(false);
+                }
+                let dynamic =
+                    fix_type.update_to_current_version(self.data_fixer.as_ref(), dynamic, version);
+                let dynamic = dynamic
+                    .clone()
+                    .set("DataVersion", dynamic.create_int(current_version));
+                util::write_file_atomically(
+                    &file.path,
+                    serde_json::to_string_pretty(&dynamic.value)?,
+                    Some(&old_file(&file.path)),
+                )
+                .await?;
+            }
+            FileKind::Region {
+                dimension,
+                fix_type,
+            } => return self.upgrade_region(&file.path, dimension, *fix_type).await,
+        }
+        Ok(true)
+    }
+
+    /// Rewrites each chunk of a region file that needs upgrading, returning whether any did.
+    /// Chunks that fail to upgrade are left as they were.
+    async fn upgrade_region(
+        &self,
+        path: &Path,
+        dimension: &ResourceLocation,
+        fix_type: DataFixTypes,
+    ) -> Result<bool> {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let captures = REGION_FILE_NAME
+            .captures(&file_name)
+            .ok_or_else(|| anyhow!("Invalid region file name {}", file_name))?;
+        let region_x: i32 = captures[1].parse()?;
+        let region_z: i32 = captures[2].parse()?;
+        let folder = path
+            .parent()
+            .ok_or_else(|| anyhow!("Region file {:?} has no parent", path))?;
+
+        let current_version = shared_constants::get_current_data_version();
+        let mut storage = RegionFileStorage::new(folder.to_path_buf(), true);
+        let mut converted = false;
+        for local_z in 0..32 {
+            for local_x in 0..32 {
+                let pos = ChunkPos::new(region_x * 32 + local_x, region_z * 32 + local_z);
+                if !storage.get_region_file(pos).await?.has_chunk(pos) {
+                    continue;
+                }
+                let res = async {
+                    let Some(chunk) = storage.read(pos).await? else {
+                        return Ok(false);
+                    };
+                    let version = nbt_utils::get_data_version(&chunk, -1);
+                    if version >= current_version && !self.erase_cache {
+                        return Ok(false);
+                    }
+                    let chunk = self.upgrade_chunk(dimension, fix_type, chunk)?;
+                    storage.write(pos, Some(&chunk)).await?;
+                    anyhow::Ok(true)
+                }
+                .await;
+                match res {
+                    Ok(res) => converted |= res,
+                    Err(e) => error!(?e, "Failed to upgrade chunk {} in {:?}", pos, path),
+                }
+            }
+        }
+        storage.close().await?;
+        Ok(converted)
+    }
+
+    /// Fixes a chunk from a region file of a dimension, erasing cached data if requested
+    pub fn upgrade_chunk(
+        &self,
+        dimension: &ResourceLocation,
+        fix_type: DataFixTypes,
+        chunk: CompoundTag,
+    ) -> Result<CompoundTag> {
+        let version = nbt_utils::get_data_version(&chunk, -1);
+        let chunk = Dynamic::new(nbt_ops::INSTANCE.clone(), Tag::CompoundTag(chunk));
+        // Chunk fixes need to know the dimension and its generator, which aren't stored in the
+        // chunk itself
+        let mut context = chunk
+            .empty_map()
+            .set("dimension", chunk.create_string(&dimension.to_string()));
+        if let Some(generator) = self.generators.get(&dimension.to_string()) {
+            context = context.set("generator", chunk.create_string(generator));
+        }
+        let chunk = chunk.set("__context", context);
+        let mut chunk = fix_type
+            .update_to_current_version(self.data_fixer.as_ref(), chunk, version)
+            .remove("__context");
+        if self.erase_cache {
+            chunk = erase_cache(chunk);
+        }
+        let mut chunk = chunk
+            .into_value()
+            .try_as_compound_tag()
+            .ok_or_else(|| anyhow!("Fixed chunk is not a compound"))?;
+        nbt_utils::add_current_data_version(&mut chunk);
+        Ok(chunk)
+    }
+}
+
+/// Where a file's previous contents are kept once it is upgraded. Files are replaced only once
+/// fully written, so they are never left partially upgraded even if the upgrade is stopped.
+fn old_file(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push("_old");
+    path.with_file_name(file_name)
+}
+
+/// Removes data that is recomputed when a chunk is loaded
+fn erase_cache<T: Clone>(chunk: Dynamic<T>) -> Dynamic<T> {
+    chunk
+        .remove("Heightmaps")
+        .remove("isLightOn")
+        .update("sections", |sections| match sections.as_list() {
+            Ok(list) => sections.create_list(
+                list.into_iter()
+                    .map(|section| section.remove("BlockLight").remove("SkyLight")),
+            ),
+            Err(_) => sections,
+        })
+}
+
+/// Saved data is fixed by the type its file name stands for
+fn get_saved_data_type(name: &str) -> Option<DataFixTypes> {
+    match name {
+        "chunks" => Some(DataFixTypes::SavedDataForcedChunks),
+        "idcounts" => Some(DataFixTypes::SavedDataMapIndex),
+        "raids" | "raids_end" => Some(DataFixTypes::SavedDataRaids),
+        "random_sequences" => Some(DataFixTypes::SavedDataRandomSequences),
+        "scoreboard" => Some(DataFixTypes::SavedDataScoreboard),
+        "Fortress" | "Mineshaft" | "Monument" | "Stronghold" | "Temple" | "Village" | "EndCity"
+        | "Mansion" => Some(DataFixTypes::SavedDataStructureFeatureIndices),
+        _ if name.starts_with("map_") => Some(DataFixTypes::SavedDataMapData),
+        _ if name.starts_with("command_storage_") => Some(DataFixTypes::SavedDataCommandStorage),
+        _ => None,
+    }
+}
+
+/// Files in a directory with an extension, or none if the directory doesn't exist
+fn list_files(dir: &Path, extension: &str) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|e| e == extension) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::datafix::data_fixers;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn upgraded_files_replace_the_originals() {
+        let temp_dir = TempDir::new().unwrap();
+        let level_path = temp_dir.path().join("world");
+        let mut access = LevelStorageAccess::new(
+            "world".to_string(),
+            level_path.clone(),
+            level_path.join("backups"),
+        )
+        .unwrap();
+        let stats_file = level_path.join("stats/00000000-0000-0000-0000-000000000000.json");
+        std::fs::create_dir_all(stats_file.parent().unwrap()).unwrap();
+        let old_stats =
+            r#"{"stats":{"minecraft:custom":{"minecraft:play_one_minute":10}},"DataVersion":2700}"#;
+        std::fs::write(&stats_file, old_stats).unwrap();
+
+        let status = WorldUpgrader::new(&mut access, None, data_fixers::get_data_fixer(), false)
+            .work()
+            .await
+            .unwrap();
+
+        assert_eq!(status.converted, 1);
+        let stats: Value =
+            serde_json::from_str(&std::fs::read_to_string(&stats_file).unwrap()).unwrap();
+        assert_eq!(
+            stats["stats"]["minecraft:custom"]["minecraft:play_time"],
+            10
+        );
+        assert_eq!(
+            stats["DataVersion"],
+            shared_constants::get_current_data_version()
+        );
+        assert_eq!(
+            std::fs::read_to_string(old_file(&stats_file)).unwrap(),
+            old_stats
+        );
+        // Only the upgraded file and its backup are left, no temporary files
+        let stats_dir = std::fs::read_dir(stats_file.parent().unwrap()).unwrap();
+        assert_eq!(stats_dir.count(), 2);
+    }
+}
@@ -1,7 +1,7 @@
 use serde::Serialize;
 use strum::FromRepr;
 
-#[derive(Default, FromRepr, PartialEq, Debug, Serialize)]
+#[derive(Copy, Clone, Default, FromRepr, PartialEq, Debug, Serialize)]
 #[repr(u8)]
 pub enum Difficulty {
     Peaceful,
@@ -0,0 +1,43 @@
+use std::fmt::{Display, Formatter};
+
+/// Position of a chunk, in chunk coordinates
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ChunkPos {
+    pub x: i32,
+    pub z: i32,
+}
+impl ChunkPos {
+    pub const fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// Packs a position into a long, as used for keys of chunk maps
+    pub const fn as_long(x: i32, z: i32) -> i64 {
+        (x as i64 & 0xFFFFFFFF) | ((z as i64 & 0xFFFFFFFF) << 32)
+    }
+
+    pub const fn to_long(self) -> i64 {
+        Self::as_long(self.x, self.z)
+    }
+
+    pub const fn get_region_x(&self) -> i32 {
+        self.x >> 5
+    }
+
+    pub const fn get_region_z(&self) -> i32 {
+        self.z >> 5
+    }
+
+    pub const fn get_region_local_x(&self) -> i32 {
+        self.x & 31
+    }
+
+    pub const fn get_region_local_z(&self) -> i32 {
+        self.z & 31
+    }
+}
+impl Display for ChunkPos {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{}, {}]", self.x, self.z)
+    }
+}
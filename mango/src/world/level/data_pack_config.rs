@@ -1,12 +1,14 @@
 use crate::codec::Codec;
 use crate::nbt::compound_tag::CompoundTag;
-use crate::nbt::tag::Tag;
+use crate::nbt::nbt_serde;
 use anyhow::Result;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct DataPackConfig {
+    #[serde(rename = "Enabled", default)]
     pub enabled: Vec<String>,
+    #[serde(rename = "Disabled", default)]
     pub disabled: Vec<String>,
 }
 impl DataPackConfig {
@@ -24,26 +26,6 @@ impl Default for DataPackConfig {
 }
 impl Codec<CompoundTag> for DataPackConfig {
     fn decode(data: CompoundTag) -> Result<Self> {
-        let enabled = data
-            .get_list("Enabled")
-            .iter()
-            .filter_map(|tag| match tag {
-                Tag::StringTag(s) => Some(s),
-                _ => None,
-            })
-            .cloned()
-            .collect::<Vec<String>>();
-
-        let disabled = data
-            .get_list("Disabled")
-            .iter()
-            .filter_map(|tag| match tag {
-                Tag::StringTag(s) => Some(s),
-                _ => None,
-            })
-            .cloned()
-            .collect();
-
-        Ok(Self { enabled, disabled })
+        nbt_serde::from_compound(data)
     }
 }
@@ -1,9 +1,11 @@
 use crate::core::Global;
 use crate::minecraft_server::MinecraftServer;
 use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::tag::Tag;
 use crate::world::flag::feature_flag_set::FeatureFlagSet;
 use crate::world::flag::feature_flags;
 use dashmap::DashMap;
+use itertools::Itertools;
 use num::cast::AsPrimitive;
 use serde::Serialize;
 use serde_with::SerializeDisplay;
@@ -469,6 +471,22 @@ impl GameRules {
         });
         res
     }
+
+    /// Game rules as saved in level.dat, with every value as a string
+    pub fn create_tag(&self) -> CompoundTag {
+        let mut tag = CompoundTag::default();
+        self.rules
+            .iter()
+            .sorted_by_key(|(key, _)| key.id)
+            .for_each(|(key, value)| {
+                let value = match value.value {
+                    GameRuleValueTypes::Integer(e) => e.to_string(),
+                    GameRuleValueTypes::Boolean(e) => e.to_string(),
+                };
+                tag.put(key.id, Tag::StringTag(value));
+            });
+        tag
+    }
 }
 impl From<FeatureFlagSet> for GameRules {
     fn from(value: FeatureFlagSet) -> Self {
@@ -1,4 +1,5 @@
 use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::tag::Tag;
 use crate::util::datafix::serialization::dynamic::Dynamic;
 use crate::world::difficulty::Difficulty;
 use crate::world::level::game_rules::GameRules;
@@ -37,32 +38,41 @@ impl LevelSettings {
         }
     }
 
-    pub fn parse(
-        dynamic: &Dynamic<CompoundTag>,
-        data_configuration: WorldDataConfiguration,
-    ) -> Self {
-        let game_type = GameType::by_id(
-            dynamic
-                .value
-                .get_int_or_default("GameType", GameType::Survival as i32),
-        );
+    pub fn level_name(&self) -> &str {
+        &self.level_name
+    }
+
+    /// Writes the settings into level data, as read by [LevelSettings::parse]
+    pub fn write_to(&self, tag: &mut CompoundTag) -> anyhow::Result<()> {
+        tag.put("LevelName", Tag::StringTag(self.level_name.clone()));
+        tag.put("GameType", Tag::IntTag(self.game_type as i32));
+        tag.put("hardcore", Tag::ByteTag(self.hardcore as u8));
+        tag.put("Difficulty", Tag::ByteTag(self.difficulty as u8));
+        tag.put("allowCommands", Tag::ByteTag(self.allow_commands as u8));
+        tag.put("GameRules", Tag::CompoundTag(self.game_rules.create_tag()));
+        self.data_configuration.write_to(tag)
+    }
+
+    pub fn parse(dynamic: &Dynamic<Tag>, data_configuration: WorldDataConfiguration) -> Self {
+        let game_type = GameType::by_id(dynamic.get("GameType").as_int(GameType::Survival as i32));
         Self {
-            level_name: dynamic
-                .value
-                .get_string_or_default("LevelName", "".to_string()),
-            hardcore: dynamic.value.get_bool_or_default("hardcore", false),
+            level_name: dynamic.get("LevelName").as_string_or(""),
+            hardcore: dynamic.get("hardcore").as_bool(false),
             difficulty: Difficulty::by_id(
-                dynamic
-                    .value
-                    .get_int_or_default("Difficulty", Difficulty::Normal as i32),
+                dynamic.get("Difficulty").as_int(Difficulty::Normal as i32),
             ),
             allow_commands: dynamic
-                .value
-                .get_bool_or_default("allowCommands", game_type == GameType::Creative),
+                .get("allowCommands")
+                .as_bool(game_type == GameType::Creative),
             game_type,
             game_rules: GameRules::new(
                 data_configuration.enabled_features.clone(),
-                dynamic.value.get_compound("GameRules"),
+                dynamic
+                    .get("GameRules")
+                    .result()
+                    .ok()
+                    .and_then(|e| e.value.try_as_compound_tag())
+                    .unwrap_or_default(),
             ),
             data_configuration,
         }
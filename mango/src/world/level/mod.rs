@@ -1,11 +1,13 @@
 pub mod block;
 pub mod block_getter;
+pub mod chunk_pos;
 pub mod data_pack_config;
 pub mod game_rules;
 mod game_type;
 pub mod level;
 mod level_settings;
 mod material;
+pub mod saveddata;
 pub mod storage;
 pub mod validation;
 pub mod world_data_configuration;
@@ -0,0 +1 @@
+pub mod saved_data;
@@ -0,0 +1,38 @@
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::nbt_utils;
+use crate::nbt::tag::Tag;
+use crate::util::datafix::data_fix_types::DataFixTypes;
+use anyhow::Result;
+use std::any::Any;
+
+/// Data a dimension keeps in `data/<id>.dat`, such as the scoreboard, command storage or forced
+/// chunks. It is held by a
+/// [DimensionDataStorage](crate::world::level::storage::dimension_data_storage::DimensionDataStorage),
+/// which writes it on autosave if it is dirty.
+pub trait SavedData: Any + Send + Sync {
+    /// Writes the data into `tag`, which is stored under `data` in the file
+    fn save(&self, tag: &mut CompoundTag) -> Result<()>;
+
+    fn is_dirty(&self) -> bool;
+
+    /// Marks the data as changed, so it is written on the next save
+    fn set_dirty(&mut self, dirty: bool);
+}
+
+/// The contents of a saved data file: the data under `data`, and the data version it was saved in
+pub fn save_to_file_tag(saved_data: &dyn SavedData) -> Result<CompoundTag> {
+    let mut data = CompoundTag::default();
+    saved_data.save(&mut data)?;
+    let mut tag = CompoundTag::default();
+    tag.put("data", Tag::CompoundTag(data));
+    nbt_utils::add_current_data_version(&mut tag);
+    Ok(tag)
+}
+
+/// How to create a type of saved data, read it from its `data` compound, and fix older versions of
+/// it
+pub struct SavedDataFactory<T: SavedData> {
+    pub constructor: fn() -> T,
+    pub deserializer: fn(&CompoundTag) -> Result<T>,
+    pub data_fix_type: DataFixTypes,
+}
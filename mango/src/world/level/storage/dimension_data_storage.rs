@@ -0,0 +1,304 @@
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::nbt_accounter::NbtAccounter;
+use crate::nbt::nbt_utils::NO_DATA_VERSION;
+use crate::nbt::tag::Tag;
+use crate::nbt::{nbt_io, nbt_ops, nbt_utils};
+use crate::util::datafix::data_fix_types::DataFixTypes;
+use crate::util::datafix::data_fixers::DataFixer;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+use crate::world::level::saveddata::saved_data;
+use crate::world::level::saveddata::saved_data::{SavedData, SavedDataFactory};
+use crate::world::level::storage::level_storage_source::BACKUP_TIMESTAMP_FORMAT;
+use anyhow::{anyhow, Result};
+use jiff::Zoned;
+use std::any::Any;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{error, warn};
+
+/// First bytes of a gzip stream. Saved data written by old versions may not be compressed.
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Saved data of a dimension, kept in its `data` folder as `<id>.dat`.
+///
+/// Each file is read the first time its data is asked for and cached from then on, including when
+/// it doesn't exist. Files that can't be read are moved aside to `<id>.dat_corrupted_<timestamp>`.
+/// [DimensionDataStorage::save] writes the data that is dirty, keeping the previous file as
+/// `<id>.dat_old`.
+pub struct DimensionDataStorage {
+    /// Saved data by id, or `None` if there is no data on disk for the id
+    cache: HashMap<String, Option<Box<dyn SavedData>>>,
+    data_folder: PathBuf,
+    fixer_upper: Arc<DataFixer>,
+}
+impl DimensionDataStorage {
+    pub fn new(data_folder: PathBuf, fixer_upper: Arc<DataFixer>) -> Self {
+        Self {
+            cache: HashMap::new(),
+            data_folder,
+            fixer_upper,
+        }
+    }
+
+    /// Gets saved data, creating it if it doesn't exist yet. Fails if the data under `id` is of
+    /// another type.
+    pub async fn compute_if_absent<T: SavedData>(
+        &mut self,
+        factory: &SavedDataFactory<T>,
+        id: &str,
+    ) -> Result<&mut T> {
+        if self.get(factory, id).await?.is_none() {
+            self.set(id, (factory.constructor)());
+        }
+        Ok(self
+            .get(factory, id)
+            .await?
+            .expect("Saved data was just set"))
+    }
+
+    /// Gets saved data, reading it from disk the first time. `None` if there is no data, or it
+    /// couldn't be read. Fails if the data under `id` is of another type.
+    pub async fn get<T: SavedData>(
+        &mut self,
+        factory: &SavedDataFactory<T>,
+        id: &str,
+    ) -> Result<Option<&mut T>> {
+        if !self.cache.contains_key(id) {
+            let saved_data = self.read_saved_data(factory, id).await;
+            self.cache.insert(
+                id.to_string(),
+                saved_data.map(|e| Box::new(e) as Box<dyn SavedData>),
+            );
+        }
+        let Some(saved_data) = self.cache.get_mut(id).and_then(|e| e.as_mut()) else {
+            return Ok(None);
+        };
+        let saved_data: &mut dyn Any = saved_data.as_mut();
+        saved_data
+            .downcast_mut()
+            .map(Some)
+            .ok_or_else(|| anyhow!("Saved data {} is of a different type", id))
+    }
+
+    /// Replaces saved data, marking it dirty so it is written on the next save
+    pub fn set(&mut self, id: &str, mut saved_data: impl SavedData) {
+        saved_data.set_dirty(true);
+        self.cache
+            .insert(id.to_string(), Some(Box::new(saved_data)));
+    }
+
+    /// Writes all dirty saved data. Data that fails to be written is kept dirty, to be retried on
+    /// the next save.
+    pub async fn save(&mut self) -> Result<()> {
+        let mut res = Ok(());
+        for (id, saved_data) in &mut self.cache {
+            let Some(saved_data) = saved_data.as_mut().filter(|e| e.is_dirty()) else {
+                continue;
+            };
+            let path = data_file(&self.data_folder, id);
+            let written = match saved_data::save_to_file_tag(saved_data.as_ref()) {
+                Ok(tag) => write_file(&self.data_folder, id, &tag).await,
+                Err(e) => Err(e),
+            };
+            match written {
+                Ok(()) => saved_data.set_dirty(false),
+                Err(e) => {
+                    error!(?e, "Could not save data to {:?}", path);
+                    res = Err(anyhow!("Could not save data to {:?}: {:#}", path, e));
+                }
+            }
+        }
+        res
+    }
+
+    /// Writes all dirty saved data
+    pub async fn close(mut self) -> Result<()> {
+        self.save().await
+    }
+
+    async fn read_saved_data<T: SavedData>(
+        &self,
+        factory: &SavedDataFactory<T>,
+        id: &str,
+    ) -> Option<T> {
+        let path = data_file(&self.data_folder, id);
+        if !path.exists() {
+            return None;
+        }
+        match self
+            .read_tag_from_disk(path.clone(), factory.data_fix_type)
+            .await
+            .and_then(|tag| (factory.deserializer)(&tag.get_compound("data")))
+        {
+            Ok(saved_data) => Some(saved_data),
+            Err(e) => {
+                error!(?e, "Error loading saved data: {}", id);
+                // Kept so the data can be recovered, as it is overwritten on the next save
+                let corrupted_file = self.data_folder.join(format!(
+                    "{}.dat_corrupted_{}",
+                    id,
+                    Zoned::now().strftime(BACKUP_TIMESTAMP_FORMAT)
+                ));
+                match tokio::fs::rename(&path, &corrupted_file).await {
+                    Ok(()) => warn!(
+                        "Moved corrupted saved data {:?} to {:?}",
+                        path, corrupted_file
+                    ),
+                    Err(e) => error!(?e, "Failed to move corrupted saved data {:?} aside", path),
+                }
+                None
+            }
+        }
+    }
+
+    /// Reads a saved data file, fixed to the current version
+    async fn read_tag_from_disk(
+        &self,
+        path: PathBuf,
+        data_fix_type: DataFixTypes,
+    ) -> Result<CompoundTag> {
+        let bytes = tokio::fs::read(&path).await?;
+        let tag = if bytes.starts_with(&GZIP_MAGIC) {
+            nbt_io::read_compressed_from(bytes.as_slice(), NbtAccounter::unlimited_heap()).await?
+        } else {
+            nbt_io::read_sync(bytes.as_slice(), NbtAccounter::unlimited_heap())?
+        };
+        let data_version = nbt_utils::get_data_version(&tag, NO_DATA_VERSION);
+        data_fix_type
+            .update_to_current_version(
+                self.fixer_upper.as_ref(),
+                Dynamic::new(nbt_ops::INSTANCE.clone(), Tag::CompoundTag(tag)),
+                data_version,
+            )
+            .into_value()
+            .try_as_compound_tag()
+            .ok_or_else(|| anyhow!("Fixed saved data {:?} is not a compound", path))
+    }
+}
+
+fn data_file(data_folder: &Path, id: &str) -> PathBuf {
+    data_folder.join(format!("{}.dat", id))
+}
+
+/// Writes a saved data file, keeping the previous file as `<id>.dat_old`
+async fn write_file(data_folder: &Path, id: &str, tag: &CompoundTag) -> Result<()> {
+    tokio::fs::create_dir_all(data_folder).await?;
+    nbt_io::write_compressed_atomically(
+        tag,
+        &data_file(data_folder, id),
+        &data_folder.join(format!("{}.dat_old", id)),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::datafix::data_fixers;
+    use tempfile::TempDir;
+
+    #[derive(Default)]
+    struct Counter {
+        count: i32,
+        dirty: bool,
+    }
+    impl SavedData for Counter {
+        fn save(&self, tag: &mut CompoundTag) -> Result<()> {
+            tag.put("count", Tag::IntTag(self.count));
+            Ok(())
+        }
+
+        fn is_dirty(&self) -> bool {
+            self.dirty
+        }
+
+        fn set_dirty(&mut self, dirty: bool) {
+            self.dirty = dirty;
+        }
+    }
+
+    #[derive(Default)]
+    struct Other {
+        dirty: bool,
+    }
+    impl SavedData for Other {
+        fn save(&self, _tag: &mut CompoundTag) -> Result<()> {
+            Ok(())
+        }
+
+        fn is_dirty(&self) -> bool {
+            self.dirty
+        }
+
+        fn set_dirty(&mut self, dirty: bool) {
+            self.dirty = dirty;
+        }
+    }
+
+    const COUNTER: SavedDataFactory<Counter> = SavedDataFactory {
+        constructor: Counter::default,
+        deserializer: |tag| {
+            Ok(Counter {
+                count: tag.get_int("count"),
+                dirty: false,
+            })
+        },
+        data_fix_type: DataFixTypes::SavedDataCommandStorage,
+    };
+    const OTHER: SavedDataFactory<Other> = SavedDataFactory {
+        constructor: Other::default,
+        deserializer: |_| Ok(Other::default()),
+        data_fix_type: DataFixTypes::SavedDataCommandStorage,
+    };
+
+    #[tokio::test]
+    async fn saved_data_is_written_and_read_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_folder = temp_dir.path().join("data");
+        let mut storage =
+            DimensionDataStorage::new(data_folder.clone(), data_fixers::get_data_fixer());
+        let counter = storage
+            .compute_if_absent(&COUNTER, "counter")
+            .await
+            .unwrap();
+        counter.count = 3;
+        storage.save().await.unwrap();
+        let counter = storage.get(&COUNTER, "counter").await.unwrap().unwrap();
+        counter.count = 5;
+        counter.set_dirty(true);
+        storage.close().await.unwrap();
+
+        let mut storage =
+            DimensionDataStorage::new(data_folder.clone(), data_fixers::get_data_fixer());
+        let counter = storage.get(&COUNTER, "counter").await.unwrap().unwrap();
+        assert_eq!(counter.count, 5);
+        assert!(data_folder.join("counter.dat_old").is_file());
+        assert!(storage.get(&OTHER, "counter").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn corrupted_saved_data_is_moved_aside() {
+        let temp_dir = TempDir::new().unwrap();
+        let data_folder = temp_dir.path().join("data");
+        std::fs::create_dir_all(&data_folder).unwrap();
+        std::fs::write(data_folder.join("counter.dat"), [0x1f, 0x8b, 0x08]).unwrap();
+
+        let mut storage =
+            DimensionDataStorage::new(data_folder.clone(), data_fixers::get_data_fixer());
+        assert!(storage.get(&COUNTER, "counter").await.unwrap().is_none());
+        assert!(!data_folder.join("counter.dat").exists());
+        let corrupted_files = std::fs::read_dir(&data_folder)
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with("counter.dat_corrupted_")
+            })
+            .count();
+        assert_eq!(corrupted_files, 1);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::nbt::compound_tag::CompoundTag;
+use crate::world::level::chunk_pos::ChunkPos;
+use crate::world::level::storage::region_file_storage::RegionFileStorage;
+use anyhow::{anyhow, Result};
+use indexmap::IndexMap;
+use std::future::Future;
+use std::path::PathBuf;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tracing::{error, warn};
+
+/// How urgently a read is needed. Foreground reads are for chunks players are waiting on, and are
+/// served before anything else queued.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Priority {
+    Foreground,
+    Background,
+}
+
+enum Task {
+    Load {
+        pos: ChunkPos,
+        respond: oneshot::Sender<Result<Option<CompoundTag>>>,
+    },
+    Store {
+        pos: ChunkPos,
+        data: Option<CompoundTag>,
+        respond: oneshot::Sender<Result<()>>,
+    },
+    Synchronize {
+        flush: bool,
+        respond: oneshot::Sender<Result<()>>,
+    },
+}
+
+/// The latest data to write for a chunk, and everyone waiting for it to be written
+struct PendingStore {
+    data: Option<CompoundTag>,
+    waiting: Vec<oneshot::Sender<Result<()>>>,
+}
+
+/// Reads and writes chunks of a region file directory on a task of its own, so disk IO stays off
+/// the tick loop.
+///
+/// Writes are queued and only written to disk when there is nothing else to do. Writing a chunk
+/// that is already queued replaces the queued data, and reads of a queued chunk are served from
+/// the queue.
+pub struct IoWorker {
+    foreground: mpsc::UnboundedSender<Task>,
+    background: mpsc::UnboundedSender<Task>,
+    worker: JoinHandle<()>,
+}
+impl IoWorker {
+    pub fn new(folder: PathBuf, sync: bool) -> Self {
+        let (foreground, foreground_receiver) = mpsc::unbounded_channel();
+        let (background, background_receiver) = mpsc::unbounded_channel();
+        let worker = tokio::spawn(
+            Worker {
+                storage: RegionFileStorage::new(folder, sync),
+                pending_writes: IndexMap::new(),
+            }
+            .run(foreground_receiver, background_receiver),
+        );
+        Self {
+            foreground,
+            background,
+            worker,
+        }
+    }
+
+    /// Queues a chunk to be written, or removed if there is no data. The returned future
+    /// completes once the chunk is written, but doesn't need to be awaited for it to be.
+    pub fn store(
+        &self,
+        pos: ChunkPos,
+        data: Option<CompoundTag>,
+    ) -> impl Future<Output = Result<()>> {
+        let (respond, response) = oneshot::channel();
+        let sent = self.foreground.send(Task::Store { pos, data, respond });
+        receive(sent.is_ok(), response)
+    }
+
+    pub fn load_async(
+        &self,
+        pos: ChunkPos,
+        priority: Priority,
+    ) -> impl Future<Output = Result<Option<CompoundTag>>> {
+        let (respond, response) = oneshot::channel();
+        let task = Task::Load { pos, respond };
+        let sent = match priority {
+            Priority::Foreground => self.foreground.send(task),
+            Priority::Background => self.background.send(task),
+        };
+        receive(sent.is_ok(), response)
+    }
+
+    /// Writes every queued chunk, then flushes the region files to disk if `flush` is set. Used
+    /// by autosave and shutdown.
+    pub fn synchronize(&self, flush: bool) -> impl Future<Output = Result<()>> {
+        let (respond, response) = oneshot::channel();
+        let sent = self.foreground.send(Task::Synchronize { flush, respond });
+        receive(sent.is_ok(), response)
+    }
+
+    /// Writes every queued chunk and stops the worker
+    pub async fn close(self) -> Result<()> {
+        let res = self.synchronize(true).await;
+        drop(self.foreground);
+        drop(self.background);
+        self.worker.await?;
+        res
+    }
+}
+
+async fn receive<T>(sent: bool, response: oneshot::Receiver<Result<T>>) -> Result<T> {
+    if !sent {
+        return Err(anyhow!("IO worker is closed"));
+    }
+    response
+        .await
+        .unwrap_or_else(|_| Err(anyhow!("IO worker stopped before completing the task")))
+}
+
+struct Worker {
+    storage: RegionFileStorage,
+    /// Queued writes, from oldest to newest
+    pending_writes: IndexMap<ChunkPos, PendingStore>,
+}
+impl Worker {
+    async fn run(
+        mut self,
+        mut foreground: mpsc::UnboundedReceiver<Task>,
+        mut background: mpsc::UnboundedReceiver<Task>,
+    ) {
+        loop {
+            tokio::select! {
+                biased;
+                Some(task) = foreground.recv() => self.handle(task).await,
+                Some(task) = background.recv() => self.handle(task).await,
+                _ = std::future::ready(()), if !self.pending_writes.is_empty() => {
+                    self.store_pending_chunk().await;
+                }
+                else => break,
+            }
+        }
+        if let Err(e) = self.storage.flush().await {
+            error!(?e, "Failed to flush chunk storage");
+        }
+    }
+
+    async fn handle(&mut self, task: Task) {
+        match task {
+            Task::Load { pos, respond } => {
+                let res = match self.pending_writes.get(&pos) {
+                    Some(pending) => Ok(pending.data.clone()),
+                    None => self.storage.read(pos).await.inspect_err(|e| {
+                        warn!(?e, "Failed to read chunk {}", pos);
+                    }),
+                };
+                let _ = respond.send(res);
+            }
+            Task::Store { pos, data, respond } => {
+                let pending = self
+                    .pending_writes
+                    .entry(pos)
+                    .or_insert_with(|| PendingStore {
+                        data: None,
+                        waiting: Vec::new(),
+                    });
+                pending.data = data;
+                pending.waiting.push(respond);
+            }
+            Task::Synchronize { flush, respond } => {
+                while !self.pending_writes.is_empty() {
+                    self.store_pending_chunk().await;
+                }
+                let res = if flush {
+                    self.storage.flush().await
+                } else {
+                    Ok(())
+                };
+                let _ = respond.send(res);
+            }
+        }
+    }
+
+    async fn store_pending_chunk(&mut self) {
+        let Some((pos, pending)) = self.pending_writes.shift_remove_index(0) else {
+            return;
+        };
+        let res = self.storage.write(pos, pending.data.as_ref()).await;
+        if let Err(e) = &res {
+            error!(?e, "Failed to store chunk {}", pos);
+        }
+        for waiting in pending.waiting {
+            let _ = waiting.send(match &res {
+                Ok(()) => Ok(()),
+                Err(e) => Err(anyhow!("Failed to store chunk {}: {:#}", pos, e)),
+            });
+        }
+    }
+}
@@ -6,6 +6,10 @@ pub enum LevelResource {
     OldLevelDataFile,
     IconFile,
     DatapackDir,
+    PlayerAdvancementsDir,
+    PlayerStatsDir,
+    PlayerDataDir,
+    PlayerOldDataDir,
 }
 impl LevelResource {
     pub const fn id(&self) -> &str {
@@ -14,6 +18,10 @@ impl LevelResource {
             LevelResource::OldLevelDataFile => "level.dat_old",
             LevelResource::IconFile => "icon.png",
             LevelResource::DatapackDir => "datapacks",
+            LevelResource::PlayerAdvancementsDir => "advancements",
+            LevelResource::PlayerStatsDir => "stats",
+            LevelResource::PlayerDataDir => "playerdata",
+            LevelResource::PlayerOldDataDir => "players",
         }
     }
 }
@@ -1,27 +1,50 @@
 use crate::codec::Codec;
 use crate::nbt::compound_tag::CompoundTag;
 use crate::nbt::nbt_accounter::NbtAccounter;
+use crate::nbt::tag::Tag;
 use crate::nbt::{nbt_io, nbt_ops, nbt_utils};
+use crate::resources::resource_location;
+use crate::resources::resource_location::ResourceLocation;
+use crate::shared_constants;
+use crate::util;
 use crate::util::datafix::data_fix_types::DataFixTypes;
 use crate::util::datafix::data_fixers;
 use crate::util::datafix::data_fixers::DataFixer;
 use crate::util::datafix::serialization::dynamic::Dynamic;
+use crate::util::directory_lock;
 use crate::util::directory_lock::DirectoryLock;
 use crate::world::flag::feature_flags;
 use crate::world::level::level_settings::LevelSettings;
 use crate::world::level::storage::level_resource::LevelResource;
 use crate::world::level::storage::level_summary::LevelSummary;
 use crate::world::level::storage::level_version::LevelVersion;
+use crate::world::level::validation::directory_validation_report::DirectoryValidationReport;
 use crate::world::level::validation::directory_validator::DirectoryValidator;
+use crate::world::level::validation::path_allow_list::PathAllowList;
 use crate::world::level::world_data_configuration::WorldDataConfiguration;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use jiff::Zoned;
 use std::collections::HashMap;
 use std::fs::File;
-use std::path::PathBuf;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
-use tracing::error;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
 
 const ALLOWED_SYMLINKS_FILE: &str = "allowed_symlinks.txt";
+/// Level data version of the Anvil format, the only one that can be loaded without conversion
+pub const ANVIL_VERSION_ID: i32 = 19133;
+/// Format of the timestamps in backup and corrupted file names
+pub const BACKUP_TIMESTAMP_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+/// Length of a timestamp in [BACKUP_TIMESTAMP_FORMAT], which is always the same
+const BACKUP_TIMESTAMP_LEN: usize = "0000-00-00_00-00-00".len();
+/// Tries at deleting a level before giving up, as files may be briefly held by other processes
+const DELETE_ATTEMPTS: u32 = 5;
+const DELETE_RETRY_DELAY: Duration = Duration::from_millis(500);
 
 pub struct LevelStorageSource {
     base_dir: PathBuf,
@@ -64,24 +87,149 @@ impl LevelStorageSource {
         )
     }
 
-    pub fn validate_and_create_access(&self, level_name: String) -> LevelStorageAccess {
-        let level_path = self.base_dir.join(&level_name);
-        let errors = self
-            .world_dir_validator
-            .validate_directory(level_path.clone(), true);
-        if !errors.is_empty() {
-            panic!("Level directory validation failed: {:#?}", errors);
+    /// Directory holding every level, the `universe`
+    pub fn base_dir(&self) -> &Path {
+        &self.base_dir
+    }
+
+    pub fn level_exists(&self, level_id: &str) -> bool {
+        let level_directory = LevelDirectory::new(self.base_dir.join(level_id));
+        level_directory.data_file().exists() || level_directory.old_data_file().exists()
+    }
+
+    /// Whether a new level can be created with this id: a single, unused directory name
+    pub fn is_new_level_id_acceptable(&self, level_id: &str) -> bool {
+        is_valid_level_id(level_id) && !self.base_dir.join(level_id).exists()
+    }
+
+    /// Level directories in the base directory, which are those with level data
+    pub fn find_level_candidates(&self) -> Result<Vec<LevelDirectory>> {
+        let mut candidates = Vec::new();
+        for entry in std::fs::read_dir(&self.base_dir)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let level_directory = LevelDirectory::new(entry.path());
+            if level_directory.data_file().exists() || level_directory.old_data_file().exists() {
+                candidates.push(level_directory);
+            }
+        }
+        Ok(candidates)
+    }
+
+    /// Summaries of every level in the base directory, most recently played first. Levels whose
+    /// data can't be read are logged and left out.
+    pub async fn load_level_summaries(&self) -> Result<Vec<LevelSummary>> {
+        let mut summaries = Vec::new();
+        for level_directory in self.find_level_candidates()? {
+            let locked = DirectoryLock::is_locked(level_directory.path())?;
+            let dynamic = match self.read_level_data(&level_directory).await {
+                Ok(dynamic) => dynamic,
+                Err(e) => {
+                    error!(
+                        ?e,
+                        "Failed to read level data of {:?}, skipping it",
+                        level_directory.path()
+                    );
+                    continue;
+                }
+            };
+            match read_level_summary(&level_directory, &dynamic, locked) {
+                Ok(summary) => summaries.push(summary),
+                Err(e) => error!(
+                    ?e,
+                    "Failed to read summary of level {:?}, skipping it",
+                    level_directory.path()
+                ),
+            }
+        }
+        summaries.sort_by(|a, b| {
+            b.last_played()
+                .cmp(&a.last_played())
+                .then_with(|| a.level_id().cmp(b.level_id()))
+        });
+        Ok(summaries)
+    }
+
+    /// Reads the fixed level data of a level, falling back to level.dat_old if level.dat can't be
+    /// read
+    async fn read_level_data(&self, level_directory: &LevelDirectory) -> Result<Dynamic<Tag>> {
+        let data_file = level_directory.data_file();
+        match read_level_data_tag_fixed(data_file.clone(), &self.fixer_upper).await {
+            Ok(dynamic) => Ok(dynamic),
+            Err(e) => {
+                warn!(?e, "Failed to read {:?}, trying level.dat_old", data_file);
+                read_level_data_tag_fixed(level_directory.old_data_file(), &self.fixer_upper).await
+            }
         }
-        LevelStorageAccess::new(level_name, level_path)
+    }
+
+    /// Creates a new, empty level with only its level.dat, and opens it
+    pub async fn create_level(
+        &self,
+        level_id: &str,
+        level_settings: &LevelSettings,
+    ) -> Result<LevelStorageAccess> {
+        if !self.is_new_level_id_acceptable(level_id) {
+            return Err(anyhow!(
+                "Level id '{}' is invalid or already in use",
+                level_id
+            ));
+        }
+        let access = LevelStorageAccess::new(
+            level_id.to_string(),
+            self.base_dir.join(level_id),
+            self.backup_dir.clone(),
+        )?;
+        access
+            .save_data_tag(&CompoundTag::default(), level_settings, None)
+            .await?;
+        info!("Created level {}", level_id);
+        Ok(access)
+    }
+
+    /// Checks the level directory for symbolic links that aren't allowed and other problems
+    pub fn validate_level_directory(&self, level_name: &str) -> DirectoryValidationReport {
+        self.world_dir_validator
+            .validate_directory(self.base_dir.join(level_name), true)
+    }
+
+    /// Opens a level after checking its directory for disallowed symbolic links, failing with the
+    /// [DirectoryValidationReport] if it isn't valid. If another instance has the level open, fails
+    /// right away, or with a `lock_timeout` waits up to that long for the level to be released.
+    pub async fn validate_and_create_access(
+        &self,
+        level_name: String,
+        lock_timeout: Option<Duration>,
+    ) -> Result<LevelStorageAccess> {
+        let report = self.validate_level_directory(&level_name);
+        if !report.is_empty() {
+            return Err(report.into());
+        }
+        let level_path = self.base_dir.join(&level_name);
+        let lock = match lock_timeout {
+            Some(timeout) => {
+                DirectoryLock::create_with_timeout(level_path.clone(), timeout).await?
+            }
+            None => DirectoryLock::create(level_path.clone())?,
+        };
+        Ok(LevelStorageAccess::with_lock(
+            level_name,
+            level_path,
+            self.backup_dir.clone(),
+            lock,
+        ))
     }
 }
 
 fn parse_validator(path: PathBuf) -> DirectoryValidator {
     if std::fs::exists(&path).unwrap_or_else(|_| panic!("Failed to check if {:?} exists", path)) {
-        match File::open(&path) {
-            Ok(_file) => {
-                todo!("{} is not yet supported", ALLOWED_SYMLINKS_FILE);
-            }
+        match File::open(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| PathAllowList::read_plain(BufReader::new(file)))
+        {
+            Ok(allow_list) => return DirectoryValidator::new(allow_list),
             Err(e) => {
                 error!(
                     ?e,
@@ -93,27 +241,40 @@ fn parse_validator(path: PathBuf) -> DirectoryValidator {
         }
     }
 
-    DirectoryValidator::new(|_| false)
+    DirectoryValidator::new(PathAllowList::default())
 }
 
 async fn read_level_data_tag_fixed(
     path: PathBuf,
     data_fixer: impl AsRef<DataFixer>,
-) -> Result<Dynamic<CompoundTag>> {
+) -> Result<Dynamic<Tag>> {
     let root = read_level_data_tag_raw(path).await?;
     let data = root.get_compound("Data");
     let data_version = nbt_utils::get_data_version(&data, 0);
-    let res = DataFixTypes::Level.update_to_current_version(
-        data_fixer.as_ref(),
-        Dynamic::new(nbt_ops::INSTANCE.clone(), data),
-        data_version,
-    );
-    // TODO: DFU for Player and WorldGenSettings. Vanilla is a mess with this
+    let data_fixer = data_fixer.as_ref();
+    let res = DataFixTypes::Level
+        .update_to_current_version(
+            data_fixer,
+            Dynamic::new(nbt_ops::INSTANCE.clone(), Tag::CompoundTag(data)),
+            data_version,
+        )
+        // The player of singleplayer worlds and world gen settings are stored in level.dat, but
+        // are their own types to the data fixer
+        .update("Player", |player| {
+            DataFixTypes::Player.update_to_current_version(data_fixer, player, data_version)
+        })
+        .update("WorldGenSettings", |settings| {
+            DataFixTypes::WorldGenSettings.update_to_current_version(
+                data_fixer,
+                settings,
+                data_version,
+            )
+        });
     Ok(res)
 }
 
 async fn read_level_data_tag_raw(path: PathBuf) -> Result<CompoundTag> {
-    nbt_io::read_compressed(path, NbtAccounter::create(104857600)).await
+    nbt_io::read_compressed(path, NbtAccounter::level_data()).await
 }
 
 pub struct LevelDirectory {
@@ -124,6 +285,10 @@ impl LevelDirectory {
         Self { path }
     }
 
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     pub fn directory_name(&self) -> String {
         self.path.file_name().unwrap().to_str().unwrap().to_string()
     }
@@ -136,6 +301,15 @@ impl LevelDirectory {
         self.resource_path(LevelResource::OldLevelDataFile)
     }
 
+    /// Where a corrupted level.dat is kept when it is replaced by level.dat_old
+    pub fn corrupted_data_file(&self, time: &Zoned) -> PathBuf {
+        self.path.join(format!(
+            "{}_corrupted_{}",
+            LevelResource::LevelDataFile.id(),
+            time.strftime(BACKUP_TIMESTAMP_FORMAT)
+        ))
+    }
+
     fn resource_path(&self, level_resource: LevelResource) -> PathBuf {
         self.path.join(level_resource.id())
     }
@@ -149,18 +323,43 @@ pub struct LevelStorageAccess {
     lock: DirectoryLock,
     pub level_directory: LevelDirectory,
     level_id: String,
+    backup_dir: PathBuf,
     resources: HashMap<LevelResource, PathBuf>,
 }
 impl LevelStorageAccess {
-    pub fn new(level_id: String, level_directory: PathBuf) -> Self {
+    /// Opens a level, locking its directory. Fails if another instance has the level open.
+    pub fn new(level_id: String, level_directory: PathBuf, backup_dir: PathBuf) -> Result<Self> {
+        let lock = DirectoryLock::create(level_directory.clone())?;
+        Ok(Self::with_lock(level_id, level_directory, backup_dir, lock))
+    }
+
+    fn with_lock(
+        level_id: String,
+        level_directory: PathBuf,
+        backup_dir: PathBuf,
+        lock: DirectoryLock,
+    ) -> Self {
         Self {
-            lock: DirectoryLock::create(level_directory.clone()),
+            lock,
             level_directory: LevelDirectory::new(level_directory),
             level_id,
+            backup_dir,
             resources: HashMap::new(),
         }
     }
 
+    /// Fails if the session lock no longer guards the level, as another instance may then be
+    /// writing to it too
+    pub fn check_lock(&self) -> Result<()> {
+        if !self.lock.is_valid() {
+            return Err(anyhow!(
+                "Lock on level {} is no longer valid",
+                self.level_id
+            ));
+        }
+        Ok(())
+    }
+
     pub fn get_level_path(&mut self, level_resource: LevelResource) -> PathBuf {
         self.resources
             .entry(level_resource)
@@ -168,41 +367,55 @@ impl LevelStorageAccess {
             .clone()
     }
 
+    /// Folder holding a dimension's chunks and saved data
+    pub fn get_dimension_path(&self, dimension: &ResourceLocation) -> PathBuf {
+        let root = self.level_directory.path();
+        match (dimension.namespace.as_str(), dimension.path.as_str()) {
+            ("minecraft", "overworld") => root.to_path_buf(),
+            ("minecraft", "the_nether") => root.join("DIM-1"),
+            ("minecraft", "the_end") => root.join("DIM1"),
+            (namespace, path) => root.join("dimensions").join(namespace).join(path),
+        }
+    }
+
+    /// Dimensions that have been saved in this level, found from their folders. The overworld is
+    /// always included.
+    pub fn list_dimensions(&self) -> Vec<ResourceLocation> {
+        let mut dimensions = vec![ResourceLocation::with_default_namespace("overworld")];
+        for path in ["the_nether", "the_end"] {
+            let dimension = ResourceLocation::with_default_namespace(path);
+            if self.get_dimension_path(&dimension).is_dir() {
+                dimensions.push(dimension);
+            }
+        }
+        let custom_dir = self.level_directory.path().join("dimensions");
+        for namespace in read_dir_names(&custom_dir) {
+            if !resource_location::is_valid_namespace(&namespace) {
+                continue;
+            }
+            for path in read_dir_names(&custom_dir.join(&namespace)) {
+                if resource_location::is_valid_path(&path) {
+                    dimensions.push(ResourceLocation::new(namespace.clone(), path));
+                }
+            }
+        }
+        dimensions
+    }
+
     pub fn has_world_data(&self) -> bool {
         self.level_directory.data_file().exists() || self.level_directory.old_data_file().exists()
     }
 
     // We differ from vanilla in that this must be for a CompoundTag
-    // TODO: move this out to share with LevelSummary readLevelSummary
-    pub fn make_level_summary(&self, dynamic: &Dynamic<CompoundTag>, locked: bool) -> LevelSummary {
-        let level_version = LevelVersion::parse(dynamic);
-        let level_data_version = level_version.level_data_version;
-        if level_data_version != 19132 && level_data_version != 19133 {
-            panic!("Unknown data version: {}", level_data_version);
-        }
-        let is_not_storage_version = level_data_version != 19133;
-        let icon_file = self.level_directory.icon_file();
-        let world_data_configuration = read_data_config(dynamic);
-        let level_settings = LevelSettings::parse(dynamic, world_data_configuration);
-        let is_experimental =
-            feature_flags::is_experimental(&level_settings.data_configuration.enabled_features);
-        LevelSummary::new(
-            level_settings,
-            level_version,
-            // TODO: This differs from vanilla but I believe this should be the same thing
-            self.level_id.clone(),
-            is_not_storage_version,
-            locked,
-            is_experimental,
-            icon_file,
-        )
+    pub fn make_level_summary(&self, dynamic: &Dynamic<Tag>, locked: bool) -> Result<LevelSummary> {
+        read_level_summary(&self.level_directory, dynamic, locked)
     }
 
     pub async fn get_data_tag(
         &self,
         use_old_data_file: bool,
         data_fixer: impl AsRef<DataFixer>,
-    ) -> Result<Dynamic<CompoundTag>> {
+    ) -> Result<Dynamic<Tag>> {
         // No need to check lock here as DirectoryLock only unlocks on drop
         read_level_data_tag_fixed(
             if use_old_data_file {
@@ -215,11 +428,512 @@ impl LevelStorageAccess {
         .await
     }
 
-    pub fn restore_level_data_from_old(&self) -> bool {
-        todo!("Restoring level data from old is not yet supported. This means the level data is corrupted but the old level data is valid.");
+    /// Saves level data to level.dat. `level_data` is the data the level was loaded with, which
+    /// keeps any fields that aren't modeled yet, and is updated with the settings, version and the
+    /// singleplayer player, if there is one.
+    pub async fn save_data_tag(
+        &self,
+        level_data: &CompoundTag,
+        level_settings: &LevelSettings,
+        player_tag: Option<&CompoundTag>,
+    ) -> Result<()> {
+        let mut data = level_data.clone();
+        level_settings.write_to(&mut data)?;
+        write_version_data(&mut data);
+        if let Some(player_tag) = player_tag {
+            data.put("Player", Tag::CompoundTag(player_tag.clone()));
+        }
+        let mut root = CompoundTag::default();
+        root.put("Data", Tag::CompoundTag(data));
+        self.save_level_data(&root).await
+    }
+
+    /// Writes level data to a temporary file, then swaps it in, moving the previous level.dat to
+    /// level.dat_old. level.dat is never partially written, even if the server stops mid-save.
+    async fn save_level_data(&self, root: &CompoundTag) -> Result<()> {
+        self.check_lock()?;
+        nbt_io::write_compressed_atomically(
+            root,
+            &self.level_directory.data_file(),
+            &self.level_directory.old_data_file(),
+        )
+        .await
+    }
+
+    /// Sets the display name of the level in level.dat, leaving the rest of the level data and its
+    /// directory as they are. A running server overwrites this with its own settings when saving.
+    pub async fn rename_level(&self, level_name: &str) -> Result<()> {
+        let mut root = read_level_data_tag_raw(self.level_directory.data_file()).await?;
+        let mut data = root.get_compound("Data");
+        data.put("LevelName", Tag::StringTag(level_name.trim().to_string()));
+        root.put("Data", Tag::CompoundTag(data));
+        self.save_level_data(&root).await
+    }
+
+    /// Deletes the level and its directory. The session lock is held until everything else is
+    /// deleted, so no other instance can open the level while it is partly removed.
+    pub async fn delete_level(self) -> Result<()> {
+        self.check_lock()?;
+        let level_path = self.level_directory.path().to_path_buf();
+        info!("Deleting level {}", self.level_id);
+        let mut attempt = 1;
+        loop {
+            info!("Attempt {}...", attempt);
+            let path = level_path.clone();
+            match tokio::task::spawn_blocking(move || clear_level_directory(&path)).await? {
+                Ok(()) => break,
+                Err(e) if attempt < DELETE_ATTEMPTS => {
+                    warn!(?e, "Failed to delete {:?}", level_path);
+                    tokio::time::sleep(DELETE_RETRY_DELAY).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        drop(self.lock);
+        tokio::fs::remove_file(level_path.join(directory_lock::LOCK_FILE)).await?;
+        tokio::fs::remove_dir(&level_path).await?;
+        Ok(())
+    }
+
+    /// Copies the level into a new level directory next to it, and opens the copy. Only what has
+    /// been saved to disk is copied.
+    pub async fn copy_level(&self, new_level_id: &str) -> Result<LevelStorageAccess> {
+        let target_path = self.level_directory.path().with_file_name(new_level_id);
+        if !is_valid_level_id(new_level_id) || target_path.exists() {
+            return Err(anyhow!(
+                "Level id '{}' is invalid or already in use",
+                new_level_id
+            ));
+        }
+        let access = LevelStorageAccess::new(
+            new_level_id.to_string(),
+            target_path.clone(),
+            self.backup_dir.clone(),
+        )?;
+        let level_path = self.level_directory.path().to_path_buf();
+        tokio::task::spawn_blocking(move || copy_level_directory(&level_path, &target_path))
+            .await??;
+        info!("Copied level {} to {}", self.level_id, new_level_id);
+        Ok(access)
+    }
+
+    /// Zips the level directory into the backup directory as `<timestamp>_<level id>.zip`, with
+    /// entries under the level's directory name. Returns the path and size of the backup.
+    ///
+    /// The zip is written to a `.tmp` file first and renamed once complete, so a failed backup
+    /// never looks like a finished one.
+    pub async fn make_world_backup(&self) -> Result<(PathBuf, u64)> {
+        tokio::fs::create_dir_all(&self.backup_dir).await?;
+        let timestamp = Zoned::now().strftime(BACKUP_TIMESTAMP_FORMAT).to_string();
+        let backup_path = self
+            .backup_dir
+            .join(format!("{}_{}.zip", timestamp, self.level_id));
+        let temp_path = self
+            .backup_dir
+            .join(format!("{}_{}.zip.tmp", timestamp, self.level_id));
+        let level_path = self.level_directory.path().to_path_buf();
+        let directory_name = self.level_directory.directory_name();
+        let path = temp_path.clone();
+        let written =
+            tokio::task::spawn_blocking(move || write_backup(&level_path, &directory_name, &path))
+                .await
+                .map_err(anyhow::Error::from)
+                .and_then(|res| res);
+        if let Err(e) = written {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(e);
+        }
+        tokio::fs::rename(&temp_path, &backup_path).await?;
+        let size = tokio::fs::metadata(&backup_path).await?.len();
+        info!("Backed up level to {:?} ({} bytes)", backup_path, size);
+        Ok((backup_path, size))
+    }
+
+    /// Backups of this level, newest first
+    pub fn list_backups(&self) -> Result<Vec<PathBuf>> {
+        if !self.backup_dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut backups = Vec::new();
+        for entry in std::fs::read_dir(&self.backup_dir)? {
+            let path = entry?.path();
+            if path
+                .file_name()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| is_backup_of(e, &self.level_id))
+            {
+                backups.push(path);
+            }
+        }
+        // Timestamps sort the same as the times they stand for
+        backups.sort();
+        backups.reverse();
+        Ok(backups)
+    }
+
+    /// Replaces the level's files with the contents of a backup. The backup is extracted in full
+    /// before anything in the level is touched, and the level's files are moved aside rather than
+    /// removed until the backup is in place, so a failed restore leaves the level as it was.
+    pub async fn restore_backup(&self, backup_path: &Path) -> Result<()> {
+        self.check_lock()?;
+        let level_path = self.level_directory.path().to_path_buf();
+        let directory_name = self.level_directory.directory_name();
+        let staging_dir = level_path.with_file_name(format!("{}.restore", directory_name));
+        let previous_dir = level_path.with_file_name(format!("{}.previous", directory_name));
+        let backup = backup_path.to_path_buf();
+        tokio::task::spawn_blocking(move || -> Result<()> {
+            for dir in [&staging_dir, &previous_dir] {
+                if dir.exists() {
+                    std::fs::remove_dir_all(dir)?;
+                }
+            }
+            extract_backup(&backup, &staging_dir)?;
+            std::fs::create_dir_all(&previous_dir)?;
+            move_level_entries(&level_path, &previous_dir)?;
+            if let Err(e) = move_level_entries(&staging_dir, &level_path) {
+                move_level_entries(&previous_dir, &level_path).inspect_err(|e| {
+                    error!(
+                        ?e,
+                        "Failed to move the level's files back from {:?}", previous_dir
+                    )
+                })?;
+                return Err(e);
+            }
+            std::fs::remove_dir_all(&staging_dir)?;
+            std::fs::remove_dir_all(&previous_dir)?;
+            Ok(())
+        })
+        .await??;
+        info!("Restored level {} from {:?}", self.level_id, backup_path);
+        Ok(())
+    }
+
+    /// Replaces a corrupted level.dat with level.dat_old, keeping the corrupted file next to it
+    pub fn restore_level_data_from_old(&self) -> Result<()> {
+        let corrupted_file = self.level_directory.corrupted_data_file(&Zoned::now());
+        util::safe_replace_or_move_file(
+            &self.level_directory.data_file(),
+            &self.level_directory.old_data_file(),
+            &corrupted_file,
+        )?;
+        warn!(
+            "Restored level data from {:?}, the corrupted level data was moved to {:?}",
+            self.level_directory.old_data_file(),
+            corrupted_file
+        );
+        Ok(())
+    }
+}
+
+fn write_backup(level_path: &Path, directory_name: &str, backup_path: &Path) -> Result<()> {
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(backup_path)?));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for entry in WalkDir::new(level_path) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(level_path)?;
+        if !entry.file_type().is_file() || relative == Path::new(directory_lock::LOCK_FILE) {
+            continue;
+        }
+        let name = Path::new(directory_name)
+            .join(relative)
+            .to_string_lossy()
+            .replace('\\', "/");
+        zip.start_file(name, options)?;
+        std::io::copy(&mut File::open(entry.path())?, &mut zip)?;
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
+
+/// Extracts a backup from [LevelStorageAccess::make_world_backup], dropping the level directory
+/// name its entries are under
+fn extract_backup(backup_path: &Path, target_dir: &Path) -> Result<()> {
+    let mut zip = ZipArchive::new(File::open(backup_path)?)?;
+    for i in 0..zip.len() {
+        let mut file = zip.by_index(i)?;
+        let Some(name) = file.enclosed_name() else {
+            return Err(anyhow!("Invalid entry {} in backup {:?}", file.name(), backup_path));
+        };
+        let relative: PathBuf = name.components().skip(1).collect();
+        if relative.as_os_str().is_empty() || file.is_dir() {
+            continue;
+        }
+        let path = target_dir.join(relative);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(&mut file, &mut File::create(path)?)?;
     }
+    Ok(())
+}
+
+/// Whether a file in the backup directory is a backup of a level, named
+/// `<timestamp>_<level id>.zip`
+fn is_backup_of(file_name: &str, level_id: &str) -> bool {
+    let Some((timestamp, rest)) = file_name.split_at_checked(BACKUP_TIMESTAMP_LEN) else {
+        return false;
+    };
+    rest == format!("_{}.zip", level_id)
+        && jiff::civil::DateTime::strptime(BACKUP_TIMESTAMP_FORMAT, timestamp).is_ok()
+}
+
+/// Moves everything in a directory but the session lock into another. If a move fails, what was
+/// already moved is moved back.
+fn move_level_entries(from: &Path, to: &Path) -> Result<()> {
+    let mut moved = Vec::new();
+    let res = (|| -> Result<()> {
+        for entry in std::fs::read_dir(from)? {
+            let name = entry?.file_name();
+            if name == directory_lock::LOCK_FILE {
+                continue;
+            }
+            std::fs::rename(from.join(&name), to.join(&name))?;
+            moved.push(name);
+        }
+        Ok(())
+    })();
+    if res.is_err() {
+        for name in moved {
+            if let Err(e) = std::fs::rename(to.join(&name), from.join(&name)) {
+                error!(?e, "Failed to move {:?} back to {:?}", to.join(&name), from);
+            }
+        }
+    }
+    res
+}
+
+/// Removes everything in a level directory but the session lock
+fn clear_level_directory(level_path: &Path) -> Result<()> {
+    for entry in std::fs::read_dir(level_path)? {
+        let entry = entry?;
+        if entry.file_name() == directory_lock::LOCK_FILE {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            std::fs::remove_dir_all(entry.path())?;
+        } else {
+            std::fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Whether a level id is a single directory name, so it can't point outside the base directory
+fn is_valid_level_id(level_id: &str) -> bool {
+    let mut components = Path::new(level_id).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(name)), None) if name == level_id
+    )
 }
 
-pub fn read_data_config(dynamic: &Dynamic<CompoundTag>) -> WorldDataConfiguration {
+/// Copies every file of a level directory but the session lock, following symbolic links
+fn copy_level_directory(level_path: &Path, target_path: &Path) -> Result<()> {
+    for entry in WalkDir::new(level_path).follow_links(true) {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(level_path)?;
+        let target = target_path.join(relative);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(target)?;
+        } else if relative != Path::new(directory_lock::LOCK_FILE) {
+            std::fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Summarizes a level for listing, from its fixed level data
+pub fn read_level_summary(
+    level_directory: &LevelDirectory,
+    dynamic: &Dynamic<Tag>,
+    locked: bool,
+) -> Result<LevelSummary> {
+    let level_version = LevelVersion::parse(dynamic);
+    let level_data_version = level_version.level_data_version;
+    if level_data_version != 19132 && level_data_version != ANVIL_VERSION_ID {
+        return Err(anyhow!("Unknown data version: {}", level_data_version));
+    }
+    let is_not_storage_version = level_data_version != ANVIL_VERSION_ID;
+    let world_data_configuration = read_data_config(dynamic);
+    let level_settings = LevelSettings::parse(dynamic, world_data_configuration);
+    let is_experimental =
+        feature_flags::is_experimental(&level_settings.data_configuration.enabled_features);
+    Ok(LevelSummary::new(
+        level_settings,
+        level_version,
+        level_directory.directory_name(),
+        is_not_storage_version,
+        locked,
+        is_experimental,
+        level_directory.icon_file(),
+    ))
+}
+
+/// Writes the version the level was saved in and when
+fn write_version_data(data: &mut CompoundTag) {
+    let world_version = &shared_constants::WORLD_VERSION;
+    let mut version = CompoundTag::default();
+    version.put("Name", Tag::StringTag(world_version.name.clone()));
+    version.put("Id", Tag::IntTag(world_version.world_version.version));
+    version.put("Snapshot", Tag::ByteTag(!world_version.stable as u8));
+    version.put(
+        "Series",
+        Tag::StringTag(world_version.world_version.series.clone()),
+    );
+    data.put("Version", Tag::CompoundTag(version));
+    nbt_utils::add_current_data_version(data);
+    data.put("version", Tag::IntTag(ANVIL_VERSION_ID));
+    data.put(
+        "LastPlayed",
+        Tag::LongTag(Zoned::now().timestamp().as_millisecond()),
+    );
+}
+
+/// Names of the directories in a directory, or none if it can't be read
+fn read_dir_names(path: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect()
+}
+
+pub fn read_data_config(dynamic: &Dynamic<Tag>) -> WorldDataConfiguration {
     WorldDataConfiguration::decode(dynamic.clone()).unwrap_or_default()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::tag_parser;
+    use tempfile::TempDir;
+
+    async fn open_level(base_dir: &Path, level_id: &str) -> LevelStorageAccess {
+        LevelStorageSource::new(
+            base_dir.to_path_buf(),
+            base_dir.join("backups"),
+            DirectoryValidator::new(PathAllowList::default()),
+            data_fixers::get_data_fixer(),
+        )
+        .validate_and_create_access(level_id.to_string(), None)
+        .await
+        .unwrap()
+    }
+
+    /// Opens a level whose level.dat is cut off halfway through, and whose level.dat_old is intact
+    async fn open_level_with_truncated_data(
+        base_dir: &Path,
+    ) -> (LevelStorageSource, LevelStorageAccess) {
+        let source = LevelStorageSource::new(
+            base_dir.to_path_buf(),
+            base_dir.join("backups"),
+            DirectoryValidator::new(PathAllowList::default()),
+            data_fixers::get_data_fixer(),
+        );
+        let access = source
+            .validate_and_create_access("world".to_string(), None)
+            .await
+            .unwrap();
+        let level_directory = &access.level_directory;
+        let data = format!(
+            r#"{{Data:{{LevelName:"Old",DataVersion:{}}}}}"#,
+            shared_constants::get_current_data_version()
+        );
+        nbt_io::write_compressed(
+            &tag_parser::parse_tag(&data).unwrap(),
+            level_directory.old_data_file(),
+        )
+        .await
+        .unwrap();
+        let bytes = std::fs::read(level_directory.old_data_file()).unwrap();
+        std::fs::write(level_directory.data_file(), &bytes[..bytes.len() / 2]).unwrap();
+        (source, access)
+    }
+
+    #[tokio::test]
+    async fn truncated_level_data_falls_back_to_old() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_dir = temp_dir.path();
+        let (source, access) = open_level_with_truncated_data(base_dir).await;
+
+        assert!(access
+            .get_data_tag(false, source.get_data_fixer())
+            .await
+            .is_err());
+        let old_data = access
+            .get_data_tag(true, source.get_data_fixer())
+            .await
+            .unwrap();
+        assert_eq!(old_data.get("LevelName").as_string_or(""), "Old");
+        let data = source
+            .read_level_data(&access.level_directory)
+            .await
+            .unwrap();
+        assert_eq!(data.get("LevelName").as_string_or(""), "Old");
+    }
+
+    #[tokio::test]
+    async fn restore_level_data_from_old_keeps_corrupted_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_dir = temp_dir.path();
+        let (_source, access) = open_level_with_truncated_data(base_dir).await;
+        let level_directory = &access.level_directory;
+        let old_bytes = std::fs::read(level_directory.old_data_file()).unwrap();
+        let truncated_bytes = std::fs::read(level_directory.data_file()).unwrap();
+
+        access.restore_level_data_from_old().unwrap();
+
+        assert_eq!(
+            std::fs::read(level_directory.data_file()).unwrap(),
+            old_bytes
+        );
+        let corrupted_files: Vec<PathBuf> = std::fs::read_dir(level_directory.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| e.starts_with("level.dat_corrupted_"))
+            })
+            .collect();
+        assert_eq!(corrupted_files.len(), 1);
+        assert_eq!(std::fs::read(&corrupted_files[0]).unwrap(), truncated_bytes);
+    }
+
+    #[tokio::test]
+    async fn backups_are_listed_and_restored() {
+        let temp_dir = TempDir::new().unwrap();
+        let base_dir = temp_dir.path();
+        let access = open_level(base_dir, "world").await;
+        let level_path = access.level_directory.path().to_path_buf();
+        std::fs::create_dir_all(level_path.join("region")).unwrap();
+        std::fs::write(level_path.join("region/r.0.0.mca"), "backed up").unwrap();
+        let (backup_path, _) = access.make_world_backup().await.unwrap();
+
+        // Backups of other levels whose ids end the same way, and unfinished backups
+        for name in [
+            "2024-01-01_00-00-00_my_world.zip",
+            "2024-01-01_00-00-00_world.zip.tmp",
+            "not-a-timestamp_world.zip",
+        ] {
+            std::fs::write(base_dir.join("backups").join(name), "").unwrap();
+        }
+        assert_eq!(access.list_backups().unwrap(), vec![backup_path.clone()]);
+
+        std::fs::write(level_path.join("region/r.0.0.mca"), "changed").unwrap();
+        std::fs::write(level_path.join("added.dat"), "added").unwrap();
+        access.restore_backup(&backup_path).await.unwrap();
+        assert_eq!(
+            std::fs::read_to_string(level_path.join("region/r.0.0.mca")).unwrap(),
+            "backed up"
+        );
+        assert!(!level_path.join("added.dat").exists());
+        assert!(level_path.join(directory_lock::LOCK_FILE).exists());
+        assert!(!level_path.with_file_name("world.restore").exists());
+        assert!(!level_path.with_file_name("world.previous").exists());
+    }
+}
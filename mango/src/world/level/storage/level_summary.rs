@@ -2,7 +2,7 @@ use crate::shared_constants;
 use crate::world::level::level_settings::LevelSettings;
 use crate::world::level::storage::level_version::LevelVersion;
 use serde::Serialize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Serialize)]
 pub struct LevelSummary {
@@ -35,6 +35,50 @@ impl LevelSummary {
         }
     }
 
+    pub fn level_id(&self) -> &str {
+        &self.level_id
+    }
+
+    pub fn level_name(&self) -> &str {
+        let level_name = self.settings.level_name();
+        if level_name.is_empty() {
+            &self.level_id
+        } else {
+            level_name
+        }
+    }
+
+    pub fn level_version(&self) -> &LevelVersion {
+        &self.level_version
+    }
+
+    /// When the level was last played, in milliseconds since the epoch
+    pub fn last_played(&self) -> i64 {
+        self.level_version.last_played()
+    }
+
+    /// Whether the level is open in a running instance
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    pub fn is_experimental(&self) -> bool {
+        self.experimental
+    }
+
+    pub fn icon(&self) -> &Path {
+        &self.icon
+    }
+
+    /// Whether the world was last played in an older version, so its data will be upgraded
+    pub fn requires_upgrade(&self) -> bool {
+        self.level_version.minecraft_version.version < shared_constants::get_current_data_version()
+    }
+
+    pub fn is_downgrade(&self) -> bool {
+        self.level_version.minecraft_version.version > shared_constants::get_current_data_version()
+    }
+
     pub fn is_compatible(&self) -> bool {
         shared_constants::WORLD_VERSION
             .world_version
@@ -1,5 +1,3 @@
-use crate::nbt::compound_tag::CompoundTag;
-use crate::nbt::tag_type::TagType;
 use crate::shared_constants;
 use crate::util::datafix::serialization::dynamic::Dynamic;
 use crate::world::level::storage::data_version;
@@ -15,27 +13,41 @@ pub struct LevelVersion {
     snapshot: bool,
 }
 impl LevelVersion {
-    pub fn parse(dynamic: &Dynamic<CompoundTag>) -> LevelVersion {
-        let version = dynamic.value.get_int("version");
-        let last_played = dynamic.value.get_long("LastPlayed");
-        if dynamic.value.contains("Version", TagType::CompoundTag) {
+    /// When the level was last played, in milliseconds since the epoch
+    pub fn last_played(&self) -> i64 {
+        self.last_played
+    }
+
+    pub fn minecraft_version_name(&self) -> &str {
+        &self.minecraft_version_name
+    }
+
+    pub fn is_snapshot(&self) -> bool {
+        self.snapshot
+    }
+
+    pub fn parse<T: Clone>(dynamic: &Dynamic<T>) -> LevelVersion {
+        let version = dynamic.get("version").as_int(0);
+        let last_played = dynamic.get("LastPlayed").as_long(0);
+        let version_tag = dynamic.get("Version");
+        if version_tag.is_present() {
             LevelVersion {
                 level_data_version: version,
                 last_played,
-                minecraft_version_name: dynamic
-                    .value
-                    .get_string_or_default("Name", shared_constants::WORLD_VERSION.name.clone()),
+                minecraft_version_name: version_tag
+                    .get("Name")
+                    .as_string_or(&shared_constants::WORLD_VERSION.name),
                 minecraft_version: DataVersion {
-                    version: dynamic
-                        .value
-                        .get_int_or_default("Id", shared_constants::get_current_data_version()),
-                    series: dynamic
-                        .value
-                        .get_string_or_default("Series", data_version::main_series()),
+                    version: version_tag
+                        .get("Id")
+                        .as_int(shared_constants::get_current_data_version()),
+                    series: version_tag
+                        .get("Series")
+                        .as_string_or(&data_version::main_series()),
                 },
-                snapshot: dynamic
-                    .value
-                    .get_bool_or_default("Snapshot", !shared_constants::WORLD_VERSION.stable),
+                snapshot: version_tag
+                    .get("Snapshot")
+                    .as_bool(!shared_constants::WORLD_VERSION.stable),
             }
         } else {
             LevelVersion {
@@ -1,5 +1,12 @@
 pub mod data_version;
+pub mod dimension_data_storage;
+pub mod io_worker;
 pub mod level_resource;
 pub mod level_storage_source;
 pub mod level_summary;
 mod level_version;
+pub mod player_data_storage;
+pub mod region_bitmap;
+pub mod region_file;
+pub mod region_file_storage;
+pub mod region_file_version;
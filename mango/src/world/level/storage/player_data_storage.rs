@@ -0,0 +1,205 @@
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::nbt_accounter::NbtAccounter;
+use crate::nbt::tag::Tag;
+use crate::nbt::{nbt_io, nbt_ops, nbt_utils};
+use crate::util::datafix::data_fix_types::DataFixTypes;
+use crate::util::datafix::data_fixers::DataFixer;
+use crate::util::datafix::serialization::dynamic::Dynamic;
+use crate::world::level::storage::level_resource::LevelResource;
+use crate::world::level::storage::level_storage_source::{
+    LevelStorageAccess, BACKUP_TIMESTAMP_FORMAT,
+};
+use anyhow::{anyhow, Result};
+use jiff::Zoned;
+use regex::Regex;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
+use tracing::warn;
+
+const DATA_SUFFIX: &str = ".dat";
+const OLD_DATA_SUFFIX: &str = ".dat_old";
+static UUID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}$")
+        .unwrap()
+});
+
+/// Per-player data stored in `playerdata/<uuid>.dat`. Players are identified by their UUID in its
+/// hyphenated string form, and anything else is rejected so it can't name a file outside the
+/// directory.
+pub struct PlayerDataStorage {
+    player_dir: PathBuf,
+    fixer_upper: Arc<DataFixer>,
+}
+impl PlayerDataStorage {
+    pub fn new(
+        level_storage_access: &mut LevelStorageAccess,
+        fixer_upper: Arc<DataFixer>,
+    ) -> Result<Self> {
+        let player_dir = level_storage_access.get_level_path(LevelResource::PlayerDataDir);
+        std::fs::create_dir_all(&player_dir).map_err(|e| {
+            anyhow!(
+                "Failed to create player data directory {:?}: {}",
+                player_dir,
+                e
+            )
+        })?;
+        Ok(Self {
+            player_dir,
+            fixer_upper,
+        })
+    }
+
+    /// Writes a player's data to a temporary file, then swaps it in, keeping the previous data as
+    /// `<uuid>.dat_old`
+    pub async fn save(&self, uuid: &str, player_tag: &CompoundTag) -> Result<()> {
+        check_uuid(uuid)?;
+        let mut tag = player_tag.clone();
+        nbt_utils::add_current_data_version(&mut tag);
+        nbt_io::write_compressed_atomically(
+            &tag,
+            &self.player_file(uuid, DATA_SUFFIX),
+            &self.player_file(uuid, OLD_DATA_SUFFIX),
+        )
+        .await
+        .inspect_err(|e| warn!(?e, "Failed to save player data for {}", uuid))
+    }
+
+    /// Loads a player's data fixed to the current version, or `None` if the player has never
+    /// joined. If `<uuid>.dat` can't be read, a copy of it is kept and `<uuid>.dat_old` is loaded
+    /// instead.
+    pub async fn load(&self, uuid: &str) -> Result<Option<CompoundTag>> {
+        check_uuid(uuid)?;
+        let mut tag = self.load_file(uuid, DATA_SUFFIX).await;
+        if tag.is_none() {
+            self.backup_corrupted(uuid).await;
+            tag = self.load_file(uuid, OLD_DATA_SUFFIX).await;
+        }
+        let Some(tag) = tag else {
+            return Ok(None);
+        };
+        // Player files from before data versions were added to them
+        let data_version = nbt_utils::get_data_version(&tag, -1);
+        DataFixTypes::Player
+            .update_to_current_version(
+                self.fixer_upper.as_ref(),
+                Dynamic::new(nbt_ops::INSTANCE.clone(), Tag::CompoundTag(tag)),
+                data_version,
+            )
+            .into_value()
+            .try_as_compound_tag()
+            .map(Some)
+            .ok_or_else(|| anyhow!("Fixed player data for {} is not a compound", uuid))
+    }
+
+    /// Loads a player's data, falling back to `level_player_tag` if the player has none. The
+    /// host of a singleplayer world has their data in level.dat's `Player` instead, so converted
+    /// worlds pass that for the host. It is already fixed when level.dat is read.
+    pub async fn load_or_level_player(
+        &self,
+        uuid: &str,
+        level_player_tag: Option<&CompoundTag>,
+    ) -> Result<Option<CompoundTag>> {
+        match self.load(uuid).await? {
+            Some(tag) => Ok(Some(tag)),
+            None => Ok(level_player_tag.cloned()),
+        }
+    }
+
+    /// UUIDs of every player with saved data
+    pub fn get_seen_players(&self) -> Result<Vec<String>> {
+        let mut players = Vec::new();
+        for entry in std::fs::read_dir(&self.player_dir)? {
+            let file_name = entry?.file_name();
+            let Some(uuid) = file_name.to_str().and_then(|e| e.strip_suffix(DATA_SUFFIX)) else {
+                continue;
+            };
+            if UUID.is_match(uuid) {
+                players.push(uuid.to_string());
+            }
+        }
+        players.sort();
+        Ok(players)
+    }
+
+    fn player_file(&self, uuid: &str, suffix: &str) -> PathBuf {
+        self.player_dir.join(format!("{}{}", uuid, suffix))
+    }
+
+    /// Reads a player file, or `None` if it doesn't exist or can't be read
+    async fn load_file(&self, uuid: &str, suffix: &str) -> Option<CompoundTag> {
+        let path = self.player_file(uuid, suffix);
+        if !path.is_file() {
+            return None;
+        }
+        nbt_io::read_compressed(path, NbtAccounter::unlimited_heap())
+            .await
+            .inspect_err(|e| warn!(?e, "Failed to load player data for {}", uuid))
+            .ok()
+    }
+
+    /// Copies `<uuid>.dat` aside so it isn't lost when the player is next saved
+    async fn backup_corrupted(&self, uuid: &str) {
+        let path = self.player_file(uuid, DATA_SUFFIX);
+        if !path.is_file() {
+            return;
+        }
+        let backup = self.player_file(
+            uuid,
+            &format!(
+                "_corrupted_{}{}",
+                Zoned::now().strftime(BACKUP_TIMESTAMP_FORMAT),
+                DATA_SUFFIX
+            ),
+        );
+        if let Err(e) = tokio::fs::copy(&path, &backup).await {
+            warn!(?e, "Failed to copy the player data file for {}", uuid);
+        }
+    }
+}
+
+fn check_uuid(uuid: &str) -> Result<()> {
+    if UUID.is_match(uuid) {
+        Ok(())
+    } else {
+        Err(anyhow!("Invalid player UUID '{}'", uuid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::datafix::data_fixers;
+    use tempfile::TempDir;
+
+    const PLAYER: &str = "00000000-0000-0000-0000-000000000001";
+
+    #[tokio::test]
+    async fn players_are_saved_by_uuid() {
+        let temp_dir = TempDir::new().unwrap();
+        let level_path = temp_dir.path().join("world");
+        let mut access = LevelStorageAccess::new(
+            "world".to_string(),
+            level_path.clone(),
+            level_path.join("backups"),
+        )
+        .unwrap();
+        let storage = PlayerDataStorage::new(&mut access, data_fixers::get_data_fixer()).unwrap();
+        let mut player_tag = CompoundTag::default();
+        player_tag.put("XpLevel", Tag::IntTag(7));
+
+        storage.save(PLAYER, &player_tag).await.unwrap();
+        let loaded = storage.load(PLAYER).await.unwrap().unwrap();
+        assert_eq!(loaded.get_int("XpLevel"), 7);
+        assert_eq!(
+            storage.get_seen_players().unwrap(),
+            vec![PLAYER.to_string()]
+        );
+
+        for uuid in ["../level", "player", ""] {
+            assert!(storage.save(uuid, &player_tag).await.is_err());
+            assert!(storage.load(uuid).await.is_err());
+            assert!(storage.load_or_level_player(uuid, None).await.is_err());
+        }
+        assert!(!level_path.join("level.dat").exists());
+    }
+}
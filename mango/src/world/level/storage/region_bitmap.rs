@@ -0,0 +1,43 @@
+/// Which sectors of a region file are in use, so chunks can be placed in free space
+#[derive(Debug, Default)]
+pub struct RegionBitmap {
+    used: Vec<bool>,
+}
+impl RegionBitmap {
+    /// Marks sectors as used
+    pub fn force(&mut self, start: usize, count: usize) {
+        if self.used.len() < start + count {
+            self.used.resize(start + count, false);
+        }
+        self.used[start..start + count].fill(true);
+    }
+
+    pub fn free(&mut self, start: usize, count: usize) {
+        let end = (start + count).min(self.used.len());
+        if start < end {
+            self.used[start..end].fill(false);
+        }
+    }
+
+    pub fn is_free(&self, start: usize, count: usize) -> bool {
+        (start..start + count).all(|i| !self.used.get(i).copied().unwrap_or(false))
+    }
+
+    /// Marks the first run of `count` free sectors as used, returning where it starts
+    pub fn allocate(&mut self, count: usize) -> usize {
+        let mut start = 0;
+        loop {
+            let free_start = (start..)
+                .find(|&i| !self.used.get(i).copied().unwrap_or(false))
+                .unwrap_or(start);
+            let free_end = (free_start..self.used.len()).find(|&i| self.used[i]);
+            match free_end {
+                Some(free_end) if free_end - free_start < count => start = free_end,
+                _ => {
+                    self.force(free_start, count);
+                    return free_start;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,539 @@
+use crate::world::level::chunk_pos::ChunkPos;
+use crate::world::level::storage::region_bitmap::RegionBitmap;
+use crate::world::level::storage::region_file_version::RegionFileVersion;
+use anyhow::{anyhow, Result};
+use jiff::Timestamp;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tracing::{error, warn};
+
+const SECTOR_BYTES: usize = 4096;
+/// Number of chunks in a region, each with an offset and a timestamp in the header
+const SECTOR_INTS: usize = 1024;
+const HEADER_BYTES: usize = SECTOR_BYTES * 2;
+/// Length and compression of the chunk in front of its data
+const CHUNK_HEADER_SIZE: usize = 5;
+/// Set on the compression of chunks too large for the region file, which are stored in a file
+/// of their own next to it
+const EXTERNAL_STREAM_FLAG: u8 = 128;
+const EXTERNAL_FILE_EXTENSION: &str = ".mcc";
+/// Sectors a chunk can take up, as the count is stored in a single byte of its offset
+const MAX_SECTORS: usize = 256;
+
+/// An Anvil region file (.mca), storing the 32x32 chunks of a region.
+///
+/// The file starts with a header of the offset of each chunk in 4 KiB sectors along with the
+/// number of sectors it takes up, followed by the time it was last saved. Each chunk's data is
+/// prefixed with its length and compression.
+pub struct RegionFile {
+    path: PathBuf,
+    file: File,
+    /// Where chunks too large for the region file are stored
+    external_file_dir: PathBuf,
+    version: RegionFileVersion,
+    offsets: Vec<u32>,
+    timestamps: Vec<u32>,
+    used_sectors: RegionBitmap,
+    /// Whether writes are flushed to disk before returning
+    sync: bool,
+}
+impl RegionFile {
+    /// Opens a region file, creating it if it doesn't exist. Chunks with invalid offsets in the
+    /// header are dropped so they can't corrupt other chunks.
+    pub async fn open(
+        path: PathBuf,
+        external_file_dir: PathBuf,
+        version: RegionFileVersion,
+        sync: bool,
+    ) -> Result<Self> {
+        if !external_file_dir.is_dir() {
+            return Err(anyhow!("Expected directory, got {:?}", external_file_dir));
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .await?;
+        let mut header = vec![0; HEADER_BYTES];
+        let header_read = read_fully(&mut file, &mut header).await?;
+        if header_read != 0 && header_read != HEADER_BYTES {
+            warn!(
+                "Region file {:?} has truncated header: {}",
+                path, header_read
+            );
+        }
+        let read_int = |i: usize| {
+            u32::from_be_bytes([
+                header[i * 4],
+                header[i * 4 + 1],
+                header[i * 4 + 2],
+                header[i * 4 + 3],
+            ])
+        };
+        let mut region_file = Self {
+            offsets: (0..SECTOR_INTS).map(read_int).collect(),
+            timestamps: (SECTOR_INTS..SECTOR_INTS * 2).map(read_int).collect(),
+            path,
+            file,
+            external_file_dir,
+            version,
+            used_sectors: RegionBitmap::default(),
+            sync,
+        };
+        region_file.used_sectors.force(0, 2);
+        region_file.validate_offsets().await?;
+        Ok(region_file)
+    }
+
+    async fn validate_offsets(&mut self) -> Result<()> {
+        let file_size = self.file.metadata().await?.len();
+        for i in 0..SECTOR_INTS {
+            let offset = self.offsets[i];
+            if offset == 0 {
+                continue;
+            }
+            let sector_number = get_sector_number(offset);
+            let num_sectors = get_num_sectors(offset);
+            if sector_number < 2 {
+                warn!(
+                    "Region file {:?} has invalid sector at index: {}; sector {} overlaps with header",
+                    self.path, i, sector_number
+                );
+            } else if num_sectors == 0 {
+                warn!(
+                    "Region file {:?} has an invalid sector at index: {}; size has to be > 0",
+                    self.path, i
+                );
+            } else if (sector_number * SECTOR_BYTES) as u64 > file_size {
+                warn!(
+                    "Region file {:?} has an invalid sector at index: {}; sector {} is out of bounds",
+                    self.path, i, sector_number
+                );
+            } else if !self.used_sectors.is_free(sector_number, num_sectors) {
+                warn!(
+                    "Region file {:?} has an invalid sector at index: {}; sector {} overlaps with another chunk",
+                    self.path, i, sector_number
+                );
+            } else {
+                self.used_sectors.force(sector_number, num_sectors);
+                continue;
+            }
+            self.offsets[i] = 0;
+        }
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn has_chunk(&self, pos: ChunkPos) -> bool {
+        self.get_offset(pos) != 0
+    }
+
+    /// When the chunk was last saved, in seconds since the epoch
+    pub fn get_chunk_timestamp(&self, pos: ChunkPos) -> u32 {
+        self.timestamps[get_offset_index(pos)]
+    }
+
+    fn get_offset(&self, pos: ChunkPos) -> u32 {
+        self.offsets[get_offset_index(pos)]
+    }
+
+    fn get_external_chunk_path(&self, pos: ChunkPos) -> PathBuf {
+        self.external_file_dir
+            .join(format!("c.{}.{}{}", pos.x, pos.z, EXTERNAL_FILE_EXTENSION))
+    }
+
+    /// Reads the decompressed data of a chunk. Returns none if the chunk doesn't exist, or its
+    /// data is missing or truncated.
+    pub async fn read(&mut self, pos: ChunkPos) -> Result<Option<Vec<u8>>> {
+        let offset = self.get_offset(pos);
+        if offset == 0 {
+            return Ok(None);
+        }
+        let sector_number = get_sector_number(offset);
+        let byte_count = get_num_sectors(offset) * SECTOR_BYTES;
+        let mut buffer = vec![0; byte_count];
+        self.file
+            .seek(SeekFrom::Start((sector_number * SECTOR_BYTES) as u64))
+            .await?;
+        let read = read_fully(&mut self.file, &mut buffer).await?;
+        if read < CHUNK_HEADER_SIZE {
+            error!(
+                "Chunk {} header is truncated: expected {} but read {}",
+                pos, byte_count, read
+            );
+            return Ok(None);
+        }
+        let length = i32::from_be_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]);
+        let version = buffer[4];
+        if length == 0 {
+            warn!("Chunk {} is allocated, but stream is missing", pos);
+            return Ok(None);
+        }
+        let payload_length = length as i64 - 1;
+        if is_external_stream_chunk(version) {
+            if payload_length != 0 {
+                warn!("Chunk has both internal and external streams");
+            }
+            return self
+                .read_external(pos, get_external_chunk_version(version))
+                .await;
+        }
+        let remaining = (read - CHUNK_HEADER_SIZE) as i64;
+        if payload_length > remaining {
+            error!(
+                "Chunk {} stream is truncated: expected {} but read {}",
+                pos, payload_length, remaining
+            );
+            return Ok(None);
+        }
+        if payload_length < 0 {
+            error!("Declared size {} of chunk {} is negative", length, pos);
+            return Ok(None);
+        }
+        let payload = &buffer[CHUNK_HEADER_SIZE..CHUNK_HEADER_SIZE + payload_length as usize];
+        decompress(pos, version, payload).await
+    }
+
+    async fn read_external(&self, pos: ChunkPos, version: u8) -> Result<Option<Vec<u8>>> {
+        let path = self.get_external_chunk_path(pos);
+        if !path.is_file() {
+            error!("External chunk path {:?} is not file", path);
+            return Ok(None);
+        }
+        decompress(pos, version, &tokio::fs::read(&path).await?).await
+    }
+
+    /// Compresses and writes the data of a chunk, freeing the space it took up before. If writing
+    /// fails, the chunk is left as it was.
+    pub async fn write(&mut self, pos: ChunkPos, data: &[u8]) -> Result<()> {
+        let compressed = self.version.compress(data).await?;
+        let mut buffer = Vec::with_capacity(CHUNK_HEADER_SIZE + compressed.len());
+        buffer.extend_from_slice(&(compressed.len() as u32 + 1).to_be_bytes());
+        buffer.push(self.version.id());
+        buffer.extend_from_slice(&compressed);
+
+        let index = get_offset_index(pos);
+        let old_offset = self.offsets[index];
+        let old_timestamp = self.timestamps[index];
+        let external_path = self.get_external_chunk_path(pos);
+        let temp_file = if size_to_sectors(buffer.len()) >= MAX_SECTORS {
+            // Only a stub pointing to the external file is kept in the region file. The external
+            // file is only moved in place once the header points to it.
+            buffer = 1u32.to_be_bytes().to_vec();
+            buffer.push(self.version.id() | EXTERNAL_STREAM_FLAG);
+            Some(self.external_file_dir.join(format!(
+                "c.{}.{}{}.tmp",
+                pos.x, pos.z, EXTERNAL_FILE_EXTENSION
+            )))
+        } else {
+            None
+        };
+        let num_sectors = size_to_sectors(buffer.len());
+        let sector_number = self.used_sectors.allocate(num_sectors);
+
+        let written = async {
+            if let Some(temp_file) = &temp_file {
+                tokio::fs::write(temp_file, &compressed).await?;
+            }
+            self.write_sectors(sector_number, buffer).await?;
+            self.offsets[index] = pack_sector_offset(sector_number, num_sectors);
+            self.timestamps[index] = get_timestamp();
+            self.write_header().await
+        }
+        .await;
+        if let Err(e) = written {
+            self.offsets[index] = old_offset;
+            self.timestamps[index] = old_timestamp;
+            self.used_sectors.free(sector_number, num_sectors);
+            if let Some(temp_file) = &temp_file {
+                let _ = tokio::fs::remove_file(temp_file).await;
+            }
+            return Err(e);
+        }
+
+        match temp_file {
+            Some(temp_file) => tokio::fs::rename(&temp_file, &external_path).await?,
+            None => remove_if_exists(&external_path).await?,
+        }
+        if old_offset != 0 {
+            self.used_sectors
+                .free(get_sector_number(old_offset), get_num_sectors(old_offset));
+        }
+        Ok(())
+    }
+
+    /// Removes a chunk
+    pub async fn clear(&mut self, pos: ChunkPos) -> Result<()> {
+        let index = get_offset_index(pos);
+        let offset = self.offsets[index];
+        if offset == 0 {
+            return Ok(());
+        }
+        self.offsets[index] = 0;
+        self.timestamps[index] = 0;
+        self.write_header().await?;
+        remove_if_exists(&self.get_external_chunk_path(pos)).await?;
+        self.used_sectors
+            .free(get_sector_number(offset), get_num_sectors(offset));
+        Ok(())
+    }
+
+    /// Writes data padded to whole sectors, so the file always ends on a sector boundary
+    async fn write_sectors(&mut self, sector_number: usize, mut data: Vec<u8>) -> Result<()> {
+        data.resize(size_to_sectors(data.len()) * SECTOR_BYTES, 0);
+        self.file
+            .seek(SeekFrom::Start((sector_number * SECTOR_BYTES) as u64))
+            .await?;
+        self.file.write_all(&data).await?;
+        if self.sync {
+            self.file.sync_data().await?;
+        }
+        Ok(())
+    }
+
+    async fn write_header(&mut self) -> Result<()> {
+        let mut header = Vec::with_capacity(HEADER_BYTES);
+        for e in self.offsets.iter().chain(self.timestamps.iter()) {
+            header.extend_from_slice(&e.to_be_bytes());
+        }
+        self.file.seek(SeekFrom::Start(0)).await?;
+        self.file.write_all(&header).await?;
+        if self.sync {
+            self.file.sync_data().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> Result<()> {
+        self.file.flush().await?;
+        self.file.sync_all().await?;
+        Ok(())
+    }
+}
+
+async fn decompress(pos: ChunkPos, version: u8, data: &[u8]) -> Result<Option<Vec<u8>>> {
+    match RegionFileVersion::from_id(version) {
+        None => {
+            error!("Chunk {} has invalid chunk stream version {}", pos, version);
+            Ok(None)
+        }
+        Some(RegionFileVersion::Custom) => {
+            // Custom compression is named by a string in front of the data
+            let name = data
+                .get(..2)
+                .and_then(|len| data.get(2..2 + u16::from_be_bytes([len[0], len[1]]) as usize))
+                .map(String::from_utf8_lossy)
+                .unwrap_or_default();
+            error!("Unrecognized custom compression {}", name);
+            Ok(None)
+        }
+        Some(version) => Ok(Some(version.decompress(data).await?)),
+    }
+}
+
+/// Reads until the buffer is full or the end of the file, returning how much was read
+async fn read_fully(file: &mut File, buffer: &mut [u8]) -> Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match file.read(&mut buffer[read..]).await? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
+
+async fn remove_if_exists(path: &Path) -> Result<()> {
+    match tokio::fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+fn get_offset_index(pos: ChunkPos) -> usize {
+    (pos.get_region_local_x() + pos.get_region_local_z() * 32) as usize
+}
+
+fn get_sector_number(offset: u32) -> usize {
+    (offset >> 8) as usize
+}
+
+fn get_num_sectors(offset: u32) -> usize {
+    (offset & 0xFF) as usize
+}
+
+fn pack_sector_offset(sector_number: usize, num_sectors: usize) -> u32 {
+    ((sector_number as u32) << 8) | num_sectors as u32
+}
+
+fn size_to_sectors(size: usize) -> usize {
+    size.div_ceil(SECTOR_BYTES)
+}
+
+fn is_external_stream_chunk(version: u8) -> bool {
+    version & EXTERNAL_STREAM_FLAG != 0
+}
+
+fn get_external_chunk_version(version: u8) -> u8 {
+    version & !EXTERNAL_STREAM_FLAG
+}
+
+fn get_timestamp() -> u32 {
+    Timestamp::now().as_second() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn open(dir: &TempDir, version: RegionFileVersion) -> RegionFile {
+        RegionFile::open(
+            dir.path().join("r.0.0.mca"),
+            dir.path().to_path_buf(),
+            version,
+            false,
+        )
+        .await
+        .unwrap()
+    }
+
+    /// Data that doesn't compress, so it takes up as many sectors as its length
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545f491u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn invalid_header_offsets_are_dropped() {
+        let dir = TempDir::new().unwrap();
+        let mut region_file = open(&dir, RegionFileVersion::Deflate).await;
+        region_file
+            .write(ChunkPos::new(0, 0), b"chunk")
+            .await
+            .unwrap();
+        let valid = region_file.offsets[0];
+        drop(region_file);
+
+        // Overlapping another chunk, overlapping the header, empty and past the end of the file
+        let invalid = [
+            valid,
+            pack_sector_offset(1, 1),
+            pack_sector_offset(2, 0),
+            pack_sector_offset(1000, 1),
+        ];
+        let mut header = std::fs::read(dir.path().join("r.0.0.mca")).unwrap();
+        for (i, offset) in invalid.iter().enumerate() {
+            header[(i + 1) * 4..(i + 2) * 4].copy_from_slice(&offset.to_be_bytes());
+        }
+        std::fs::write(dir.path().join("r.0.0.mca"), header).unwrap();
+
+        let mut region_file = open(&dir, RegionFileVersion::Deflate).await;
+        assert!(region_file.has_chunk(ChunkPos::new(0, 0)));
+        for x in 1..=4 {
+            assert!(!region_file.has_chunk(ChunkPos::new(x, 0)));
+        }
+        // The valid chunk's sectors are still reserved, so new chunks don't overwrite it
+        region_file
+            .write(ChunkPos::new(5, 0), b"other")
+            .await
+            .unwrap();
+        assert_eq!(
+            region_file
+                .read(ChunkPos::new(0, 0))
+                .await
+                .unwrap()
+                .unwrap(),
+            b"chunk"
+        );
+    }
+
+    #[tokio::test]
+    async fn lz4_chunks_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let data = [noise(3000), vec![7; 5000]].concat();
+        let mut region_file = open(&dir, RegionFileVersion::Lz4).await;
+        region_file.write(ChunkPos::new(1, 2), &data).await.unwrap();
+        assert_eq!(
+            region_file
+                .read(ChunkPos::new(1, 2))
+                .await
+                .unwrap()
+                .unwrap(),
+            data
+        );
+        drop(region_file);
+
+        // Chunks are read with the compression they were written with
+        let mut region_file = open(&dir, RegionFileVersion::Deflate).await;
+        assert_eq!(
+            region_file
+                .read(ChunkPos::new(1, 2))
+                .await
+                .unwrap()
+                .unwrap(),
+            data
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_chunks_are_stored_externally() {
+        let dir = TempDir::new().unwrap();
+        let pos = ChunkPos::new(3, 4);
+        let external_path = dir.path().join("c.3.4.mcc");
+        let data = noise(MAX_SECTORS * SECTOR_BYTES);
+        let mut region_file = open(&dir, RegionFileVersion::None).await;
+
+        region_file.write(pos, &data).await.unwrap();
+        assert_eq!(get_num_sectors(region_file.get_offset(pos)), 1);
+        assert_eq!(std::fs::read(&external_path).unwrap(), data);
+        assert_eq!(region_file.read(pos).await.unwrap().unwrap(), data);
+
+        region_file.write(pos, b"small").await.unwrap();
+        assert!(!external_path.exists());
+        assert_eq!(region_file.read(pos).await.unwrap().unwrap(), b"small");
+        // Only the region file is left, no temporary files
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn failed_writes_free_their_sectors() {
+        let dir = TempDir::new().unwrap();
+        let pos = ChunkPos::new(0, 0);
+        let mut region_file = open(&dir, RegionFileVersion::None).await;
+        region_file.write(pos, b"before").await.unwrap();
+        let offset = region_file.get_offset(pos);
+
+        // The external chunk can't be written while a directory is in the way
+        let temp_file = dir.path().join("c.0.0.mcc.tmp");
+        std::fs::create_dir(&temp_file).unwrap();
+        let data = noise(MAX_SECTORS * SECTOR_BYTES);
+        assert!(region_file.write(pos, &data).await.is_err());
+        assert_eq!(region_file.get_offset(pos), offset);
+        assert_eq!(region_file.read(pos).await.unwrap().unwrap(), b"before");
+
+        std::fs::remove_dir(&temp_file).unwrap();
+        region_file
+            .write(ChunkPos::new(1, 0), b"after")
+            .await
+            .unwrap();
+        assert_eq!(
+            get_sector_number(region_file.get_offset(ChunkPos::new(1, 0))),
+            get_sector_number(offset) + 1
+        );
+    }
+}
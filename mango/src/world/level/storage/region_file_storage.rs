@@ -0,0 +1,149 @@
+use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::nbt_accounter::NbtAccounter;
+use crate::nbt::nbt_io;
+use crate::world::level::chunk_pos::ChunkPos;
+use crate::world::level::storage::region_file::RegionFile;
+use crate::world::level::storage::region_file_version::RegionFileVersion;
+use anyhow::Result;
+use indexmap::IndexMap;
+use std::path::PathBuf;
+
+/// Region files kept open at once. The least recently used file is closed to open another.
+pub const MAX_CACHE_SIZE: usize = 256;
+
+pub const ANVIL_EXTENSION: &str = ".mca";
+
+/// Reads and writes chunks in the region files of a directory, keeping recently used files open
+pub struct RegionFileStorage {
+    folder: PathBuf,
+    /// Open region files by region position, from least to most recently used
+    region_cache: IndexMap<i64, RegionFile>,
+    sync: bool,
+}
+impl RegionFileStorage {
+    pub fn new(folder: PathBuf, sync: bool) -> Self {
+        Self {
+            folder,
+            region_cache: IndexMap::new(),
+            sync,
+        }
+    }
+
+    /// The region file a chunk is in, opening or creating it if needed
+    pub async fn get_region_file(&mut self, pos: ChunkPos) -> Result<&mut RegionFile> {
+        let key = ChunkPos::as_long(pos.get_region_x(), pos.get_region_z());
+        match self.region_cache.get_index_of(&key) {
+            Some(index) => self
+                .region_cache
+                .move_index(index, self.region_cache.len() - 1),
+            None => {
+                if self.region_cache.len() >= MAX_CACHE_SIZE {
+                    if let Some((_, mut region_file)) = self.region_cache.shift_remove_index(0) {
+                        region_file.flush().await?;
+                    }
+                }
+                tokio::fs::create_dir_all(&self.folder).await?;
+                let path = self.folder.join(format!(
+                    "r.{}.{}{}",
+                    pos.get_region_x(),
+                    pos.get_region_z(),
+                    ANVIL_EXTENSION
+                ));
+                let region_file = RegionFile::open(
+                    path,
+                    self.folder.clone(),
+                    RegionFileVersion::get_selected(),
+                    self.sync,
+                )
+                .await?;
+                self.region_cache.insert(key, region_file);
+            }
+        }
+        Ok(self
+            .region_cache
+            .last_mut()
+            .map(|(_, region_file)| region_file)
+            .expect("Region file was just cached"))
+    }
+
+    pub async fn read(&mut self, pos: ChunkPos) -> Result<Option<CompoundTag>> {
+        match self.get_region_file(pos).await?.read(pos).await? {
+            Some(data) => Ok(Some(nbt_io::read_sync(
+                data.as_slice(),
+                NbtAccounter::unlimited_heap(),
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Writes a chunk, or removes it if there is no data
+    pub async fn write(&mut self, pos: ChunkPos, tag: Option<&CompoundTag>) -> Result<()> {
+        let region_file = self.get_region_file(pos).await?;
+        match tag {
+            Some(tag) => {
+                let mut data = Vec::new();
+                nbt_io::write(tag, &mut data).await?;
+                region_file.write(pos, &data).await
+            }
+            None => region_file.clear(pos).await,
+        }
+    }
+
+    pub async fn flush(&mut self) -> Result<()> {
+        for region_file in self.region_cache.values_mut() {
+            region_file.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Flushes and closes every open region file
+    pub async fn close(mut self) -> Result<()> {
+        self.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nbt::tag::Tag;
+    use tempfile::TempDir;
+
+    fn region_key(region_x: i32) -> i64 {
+        ChunkPos::as_long(region_x, 0)
+    }
+
+    #[tokio::test]
+    async fn least_recently_used_region_file_is_closed() {
+        let dir = TempDir::new().unwrap();
+        let mut storage = RegionFileStorage::new(dir.path().to_path_buf(), false);
+        let mut chunk = CompoundTag::default();
+        chunk.put("Status", Tag::StringTag("minecraft:full".to_string()));
+        storage
+            .write(ChunkPos::new(32, 0), Some(&chunk))
+            .await
+            .unwrap();
+        for region_x in 0..MAX_CACHE_SIZE as i32 {
+            storage
+                .get_region_file(ChunkPos::new(region_x * 32, 0))
+                .await
+                .unwrap();
+        }
+        assert_eq!(storage.region_cache.len(), MAX_CACHE_SIZE);
+
+        // Using region 0 again makes region 1 the least recently used
+        storage.get_region_file(ChunkPos::new(0, 0)).await.unwrap();
+        storage
+            .get_region_file(ChunkPos::new(MAX_CACHE_SIZE as i32 * 32, 0))
+            .await
+            .unwrap();
+        assert_eq!(storage.region_cache.len(), MAX_CACHE_SIZE);
+        assert!(storage.region_cache.contains_key(&region_key(0)));
+        assert!(!storage.region_cache.contains_key(&region_key(1)));
+
+        // Closed region files are opened again when needed
+        let read = storage.read(ChunkPos::new(32, 0)).await.unwrap().unwrap();
+        assert_eq!(read.get_string("Status"), "minecraft:full");
+        assert!(storage.region_cache.contains_key(&region_key(1)));
+        assert!(!storage.region_cache.contains_key(&region_key(2)));
+    }
+}
@@ -0,0 +1,212 @@
+use anyhow::{anyhow, Result};
+use async_compression::tokio::bufread::{GzipDecoder, ZlibDecoder};
+use async_compression::tokio::write::{GzipEncoder, ZlibEncoder};
+use std::sync::atomic::{AtomicU8, Ordering};
+use strum::FromRepr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::error;
+use twox_hash::XxHash32;
+
+/// Compression of newly written chunks, set from server.properties
+static SELECTED_VERSION: AtomicU8 = AtomicU8::new(RegionFileVersion::Deflate as u8);
+
+/// Header of each block of an LZ4 stream, as written by lz4-java's `LZ4BlockOutputStream`
+const LZ4_MAGIC: &[u8; 8] = b"LZ4Block";
+const LZ4_HEADER_LENGTH: usize = LZ4_MAGIC.len() + 13;
+const LZ4_COMPRESSION_METHOD_RAW: u8 = 0x10;
+const LZ4_COMPRESSION_METHOD_LZ4: u8 = 0x20;
+const LZ4_COMPRESSION_LEVEL_BASE: u8 = 10;
+const LZ4_BLOCK_SIZE: usize = 1 << 16;
+const LZ4_CHECKSUM_SEED: u32 = 0x9747b28c;
+
+/// How a chunk is compressed in a region file, stored in front of each chunk
+#[derive(Copy, Clone, Debug, FromRepr, PartialEq, Eq)]
+#[repr(u8)]
+pub enum RegionFileVersion {
+    Gzip = 1,
+    Deflate = 2,
+    None = 3,
+    Lz4 = 4,
+    /// Compression added by mods, which is named after the version and can't be read
+    Custom = 127,
+}
+impl RegionFileVersion {
+    pub fn from_id(id: u8) -> Option<Self> {
+        Self::from_repr(id)
+    }
+
+    pub const fn id(&self) -> u8 {
+        *self as u8
+    }
+
+    /// Name in the `region-file-compression` property, if the version can be selected
+    pub const fn name(&self) -> Option<&'static str> {
+        match self {
+            RegionFileVersion::Deflate => Some("deflate"),
+            RegionFileVersion::None => Some("none"),
+            RegionFileVersion::Lz4 => Some("lz4"),
+            RegionFileVersion::Gzip | RegionFileVersion::Custom => None,
+        }
+    }
+
+    /// Selects the compression of newly written chunks by name, keeping the current one if the
+    /// name is unknown
+    pub fn configure(name: &str) {
+        let selectable = [
+            RegionFileVersion::Deflate,
+            RegionFileVersion::Lz4,
+            RegionFileVersion::None,
+        ];
+        match selectable.iter().find(|e| e.name() == Some(name)) {
+            Some(version) => SELECTED_VERSION.store(version.id(), Ordering::Relaxed),
+            None => error!(
+                "Invalid `region-file-compression` value `{}` in server.properties. Please use one of: {}",
+                name,
+                selectable.map(|e| e.name().unwrap_or_default()).join(", ")
+            ),
+        }
+    }
+
+    pub fn get_selected() -> Self {
+        Self::from_id(SELECTED_VERSION.load(Ordering::Relaxed)).unwrap_or(Self::Deflate)
+    }
+
+    pub async fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut res = Vec::new();
+        match self {
+            RegionFileVersion::Gzip => {
+                GzipDecoder::new(data).read_to_end(&mut res).await?;
+            }
+            RegionFileVersion::Deflate => {
+                ZlibDecoder::new(data).read_to_end(&mut res).await?;
+            }
+            RegionFileVersion::None => res.extend_from_slice(data),
+            RegionFileVersion::Lz4 => res = lz4_block_decompress(data)?,
+            RegionFileVersion::Custom => {
+                return Err(anyhow!("Custom compression is not supported"))
+            }
+        }
+        Ok(res)
+    }
+
+    pub async fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            RegionFileVersion::Gzip => {
+                let mut encoder = GzipEncoder::new(Vec::new());
+                encoder.write_all(data).await?;
+                encoder.shutdown().await?;
+                Ok(encoder.into_inner())
+            }
+            RegionFileVersion::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new());
+                encoder.write_all(data).await?;
+                encoder.shutdown().await?;
+                Ok(encoder.into_inner())
+            }
+            RegionFileVersion::None => Ok(data.to_vec()),
+            RegionFileVersion::Lz4 => Ok(lz4_block_compress(data)),
+            RegionFileVersion::Custom => Err(anyhow!("Custom compression is not supported")),
+        }
+    }
+}
+
+/// lz4-java's stream checksum, which drops the top 4 bits of the hash
+fn lz4_checksum(data: &[u8]) -> u32 {
+    XxHash32::oneshot(LZ4_CHECKSUM_SEED, data) & 0xFFFFFFF
+}
+
+fn write_lz4_block_header(
+    out: &mut Vec<u8>,
+    method: u8,
+    compressed_length: usize,
+    original_length: usize,
+    checksum: u32,
+) {
+    // Compression level of the block size, which readers use as the upper bound of a block
+    let compression_level = LZ4_BLOCK_SIZE.trailing_zeros() as u8 - LZ4_COMPRESSION_LEVEL_BASE;
+    out.extend_from_slice(LZ4_MAGIC);
+    out.push(method | compression_level);
+    out.extend_from_slice(&(compressed_length as u32).to_le_bytes());
+    out.extend_from_slice(&(original_length as u32).to_le_bytes());
+    out.extend_from_slice(&checksum.to_le_bytes());
+}
+
+/// Compresses into lz4-java's block stream format: blocks of up to 64 KiB, each stored raw if
+/// compression doesn't shrink it, followed by an empty block marking the end of the stream
+fn lz4_block_compress(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for block in data.chunks(LZ4_BLOCK_SIZE) {
+        let checksum = lz4_checksum(block);
+        let compressed = lz4_flex::block::compress(block);
+        if compressed.len() < block.len() {
+            let method = LZ4_COMPRESSION_METHOD_LZ4;
+            write_lz4_block_header(&mut out, method, compressed.len(), block.len(), checksum);
+            out.extend_from_slice(&compressed);
+        } else {
+            let method = LZ4_COMPRESSION_METHOD_RAW;
+            write_lz4_block_header(&mut out, method, block.len(), block.len(), checksum);
+            out.extend_from_slice(block);
+        }
+    }
+    write_lz4_block_header(&mut out, LZ4_COMPRESSION_METHOD_RAW, 0, 0, 0);
+    out
+}
+
+fn lz4_block_decompress(mut data: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    loop {
+        if data.len() < LZ4_HEADER_LENGTH {
+            // lz4-java also accepts streams that end without an end block
+            if data.is_empty() {
+                return Ok(out);
+            }
+            return Err(anyhow!("LZ4 stream ended in a block header"));
+        }
+        let (header, rest) = data.split_at(LZ4_HEADER_LENGTH);
+        if &header[..LZ4_MAGIC.len()] != LZ4_MAGIC {
+            return Err(anyhow!("LZ4 stream is corrupted: bad magic"));
+        }
+        let token = header[LZ4_MAGIC.len()];
+        let method = token & 0xF0;
+        let compression_level = LZ4_COMPRESSION_LEVEL_BASE + (token & 0x0F);
+        let read_int = |i: usize| {
+            let start = LZ4_MAGIC.len() + 1 + i * 4;
+            i32::from_le_bytes([
+                header[start],
+                header[start + 1],
+                header[start + 2],
+                header[start + 3],
+            ])
+        };
+        let (compressed_length, original_length, checksum) =
+            (read_int(0), read_int(1), read_int(2));
+        if original_length < 0
+            || compressed_length < 0
+            || original_length as u64 > 1 << compression_level
+            || (original_length == 0) != (compressed_length == 0)
+            || (method == LZ4_COMPRESSION_METHOD_RAW && original_length != compressed_length)
+            || (method != LZ4_COMPRESSION_METHOD_RAW && method != LZ4_COMPRESSION_METHOD_LZ4)
+        {
+            return Err(anyhow!("LZ4 stream is corrupted: bad block header"));
+        }
+        if original_length == 0 {
+            if checksum != 0 {
+                return Err(anyhow!("LZ4 stream is corrupted: bad end block"));
+            }
+            return Ok(out);
+        }
+        let (compressed, rest) = rest
+            .split_at_checked(compressed_length as usize)
+            .ok_or_else(|| anyhow!("LZ4 stream ended in a block"))?;
+        let block = if method == LZ4_COMPRESSION_METHOD_RAW {
+            compressed.to_vec()
+        } else {
+            lz4_flex::block::decompress(compressed, original_length as usize)?
+        };
+        if lz4_checksum(&block) != checksum as u32 {
+            return Err(anyhow!("LZ4 stream is corrupted: checksum mismatch"));
+        }
+        out.extend_from_slice(&block);
+        data = rest;
+    }
+}
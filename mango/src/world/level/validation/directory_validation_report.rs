@@ -0,0 +1,64 @@
+use crate::world::level::validation::forbidden_symlink_info::{
+    ForbiddenSymlinkInfo, SymlinkLoopInfo, UnreadableSymlinkInfo,
+};
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// Everything wrong with a directory found by
+/// [DirectoryValidator](super::directory_validator::DirectoryValidator). The directory is valid
+/// if the report is empty.
+#[derive(Debug, Default)]
+pub struct DirectoryValidationReport {
+    pub path: PathBuf,
+    pub forbidden_symlinks: Vec<ForbiddenSymlinkInfo>,
+    pub unreadable_symlinks: Vec<UnreadableSymlinkInfo>,
+    /// Set if the path is a file instead of a directory
+    pub not_a_directory: bool,
+    pub symlink_loops: Vec<SymlinkLoopInfo>,
+}
+impl DirectoryValidationReport {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.forbidden_symlinks.is_empty()
+            && self.unreadable_symlinks.is_empty()
+            && !self.not_a_directory
+            && self.symlink_loops.is_empty()
+    }
+}
+impl Display for DirectoryValidationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to validate {:?}:", self.path)?;
+        if self.not_a_directory {
+            write!(f, "\n  - Path is not a directory")?;
+        }
+        for info in &self.forbidden_symlinks {
+            write!(
+                f,
+                "\n  - Symbolic link {:?} points to {:?}, which is not allowed by allowed_symlinks.txt",
+                info.link, info.target
+            )?;
+        }
+        for info in &self.unreadable_symlinks {
+            write!(
+                f,
+                "\n  - Failed to read symbolic link {:?}: {}",
+                info.link, info.error
+            )?;
+        }
+        for info in &self.symlink_loops {
+            write!(
+                f,
+                "\n  - Symbolic link {:?} loops back to {:?}",
+                info.link, info.ancestor
+            )?;
+        }
+        Ok(())
+    }
+}
+impl std::error::Error for DirectoryValidationReport {}
@@ -1,24 +1,34 @@
-use crate::world::level::validation::forbidden_symlink_info::ForbiddenSymlinkInfo;
+use crate::world::level::validation::directory_validation_report::DirectoryValidationReport;
+use crate::world::level::validation::forbidden_symlink_info::{
+    ForbiddenSymlinkInfo, SymlinkLoopInfo, UnreadableSymlinkInfo,
+};
+use crate::world::level::validation::path_allow_list::PathAllowList;
 use std::path::PathBuf;
 use walkdir::WalkDir;
 
-type PathMatcher = fn(&PathBuf) -> bool;
-
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct DirectoryValidator {
-    symlink_target_allowlist: PathMatcher,
+    symlink_target_allowlist: PathAllowList,
 }
 impl DirectoryValidator {
-    pub fn new(symlink_target_allowlist: PathMatcher) -> Self {
+    pub fn new(symlink_target_allowlist: PathAllowList) -> Self {
         Self {
             symlink_target_allowlist,
         }
     }
 
-    pub fn validate_symlink(&self, path: PathBuf, errors: &mut Vec<ForbiddenSymlinkInfo>) {
-        let target = std::fs::read_link(&path).expect("Failed to read symlink target");
-        if !(self.symlink_target_allowlist)(&target) {
-            errors.push(ForbiddenSymlinkInfo { link: path, target });
+    pub fn validate_symlink(&self, path: PathBuf, report: &mut DirectoryValidationReport) {
+        match std::fs::read_link(&path) {
+            Ok(target) => {
+                if !self.symlink_target_allowlist.matches(&target) {
+                    report
+                        .forbidden_symlinks
+                        .push(ForbiddenSymlinkInfo { link: path, target });
+                }
+            }
+            Err(error) => report
+                .unreadable_symlinks
+                .push(UnreadableSymlinkInfo { link: path, error }),
         }
     }
 
@@ -26,40 +36,63 @@ impl DirectoryValidator {
         &self,
         mut path: PathBuf,
         skip_symlink_validation: bool,
-    ) -> Vec<ForbiddenSymlinkInfo> {
+    ) -> DirectoryValidationReport {
+        let mut report = DirectoryValidationReport::new(path.clone());
         // Get file metadata without following symlinks
         let metadata = match std::fs::symlink_metadata(&path) {
             Ok(m) => m,
-            Err(_e) => return Vec::new(),
+            Err(_e) => return report,
         };
 
         if metadata.is_file() {
-            panic!("Path {:?} is not a directory", path);
+            report.not_a_directory = true;
         } else {
-            let mut res = Vec::new();
             if metadata.is_symlink() {
                 if !skip_symlink_validation {
-                    self.validate_symlink(path, &mut res);
-                    return res;
+                    self.validate_symlink(path, &mut report);
+                    return report;
                 }
 
-                let symlink_target =
-                    std::fs::read_link(&path).expect("Failed to read symlink target");
-                let _ = std::mem::replace(&mut path, symlink_target);
+                match std::fs::read_link(&path) {
+                    Ok(symlink_target) => path = symlink_target,
+                    Err(error) => {
+                        report
+                            .unreadable_symlinks
+                            .push(UnreadableSymlinkInfo { link: path, error });
+                        return report;
+                    }
+                }
             }
 
-            self.validate_known_directory(path, &mut res);
-            res
+            self.validate_known_directory(path, &mut report);
         }
+        report
     }
 
     /// Verifies all nested symlinks
-    pub fn validate_known_directory(&self, path: PathBuf, errors: &mut Vec<ForbiddenSymlinkInfo>) {
-        WalkDir::new(path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_symlink())
-            .for_each(|e| self.validate_symlink(e.path().to_path_buf(), errors));
+    pub fn validate_known_directory(&self, path: PathBuf, report: &mut DirectoryValidationReport) {
+        for entry in WalkDir::new(path).follow_links(true) {
+            match entry {
+                Ok(entry) => {
+                    if entry.path_is_symlink() {
+                        self.validate_symlink(entry.into_path(), report);
+                    }
+                }
+                Err(e) => {
+                    let Some(link) = e.path().map(|e| e.to_path_buf()) else {
+                        continue;
+                    };
+                    if let Some(ancestor) = e.loop_ancestor() {
+                        report.symlink_loops.push(SymlinkLoopInfo {
+                            link,
+                            ancestor: ancestor.to_path_buf(),
+                        });
+                    } else if link.is_symlink() {
+                        // Links to missing targets can't be followed, but are still checked
+                        self.validate_symlink(link, report);
+                    }
+                }
+            }
+        }
     }
 }
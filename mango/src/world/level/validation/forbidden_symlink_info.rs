@@ -5,3 +5,17 @@ pub struct ForbiddenSymlinkInfo {
     pub link: PathBuf,
     pub target: PathBuf,
 }
+
+/// A symlink whose target couldn't be read, so it can't be checked
+#[derive(Debug)]
+pub struct UnreadableSymlinkInfo {
+    pub link: PathBuf,
+    pub error: std::io::Error,
+}
+
+/// A symlink pointing to one of its own parent directories
+#[derive(Debug)]
+pub struct SymlinkLoopInfo {
+    pub link: PathBuf,
+    pub ancestor: PathBuf,
+}
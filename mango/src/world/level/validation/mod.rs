@@ -1,2 +1,4 @@
+pub mod directory_validation_report;
 pub mod directory_validator;
 pub mod forbidden_symlink_info;
+pub mod path_allow_list;
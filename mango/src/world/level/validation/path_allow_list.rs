@@ -0,0 +1,233 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use std::io::BufRead;
+use std::path::Path;
+
+const COMMENT_PREFIX: &str = "#";
+
+/// How an entry matches paths
+#[derive(Clone, Debug)]
+enum EntryType {
+    /// Whole path matches a regex, compiled from a glob or regex pattern
+    FileSystem(Regex),
+    Prefix(String),
+}
+
+/// Paths that are allowed, as configured in allowed_symlinks.txt.
+///
+/// Each line is a pattern, optionally prefixed with its type as `[type]` or `type:`:
+/// - `glob`: a glob matched against the whole path, e.g. `[glob]/mnt/shared/*`
+/// - `regex`: a regex matched against the whole path
+/// - `prefix`: a string the path starts with, which is the default when no type is given
+///
+/// Blank lines and lines starting with `#` are ignored.
+#[derive(Clone, Debug, Default)]
+pub struct PathAllowList {
+    entries: Vec<EntryType>,
+}
+impl PathAllowList {
+    pub fn read_plain(reader: impl BufRead) -> Result<Self> {
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            if let Some(entry) = parse_entry(&line?)? {
+                entries.push(entry);
+            }
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let path = path.to_string_lossy();
+        self.entries.iter().any(|entry| match entry {
+            EntryType::FileSystem(regex) => regex.is_match(&path),
+            EntryType::Prefix(prefix) => path.starts_with(prefix.as_str()),
+        })
+    }
+}
+
+fn parse_entry(line: &str) -> Result<Option<EntryType>> {
+    if line.trim().is_empty() || line.starts_with(COMMENT_PREFIX) {
+        return Ok(None);
+    }
+    let (entry_type, pattern) = if let Some(rest) = line.strip_prefix('[') {
+        rest.split_once(']')
+            .ok_or_else(|| anyhow!("Unterminated type in line '{}'", line))?
+    } else {
+        match line.split_once(':') {
+            Some((entry_type @ ("glob" | "regex" | "prefix"), pattern)) => (entry_type, pattern),
+            _ => ("prefix", line),
+        }
+    };
+    match entry_type {
+        "glob" => Ok(Some(EntryType::FileSystem(Regex::new(&glob_to_regex(
+            pattern,
+        )?)?))),
+        "regex" => Ok(Some(EntryType::FileSystem(Regex::new(&format!(
+            "^(?:{})$",
+            pattern
+        ))?))),
+        "prefix" => Ok(Some(EntryType::Prefix(pattern.to_string()))),
+        _ => Err(anyhow!("Unsupported definition type in line '{}'", line)),
+    }
+}
+
+/// Converts a glob to an anchored regex, following Java's glob syntax: `*` matches within a
+/// directory, `**` across directories, `?` a single character, `[...]` a character class with
+/// `!` for negation, and `{a,b}` any of the comma separated alternatives
+fn glob_to_regex(glob: &str) -> Result<String> {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    let mut in_group = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let escaped = chars
+                    .next()
+                    .ok_or_else(|| anyhow!("No character to escape in glob '{}'", glob))?;
+                regex.push_str(&regex::escape(&escaped.to_string()));
+            }
+            '*' => {
+                if chars.peek() == Some(&'*') {
+                    chars.next();
+                    regex.push_str(".*");
+                } else {
+                    regex.push_str("[^/]*");
+                }
+            }
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                regex.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    regex.push('^');
+                }
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    match c {
+                        ']' => {
+                            closed = true;
+                            break;
+                        }
+                        '/' => {
+                            return Err(anyhow!(
+                                "Explicit 'name separator' in class in glob '{}'",
+                                glob
+                            ))
+                        }
+                        '\\' | '[' | '&' | '~' | '^' => {
+                            regex.push('\\');
+                            regex.push(c);
+                        }
+                        _ => regex.push(c),
+                    }
+                }
+                if !closed {
+                    return Err(anyhow!("Missing ']' in glob '{}'", glob));
+                }
+                regex.push(']');
+            }
+            '{' if !in_group => {
+                in_group = true;
+                regex.push_str("(?:");
+            }
+            '{' => return Err(anyhow!("Cannot nest groups in glob '{}'", glob)),
+            ',' if in_group => regex.push('|'),
+            '}' if in_group => {
+                in_group = false;
+                regex.push(')');
+            }
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    if in_group {
+        return Err(anyhow!("Missing '}}' in glob '{}'", glob));
+    }
+    regex.push('$');
+    Ok(regex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allow_list(text: &str) -> PathAllowList {
+        PathAllowList::read_plain(text.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn globs_are_translated_to_anchored_regexes() {
+        assert_eq!(glob_to_regex("/mnt/*.txt").unwrap(), r"^/mnt/[^/]*\.txt$");
+        assert_eq!(glob_to_regex("/mnt/**").unwrap(), "^/mnt/.*$");
+        assert_eq!(glob_to_regex("a?c").unwrap(), "^a[^/]c$");
+        assert_eq!(glob_to_regex("[!a-c]").unwrap(), "^[^a-c]$");
+        assert_eq!(glob_to_regex("[a^]").unwrap(), r"^[a\^]$");
+        assert_eq!(glob_to_regex("{a,b}").unwrap(), "^(?:a|b)$");
+        assert_eq!(glob_to_regex(r"\*").unwrap(), r"^\*$");
+    }
+
+    #[test]
+    fn invalid_globs_are_rejected() {
+        for glob in ["[ab", "[a/b]", "{a,b", "{a,{b}}", "a\\"] {
+            assert!(glob_to_regex(glob).is_err(), "{}", glob);
+        }
+    }
+
+    #[test]
+    fn globs_match_whole_paths() {
+        let list = allow_list("[glob]/mnt/*/world\nglob:/srv/**/saves\nglob:/tmp/w?rld[0-9]");
+        assert!(list.matches(Path::new("/mnt/shared/world")));
+        assert!(!list.matches(Path::new("/mnt/shared/nested/world")));
+        assert!(!list.matches(Path::new("/mnt/shared/world2")));
+        assert!(list.matches(Path::new("/srv/a/b/saves")));
+        assert!(list.matches(Path::new("/tmp/world1")));
+        assert!(!list.matches(Path::new("/tmp/w/rld1")));
+        assert!(!list.matches(Path::new("/tmp/worldx")));
+    }
+
+    #[test]
+    fn regexes_match_whole_paths() {
+        let list = allow_list("[regex]/mnt/world[0-9]+\nregex:/srv/.*/saves");
+        assert!(list.matches(Path::new("/mnt/world12")));
+        assert!(!list.matches(Path::new("/mnt/world12/region")));
+        assert!(!list.matches(Path::new("/old/mnt/world1")));
+        assert!(list.matches(Path::new("/srv/a/saves")));
+    }
+
+    #[test]
+    fn prefixes_are_the_default_type() {
+        let list = allow_list("[prefix]/mnt/\nprefix:/srv/\n/opt/worlds");
+        assert!(list.matches(Path::new("/mnt/anything")));
+        assert!(list.matches(Path::new("/srv/anything")));
+        assert!(list.matches(Path::new("/opt/worlds2")));
+        assert!(!list.matches(Path::new("/home/worlds")));
+    }
+
+    #[test]
+    fn unknown_colon_prefixes_are_part_of_the_pattern() {
+        let list = allow_list("C:/worlds");
+        assert!(list.matches(Path::new("C:/worlds/survival")));
+    }
+
+    #[test]
+    fn comments_and_blank_lines_are_ignored() {
+        let list = allow_list("# [glob]*\n\n   \n/mnt/");
+        assert_eq!(list.entries.len(), 1);
+        assert!(!list.matches(Path::new("# [glob]*")));
+    }
+
+    #[test]
+    fn bad_lines_are_rejected() {
+        for line in [
+            "[glob/mnt",
+            "[unknown]/mnt",
+            "[glob]/mnt/[a",
+            "regex:/mnt/(",
+        ] {
+            assert!(
+                PathAllowList::read_plain(line.as_bytes()).is_err(),
+                "{}",
+                line
+            );
+        }
+    }
+}
@@ -1,12 +1,16 @@
 use crate::codec::Codec;
 use crate::nbt::compound_tag::CompoundTag;
+use crate::nbt::list_tag::ListTag;
+use crate::nbt::nbt_serde;
+use crate::nbt::tag::Tag;
 use crate::util::datafix::serialization::dynamic::Dynamic;
 use crate::world::flag::feature_flag_set::FeatureFlagSet;
+use crate::world::flag::feature_flags;
 use crate::world::level::data_pack_config::DataPackConfig;
+use itertools::Itertools;
 use serde::Serialize;
-use std::borrow::Borrow;
 
-#[derive(Debug, Default, Serialize)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct WorldDataConfiguration {
     pub datapacks: DataPackConfig,
     pub enabled_features: FeatureFlagSet,
@@ -18,19 +22,45 @@ impl WorldDataConfiguration {
             enabled_features,
         }
     }
+
+    /// Writes the fields into level data, as read by [Codec::decode]
+    pub fn write_to(&self, tag: &mut CompoundTag) -> anyhow::Result<()> {
+        tag.put(
+            "DataPacks",
+            Tag::CompoundTag(nbt_serde::to_compound(&self.datapacks)?),
+        );
+        let enabled_features = feature_flags::FEATURE_FLAGS
+            .registry
+            .to_names(&self.enabled_features)
+            .into_iter()
+            .map(|e| e.to_string())
+            .sorted()
+            .map(Tag::StringTag)
+            .collect();
+        tag.put(
+            "enabled_features",
+            Tag::ListTag(ListTag::new(enabled_features)),
+        );
+        Ok(())
+    }
 }
-impl Codec<Dynamic<CompoundTag>> for WorldDataConfiguration {
-    fn decode(data: Dynamic<CompoundTag>) -> anyhow::Result<Self> {
-        let data = data.borrow();
-        let datapacks = data
-            .value
-            .try_get_compound("DataPacks")
-            .map_or(Ok(DataPackConfig::default()), DataPackConfig::decode)?;
+impl Codec<Dynamic<Tag>> for WorldDataConfiguration {
+    fn decode(data: Dynamic<Tag>) -> anyhow::Result<Self> {
+        let datapacks = match data.get("DataPacks").result() {
+            Ok(datapacks) => match datapacks.value {
+                Tag::CompoundTag(compound) => DataPackConfig::decode(compound)?,
+                tag => return Err(anyhow::anyhow!("DataPacks is not a compound: {}", tag)),
+            },
+            Err(_) => DataPackConfig::default(),
+        };
 
-        let enabled_features = data
-            .value
-            .try_get_list("enabled_features")
-            .map_or(Ok(FeatureFlagSet::default()), FeatureFlagSet::decode)?;
+        let enabled_features = match data.get("enabled_features").result() {
+            Ok(features) => match features.value {
+                Tag::ListTag(list) => FeatureFlagSet::decode(list)?,
+                tag => return Err(anyhow::anyhow!("enabled_features is not a list: {}", tag)),
+            },
+            Err(_) => FeatureFlagSet::default(),
+        };
 
         Ok(Self {
             datapacks,